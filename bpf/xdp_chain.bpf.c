@@ -0,0 +1,26 @@
+/* SPDX-License-Identifier: (GPL-2.0-only OR BSD-2-Clause) */
+/* Copyright Authors of bpfd */
+#include <linux/bpf.h>
+#include <bpf/bpf_helpers.h>
+
+/* xdp_chain replaces the last program slot of a full XDP dispatcher and tail
+ * calls the next dispatcher of the chain, which bpfd stores at index 0 of
+ * chain_next. A tail call from a subprogram returns to the caller of the
+ * subprogram, so the verdict of the next dispatcher is handed back to the
+ * previous one like the verdict of any other program.
+ */
+struct {
+	__uint(type, BPF_MAP_TYPE_PROG_ARRAY);
+	__uint(max_entries, 1);
+	__type(key, __u32);
+	__type(value, __u32);
+} chain_next SEC(".maps");
+
+SEC("xdp")
+int xdp_chain(struct xdp_md *ctx)
+{
+	bpf_tail_call(ctx, &chain_next, 0);
+	return XDP_PASS;
+}
+
+char _license[] SEC("license") = "GPL";
@@ -1,7 +1,13 @@
 // SPDX-License-Identifier: (MIT OR Apache-2.0)
 // Copyright Authors of bpfd
 
-use std::{collections::HashMap, fs, net::SocketAddr, str};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    net::SocketAddr,
+    str,
+};
 
 use anyhow::{bail, Context};
 use base64::{engine::general_purpose, Engine as _};
@@ -9,19 +15,27 @@ use bpfd_api::{
     config::{self, Config},
     util::directories::*,
     v1::{
+        get_maps_response,
         list_response::{self, list_result::Location},
         load_request::{self, AttachInfo},
         load_request_common,
         loader_client::LoaderClient,
-        BytecodeImage, KprobeAttachInfo, ListRequest, LoadRequest, LoadRequestCommon,
-        PullBytecodeRequest, TcAttachInfo, TracepointAttachInfo, UnloadRequest, UprobeAttachInfo,
+        perf_event_attach_info::SamplePolicy,
+        replace_request, BytecodeImage, CgroupAttachInfo, FentryAttachInfo, FexitAttachInfo,
+        FlowDissectorAttachInfo, GetMapsRequest, IterAttachInfo, KprobeAttachInfo, ListRequest,
+        LoadRequest, LoadRequestCommon, LsmAttachInfo, MapDeleteRequest, MapDumpRequest, MapEntry,
+        MapLookupRequest, MapUpdateRequest, PerfEventAttachInfo, PullBytecodeRequest,
+        RawTracepointAttachInfo, ReadIteratorRequest, ReplaceRequest, SetEnabledRequest,
+        SkLookupAttachInfo, SkMsgAttachInfo, SkSkbAttachInfo, SocketFilterAttachInfo,
+        SubscribePerfEventArrayRequest, SubscribeRingBufRequest, TcAttachInfo, TpBtfAttachInfo,
+        TracepointAttachInfo, UnloadRequest, UpdateRequest, UprobeAttachInfo, WatchMapRequest,
         XdpAttachInfo,
     },
-    ImagePullPolicy,
+    CgroupAttachType, ImagePullPolicy,
     ProbeType::*,
     ProgramType, TcProceedOn, XdpProceedOn,
 };
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use comfy_table::Table;
 use hex::{encode_upper, FromHex};
 use itertools::Itertools;
@@ -45,6 +59,20 @@ enum Commands {
     LoadFromImage(LoadImageArgs),
     /// Unload an eBPF program using the UUID.
     Unload(UnloadArgs),
+    /// Change the priority or proceed-on of a loaded XDP or TC program.
+    Update(UpdateArgs),
+    /// Replace a loaded XDP or TC program with new bytecode in the same position.
+    Replace(ReplaceArgs),
+    /// Put a disabled XDP or TC program back into its dispatcher.
+    Enable {
+        /// Required: UUID of the XDP or TC program.
+        id: String,
+    },
+    /// Take an XDP or TC program out of its dispatcher, keeping it and its maps loaded.
+    Disable {
+        /// Required: UUID of the XDP or TC program.
+        id: String,
+    },
     /// List all eBPF programs loaded via bpfd.
     List(ListArgs),
     /// Get a program's metadata by kernel id.
@@ -54,6 +82,136 @@ enum Commands {
     },
     /// Pull a bytecode image for future use by a load command.
     PullBytecode(PullBytecodeArgs),
+    /// Work with loaded eBPF iterator programs.
+    Iter {
+        #[clap(subcommand)]
+        command: IterCommands,
+    },
+    /// Work with the maps of loaded eBPF programs.
+    Map {
+        #[clap(subcommand)]
+        command: MapCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum IterCommands {
+    /// Run an iterator program and print its output.
+    Read {
+        /// Required: UUID of the iterator program.
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum MapCommands {
+    /// List the maps of a program.
+    List {
+        /// Required: UUID of the program.
+        id: String,
+    },
+    /// Print the value of a key.
+    Get {
+        #[clap(flatten)]
+        map: MapArgs,
+        /// Required: The key.
+        key: String,
+        /// Optional: Print the value with --value-format, even if bpfd can decode it
+        /// with the BTF of the map.
+        #[clap(long, verbatim_doc_comment)]
+        raw: bool,
+    },
+    /// Set the value of a key.
+    Set {
+        #[clap(flatten)]
+        map: MapArgs,
+        /// Required: The key.
+        key: String,
+        /// Required: The value. A single value given for a per-CPU map is set on every CPU.
+        value: String,
+        /// Optional: Only create the key (noexist) or only change an existing key (exist).
+        #[clap(long, value_enum, default_value_t = MapUpdateFlag::Any)]
+        flag: MapUpdateFlag,
+    },
+    /// Delete keys.
+    Del {
+        #[clap(flatten)]
+        map: MapArgs,
+        /// Required: The keys.
+        #[clap(required = true)]
+        keys: Vec<String>,
+    },
+    /// Print all keys and their values.
+    Dump {
+        #[clap(flatten)]
+        map: MapArgs,
+        /// Optional: Number of entries bpfd sends at a time.
+        #[clap(long)]
+        batch_size: Option<u32>,
+        /// Optional: Print keys and values with --key-format and --value-format, even if
+        /// bpfd can decode them with the BTF of the map.
+        #[clap(long, verbatim_doc_comment)]
+        raw: bool,
+    },
+    /// Print the keys of a hash or array map that are added (+), changed (~) or removed (-).
+    Watch {
+        #[clap(flatten)]
+        map: MapArgs,
+        /// Optional: Milliseconds between samples of the map [default: 1000].
+        #[clap(long)]
+        interval: Option<u32>,
+        /// Optional: Print the values of per-CPU maps for every CPU, instead of their sum.
+        #[clap(long)]
+        per_cpu: bool,
+        /// Optional: Print keys and values with --key-format and --value-format, even if
+        /// bpfd can decode them with the BTF of the map.
+        #[clap(long, verbatim_doc_comment)]
+        raw: bool,
+    },
+    /// Print the records sent through a ringbuf or perf_event_array map, as they arrive.
+    Tail {
+        /// Required: UUID of the program.
+        id: String,
+        /// Required: Name of the map, as shown by bpfctl map list.
+        map_name: String,
+        /// Optional: Print records as text instead of hex.
+        #[clap(long)]
+        text: bool,
+    },
+}
+
+#[derive(Args)]
+struct MapArgs {
+    /// Required: UUID of the program.
+    id: String,
+
+    /// Required: Name of the map, as shown by bpfctl map list.
+    map_name: String,
+
+    /// Optional: Encoding of keys.
+    /// Integers are in host byte order and as long as the keys of the map.
+    #[clap(long, value_enum, verbatim_doc_comment, default_value_t = MapEncoding::Hex)]
+    key_format: MapEncoding,
+
+    /// Optional: Encoding of values.
+    /// Integers are in host byte order and as long as the values of the map.
+    #[clap(long, value_enum, verbatim_doc_comment, default_value_t = MapEncoding::Hex)]
+    value_format: MapEncoding,
+}
+
+#[derive(ValueEnum, Copy, Clone)]
+enum MapEncoding {
+    /// Bytes in hex, such as "0a 00 00 00".
+    Hex,
+    /// An integer, such as 10 or 0xa.
+    Int,
+}
+
+#[derive(ValueEnum, Copy, Clone)]
+enum MapUpdateFlag {
+    Any = 0,
+    Noexist = 1,
+    Exist = 2,
 }
 
 #[derive(Args)]
@@ -153,9 +311,12 @@ struct LoadImageArgs {
 enum LoadCommands {
     /// Install an eBPF program on the XDP hook point for a given interface.
     Xdp {
-        /// Required: Interface to load program on.
-        #[clap(short, long)]
-        iface: String,
+        /// Required: Interface to load program on. Multiple values, which
+        /// may be glob patterns, supported by repeating the parameter. The
+        /// program is attached to each of them, and shares its maps.
+        /// Example: --iface eth0 --iface "ens*"
+        #[clap(short, long, verbatim_doc_comment, required = true)]
+        iface: Vec<String>,
 
         /// Required: Priority to run program in chain. Lower value runs first.
         #[clap(short, long)]
@@ -170,6 +331,19 @@ enum LoadCommands {
         /// [default: pass, dispatcher_return]
         #[clap(long, verbatim_doc_comment, num_args(1..))]
         proceed_on: Vec<String>,
+
+        /// Optional: Keep the program pending until the interface exists and
+        /// is up, instead of failing the load. The program follows the
+        /// interface when it is recreated or renamed.
+        #[clap(long, verbatim_doc_comment)]
+        wait_for_iface: bool,
+
+        /// Optional: Network namespace the interface is in, as a path to it.
+        /// Example: --netns /var/run/netns/pod1
+        ///
+        /// [default: bpfd's own network namespace]
+        #[clap(long, verbatim_doc_comment)]
+        netns: Option<String>,
     },
     /// Install an eBPF program on the TC hook point for a given interface.
     Tc {
@@ -179,9 +353,12 @@ enum LoadCommands {
         #[clap(short, long, verbatim_doc_comment)]
         direction: String,
 
-        /// Required: Interface to load program on.
-        #[clap(short, long)]
-        iface: String,
+        /// Required: Interface to load program on. Multiple values, which
+        /// may be glob patterns, supported by repeating the parameter. The
+        /// program is attached to each of them, and shares its maps.
+        /// Example: --iface eth0 --iface "ens*"
+        #[clap(short, long, verbatim_doc_comment, required = true)]
+        iface: Vec<String>,
 
         /// Required: Priority to run program in chain. Lower value runs first.
         #[clap(short, long)]
@@ -197,6 +374,19 @@ enum LoadCommands {
         /// [default: ok, pipe, dispatcher_return]
         #[clap(long, verbatim_doc_comment, num_args(1..))]
         proceed_on: Vec<String>,
+
+        /// Optional: Keep the program pending until the interface exists and
+        /// is up, instead of failing the load. The program follows the
+        /// interface when it is recreated or renamed.
+        #[clap(long, verbatim_doc_comment)]
+        wait_for_iface: bool,
+
+        /// Optional: Network namespace the interface is in, as a path to it.
+        /// Example: --netns /var/run/netns/pod1
+        ///
+        /// [default: bpfd's own network namespace]
+        #[clap(long, verbatim_doc_comment)]
+        netns: Option<String>,
     },
     /// Install an eBPF program on a Tracepoint.
     Tracepoint {
@@ -259,6 +449,140 @@ enum LoadCommands {
         #[clap(short, long)]
         namespace: Option<String>,
     },
+    /// Install an eBPF fentry program
+    Fentry {
+        /// Required: Kernel function to attach the fentry program to.
+        /// The function is resolved against the kernel's BTF.
+        #[clap(short, long, verbatim_doc_comment)]
+        fn_name: String,
+    },
+    /// Install an eBPF fexit program
+    Fexit {
+        /// Required: Kernel function to attach the fexit program to.
+        /// The function is resolved against the kernel's BTF.
+        #[clap(short, long, verbatim_doc_comment)]
+        fn_name: String,
+    },
+    /// Install an eBPF LSM program
+    Lsm {
+        /// Required: LSM hook to attach the program to. The BPF LSM must be
+        /// enabled in the kernel.
+        /// Example: --hook "file_open"
+        #[clap(long, verbatim_doc_comment)]
+        hook: String,
+    },
+    /// Install an eBPF program attached to a cgroup
+    /// (cgroup_skb, cgroup_sock, cgroup_sock_addr, sock_ops, cgroup_sysctl or cgroup_device)
+    #[clap(verbatim_doc_comment)]
+    Cgroup {
+        /// Required: Path of the cgroup to attach the program to.
+        /// Example: --cgroup-path "/sys/fs/cgroup/system.slice"
+        #[clap(short, long, verbatim_doc_comment)]
+        cgroup_path: String,
+
+        /// Required: Cgroup hook to attach the program to, which also determines the program type.
+        /// Must match the section of the program.
+        #[clap(short, long, value_enum, verbatim_doc_comment)]
+        attach_type: CgroupAttachType,
+    },
+    /// Install an eBPF sk_msg program on a sockmap or sockhash
+    SkMsg {
+        /// Optional: UUID of the program which owns the sockmap or sockhash.
+        /// If not provided, the map is looked up in the maps of the program being loaded.
+        #[clap(short, long, verbatim_doc_comment)]
+        map_owner_uuid: Option<String>,
+
+        /// Required: Name of the sockmap or sockhash to attach the program to.
+        #[clap(long, verbatim_doc_comment)]
+        map_name: String,
+    },
+    /// Install an eBPF sk_skb program on a sockmap or sockhash
+    SkSkb {
+        /// Optional: UUID of the program which owns the sockmap or sockhash.
+        /// If not provided, the map is looked up in the maps of the program being loaded.
+        #[clap(short, long, verbatim_doc_comment)]
+        map_owner_uuid: Option<String>,
+
+        /// Required: Name of the sockmap or sockhash to attach the program to.
+        #[clap(long, verbatim_doc_comment)]
+        map_name: String,
+
+        /// Required: Must match the section of the program.
+        /// Possible values: [stream_parser, stream_verdict]
+        #[clap(short, long, verbatim_doc_comment)]
+        attach_type: String,
+    },
+    /// Install an eBPF program on a raw Tracepoint
+    RawTracepoint {
+        /// Required: The raw tracepoint to attach to, without its category.
+        /// Example: --tracepoint "sched_switch"
+        #[clap(short, long, verbatim_doc_comment)]
+        tracepoint: String,
+    },
+    /// Install an eBPF program on a BTF-enabled Tracepoint (tp_btf)
+    TpBtf {
+        /// Required: The tracepoint to attach to, resolved against the kernel's BTF.
+        /// Example: --tracepoint "sched_switch"
+        #[clap(short, long, verbatim_doc_comment)]
+        tracepoint: String,
+    },
+    /// Install an eBPF perf_event program on every CPU, or on a subset of CPUs or a process
+    PerfEvent {
+        /// Required: Type of the perf event.
+        /// Possible values: [hardware, software, tracepoint, hw_cache, raw, breakpoint]
+        #[clap(short, long, verbatim_doc_comment)]
+        event_type: String,
+
+        /// Required: Type specific configuration of the perf event,
+        /// for example PERF_COUNT_SW_CPU_CLOCK (0) for software events.
+        #[clap(short, long, verbatim_doc_comment)]
+        config: u64,
+
+        /// Optional: Sample every N events. Conflicts with --sample-frequency.
+        #[clap(long, verbatim_doc_comment, conflicts_with = "sample_frequency")]
+        sample_period: Option<u64>,
+
+        /// Optional: Sample N times per second. Conflicts with --sample-period.
+        #[clap(long, verbatim_doc_comment, required_unless_present = "sample_period")]
+        sample_frequency: Option<u64>,
+
+        /// Optional: CPUs to attach to. If not provided, all online CPUs are used,
+        /// unless --pid is given in which case the process is followed across CPUs.
+        /// Example: --cpus 0 1 2
+        #[clap(long, verbatim_doc_comment, num_args(1..))]
+        cpus: Option<Vec<u32>>,
+
+        /// Optional: Only sample the process with this process identification number (PID).
+        /// -1 samples any process, which is the same as leaving it out.
+        #[clap(short, long, verbatim_doc_comment, allow_negative_numbers = true)]
+        pid: Option<i32>,
+    },
+    /// Install an eBPF sk_lookup program in a network namespace
+    SkLookup {
+        /// Required: Path of the network namespace to attach the program to.
+        /// Example: --netns /var/run/netns/blue
+        #[clap(short, long, verbatim_doc_comment)]
+        netns: String,
+    },
+    /// Install an eBPF socket_filter program for applications to attach to their sockets
+    ///
+    /// The program is pinned under /run/bpfd/fs, `bpfctl get` shows the pin path.
+    SocketFilter,
+    /// Install an eBPF flow_dissector program in a network namespace
+    FlowDissector {
+        /// Required: Path of the network namespace to attach the program to.
+        /// Example: --netns /var/run/netns/blue
+        #[clap(short, long, verbatim_doc_comment)]
+        netns: String,
+    },
+    /// Install an eBPF iterator program, read it with `bpfctl iter read`
+    Iter {
+        /// Required: The kernel object the iterator walks, as in the program's
+        /// iter/<type> section name.
+        /// Example: --iter-type task
+        #[clap(short, long, verbatim_doc_comment)]
+        iter_type: String,
+    },
 }
 
 #[derive(Args)]
@@ -267,6 +591,50 @@ struct UnloadArgs {
     id: String,
 }
 
+#[derive(Args)]
+struct UpdateArgs {
+    /// Required: UUID of the XDP or TC program to be updated
+    id: String,
+
+    /// Optional: New priority to run program in chain. Lower value runs first.
+    #[clap(short, long)]
+    priority: Option<i32>,
+
+    /// Optional: New exit codes to proceed to call other programs in chain on.
+    /// Multiple values supported by repeating the parameter.
+    /// Possible values are the ones of the load command for the program type.
+    /// Example: --proceed-on "pass" --proceed-on "drop"
+    #[clap(long, verbatim_doc_comment, num_args(1..))]
+    proceed_on: Vec<String>,
+}
+
+#[derive(Args)]
+struct ReplaceArgs {
+    /// Required: UUID of the XDP or TC program to be replaced
+    id: String,
+
+    /// Optional: Keep the UUID and maps of the replaced program.
+    /// Required when other programs share its maps.
+    #[clap(short, long)]
+    keep_id: bool,
+
+    #[clap(subcommand)]
+    location: ReplaceLocation,
+}
+
+#[derive(Subcommand)]
+enum ReplaceLocation {
+    /// Use bytecode from a local .o file.
+    File {
+        /// Required: Location of local bytecode file
+        /// Example: --path /run/bpfd/examples/go-xdp-counter/bpf_bpfel.o
+        #[clap(short, long, verbatim_doc_comment)]
+        path: String,
+    },
+    /// Use bytecode packaged in a OCI container image.
+    Image(PullBytecodeArgs),
+}
+
 #[derive(Args)]
 struct PullBytecodeArgs {
     /// Required: Container Image URL.
@@ -347,7 +715,12 @@ impl ProgTable {
             r.id.unwrap_or("".to_string()),
             r.name,
             (ProgramType::try_from(r.program_type)?).to_string(),
-            r.loaded_at,
+            // Pending programs are waiting for their interface to be loaded.
+            if r.pending {
+                "pending".to_string()
+            } else {
+                r.loaded_at
+            },
         );
 
         Ok(())
@@ -360,6 +733,22 @@ impl std::fmt::Display for ProgTable {
     }
 }
 
+// Lists the interfaces a program loaded on several of them at once is on,
+// one per line, or nothing if it was loaded on a single interface.
+fn format_attachments(attachments: &[list_response::Attachment]) -> String {
+    let mut output = String::new();
+    for (i, a) in attachments.iter().enumerate() {
+        let state = if a.pending {
+            "pending".to_string()
+        } else {
+            format!("position {}", a.position)
+        };
+        let label = if i == 0 { "Attachments:" } else { "" };
+        output.push_str(&format!("\n{label:<36}{} ({state}, {})", a.iface, a.id));
+    }
+    output
+}
+
 fn print_get(r: &list_response::ListResult) -> anyhow::Result<()> {
     // if program is managed by bpfd print UUID, Location and Metadata
     let bpfd_info = if let Some(uuid) = r.clone().id {
@@ -438,6 +827,10 @@ Pull Policy:                        {}"#,
                         iface,
                         position,
                         proceed_on,
+                        frags,
+                        wait_for_iface,
+                        netns,
+                        ..
                     },
                 )) = r.clone().attach_info
                 {
@@ -445,11 +838,22 @@ Pull Policy:                        {}"#,
                         Ok(p) => p,
                         Err(e) => bail!("error parsing proceed_on {e}"),
                     };
+                    let netns = if netns.is_empty() {
+                        "None".to_string()
+                    } else {
+                        netns
+                    };
                     format!(
                         r#"Priority:                           {priority}
 Iface:                              {iface}
+Netns:                              {netns}
 Position:                           {position}
-Proceed On:                         {proc_on}"#
+Proceed On:                         {proc_on}
+Frags:                              {frags}
+Wait For Iface:                     {wait_for_iface}
+Enabled:                            {}{}"#,
+                        r.enabled,
+                        format_attachments(&r.attachments)
                     )
                 } else {
                     "".to_string()
@@ -462,6 +866,9 @@ Proceed On:                         {proc_on}"#
                     position,
                     direction,
                     proceed_on,
+                    wait_for_iface,
+                    netns,
+                    ..
                 })) = r.clone().attach_info
                 {
                     let proc_on = match TcProceedOn::from_int32s(proceed_on) {
@@ -469,12 +876,22 @@ Proceed On:                         {proc_on}"#
                         Err(e) => bail!("error parsing proceed_on {e}"),
                     };
 
+                    let netns = if netns.is_empty() {
+                        "None".to_string()
+                    } else {
+                        netns
+                    };
                     format!(
                         r#"Priority:                           {priority}
 Iface:                              {iface}
+Netns:                              {netns}
 Position:                           {position}
 Direction:                          {direction}
-Proceed On:                         {proc_on}"#
+Proceed On:                         {proc_on}
+Wait For Iface:                     {wait_for_iface}
+Enabled:                            {}{}"#,
+                        r.enabled,
+                        format_attachments(&r.attachments)
                     )
                 } else {
                     "".to_string()
@@ -545,6 +962,190 @@ Namespace:                          {namespace}"# //fn_name: {fn_name}, offset:
                     "".to_string()
                 }
             }
+            ProgramType::Tracing => match r.clone().attach_info {
+                Some(list_response::list_result::AttachInfo::FentryAttachInfo(
+                    FentryAttachInfo { fn_name },
+                )) => {
+                    format!(
+                        r#"Tracing Type:                       fentry
+Function Name:                      {fn_name}"#
+                    )
+                }
+                Some(list_response::list_result::AttachInfo::FexitAttachInfo(
+                    FexitAttachInfo { fn_name },
+                )) => {
+                    format!(
+                        r#"Tracing Type:                       fexit
+Function Name:                      {fn_name}"#
+                    )
+                }
+                Some(list_response::list_result::AttachInfo::TpBtfAttachInfo(
+                    TpBtfAttachInfo { tracepoint },
+                )) => {
+                    format!(
+                        r#"Tracing Type:                       tp_btf
+Tracepoint:                         {tracepoint}"#
+                    )
+                }
+                Some(list_response::list_result::AttachInfo::IterAttachInfo(IterAttachInfo {
+                    iter_type,
+                })) => {
+                    format!(
+                        r#"Tracing Type:                       iter
+Iterator Type:                      {iter_type}"#
+                    )
+                }
+                attach_info => {
+                    debug!(
+                        "invalid AttachInfo message for ProgramType::Tracing: {:?}",
+                        attach_info
+                    );
+                    "".to_string()
+                }
+            },
+            ProgramType::Lsm => {
+                if let Some(list_response::list_result::AttachInfo::LsmAttachInfo(
+                    LsmAttachInfo { hook },
+                )) = r.clone().attach_info
+                {
+                    format!(r#"Hook:                               {hook}"#)
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::CgroupSkb
+            | ProgramType::CgroupSock
+            | ProgramType::CgroupSockAddr
+            | ProgramType::SockOps
+            | ProgramType::CgroupSysctl
+            | ProgramType::CgroupDevice => {
+                if let Some(list_response::list_result::AttachInfo::CgroupAttachInfo(
+                    CgroupAttachInfo {
+                        cgroup_path,
+                        attach_type,
+                    },
+                )) = r.clone().attach_info
+                {
+                    format!(
+                        r#"Cgroup Path:                        {cgroup_path}
+Attach Type:                        {attach_type}"#
+                    )
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::SkMsg => {
+                if let Some(list_response::list_result::AttachInfo::SkMsgAttachInfo(
+                    SkMsgAttachInfo {
+                        map_owner_uuid,
+                        map_name,
+                    },
+                )) = r.clone().attach_info
+                {
+                    format!(
+                        r#"Map Owner UUID:                     {}
+Map Name:                           {map_name}"#,
+                        map_owner_uuid.unwrap_or("None".to_string())
+                    )
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::SkSkb => {
+                if let Some(list_response::list_result::AttachInfo::SkSkbAttachInfo(
+                    SkSkbAttachInfo {
+                        map_owner_uuid,
+                        map_name,
+                        attach_type,
+                    },
+                )) = r.clone().attach_info
+                {
+                    format!(
+                        r#"Map Owner UUID:                     {}
+Map Name:                           {map_name}
+Attach Type:                        {attach_type}"#,
+                        map_owner_uuid.unwrap_or("None".to_string())
+                    )
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::RawTracepoint => {
+                if let Some(list_response::list_result::AttachInfo::RawTracepointAttachInfo(
+                    RawTracepointAttachInfo { tracepoint },
+                )) = r.clone().attach_info
+                {
+                    format!(r#"Tracepoint:                         {tracepoint}"#)
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::PerfEvent => {
+                if let Some(list_response::list_result::AttachInfo::PerfEventAttachInfo(
+                    PerfEventAttachInfo {
+                        event_type,
+                        config,
+                        sample_policy,
+                        cpus,
+                        pid,
+                    },
+                )) = r.clone().attach_info
+                {
+                    let sample = match sample_policy {
+                        Some(SamplePolicy::SamplePeriod(p)) => format!("period {p}"),
+                        Some(SamplePolicy::SampleFrequency(f)) => format!("frequency {f}"),
+                        None => "None".to_string(),
+                    };
+                    let cpus = if cpus.is_empty() {
+                        "All".to_string()
+                    } else {
+                        cpus.iter().join(", ")
+                    };
+                    let pid = match pid {
+                        Some(p) => p.to_string(),
+                        None => "None".to_string(),
+                    };
+                    format!(
+                        r#"Event Type:                         {event_type}
+Config:                             {config}
+Sample:                             {sample}
+CPUs:                               {cpus}
+PID:                                {pid}"#
+                    )
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::SkLookup => {
+                if let Some(list_response::list_result::AttachInfo::SkLookupAttachInfo(
+                    SkLookupAttachInfo { netns },
+                )) = r.clone().attach_info
+                {
+                    format!(r#"Network Namespace:                  {netns}"#)
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::SocketFilter => {
+                if let Some(list_response::list_result::AttachInfo::SocketFilterAttachInfo(
+                    SocketFilterAttachInfo { pin_path },
+                )) = r.clone().attach_info
+                {
+                    format!(r#"Pin Path:                           {pin_path}"#)
+                } else {
+                    "".to_string()
+                }
+            }
+            ProgramType::FlowDissector => {
+                if let Some(list_response::list_result::AttachInfo::FlowDissectorAttachInfo(
+                    FlowDissectorAttachInfo { netns },
+                )) = r.clone().attach_info
+                {
+                    format!(r#"Network Namespace:                  {netns}"#)
+                } else {
+                    "".to_string()
+                }
+            }
             // skip unknown program types
             _ => {
                 bail!("program has bpfd UUID but no attach info")
@@ -619,6 +1220,19 @@ impl LoadCommands {
             LoadCommands::Tracepoint { .. } => ProgramType::Tracepoint,
             LoadCommands::Kprobe { .. } => ProgramType::Probe,
             LoadCommands::Uprobe { .. } => ProgramType::Probe,
+            LoadCommands::Fentry { .. } => ProgramType::Tracing,
+            LoadCommands::Fexit { .. } => ProgramType::Tracing,
+            LoadCommands::Lsm { .. } => ProgramType::Lsm,
+            LoadCommands::Cgroup { attach_type, .. } => attach_type.program_type(),
+            LoadCommands::SkMsg { .. } => ProgramType::SkMsg,
+            LoadCommands::SkSkb { .. } => ProgramType::SkSkb,
+            LoadCommands::SkLookup { .. } => ProgramType::SkLookup,
+            LoadCommands::RawTracepoint { .. } => ProgramType::RawTracepoint,
+            LoadCommands::TpBtf { .. } => ProgramType::Tracing,
+            LoadCommands::PerfEvent { .. } => ProgramType::PerfEvent,
+            LoadCommands::SocketFilter => ProgramType::SocketFilter,
+            LoadCommands::FlowDissector { .. } => ProgramType::FlowDissector,
+            LoadCommands::Iter { .. } => ProgramType::Tracing,
         }
     }
 
@@ -628,6 +1242,8 @@ impl LoadCommands {
                 iface,
                 priority,
                 proceed_on,
+                wait_for_iface,
+                netns,
             } => {
                 let proc_on = match XdpProceedOn::from_strings(proceed_on) {
                     Ok(p) => p,
@@ -636,9 +1252,13 @@ impl LoadCommands {
                 Ok(Some(load_request::AttachInfo::XdpAttachInfo(
                     XdpAttachInfo {
                         priority: *priority,
-                        iface: iface.to_string(),
+                        iface: iface[0].to_string(),
                         position: 0,
                         proceed_on: proc_on.as_action_vec(),
+                        frags: false,
+                        wait_for_iface: *wait_for_iface,
+                        netns: netns.clone().unwrap_or_default(),
+                        ifaces: iface[1..].to_vec(),
                     },
                 )))
             }
@@ -647,6 +1267,8 @@ impl LoadCommands {
                 iface,
                 priority,
                 proceed_on,
+                wait_for_iface,
+                netns,
             } => {
                 match direction.as_str() {
                     "ingress" | "egress" => (),
@@ -658,10 +1280,13 @@ impl LoadCommands {
                 };
                 Ok(Some(load_request::AttachInfo::TcAttachInfo(TcAttachInfo {
                     priority: *priority,
-                    iface: iface.to_string(),
+                    iface: iface[0].to_string(),
                     position: 0,
                     direction: direction.to_string(),
                     proceed_on: proc_on.as_action_vec(),
+                    wait_for_iface: *wait_for_iface,
+                    netns: netns.clone().unwrap_or_default(),
+                    ifaces: iface[1..].to_vec(),
                 })))
             }
             LoadCommands::Tracepoint { tracepoint } => Ok(Some(
@@ -711,6 +1336,105 @@ impl LoadCommands {
                     },
                 )))
             }
+            LoadCommands::Fentry { fn_name } => Ok(Some(
+                load_request::AttachInfo::FentryAttachInfo(FentryAttachInfo {
+                    fn_name: fn_name.to_string(),
+                }),
+            )),
+            LoadCommands::Fexit { fn_name } => Ok(Some(load_request::AttachInfo::FexitAttachInfo(
+                FexitAttachInfo {
+                    fn_name: fn_name.to_string(),
+                },
+            ))),
+            LoadCommands::Lsm { hook } => Ok(Some(load_request::AttachInfo::LsmAttachInfo(
+                LsmAttachInfo {
+                    hook: hook.to_string(),
+                },
+            ))),
+            LoadCommands::Cgroup {
+                cgroup_path,
+                attach_type,
+            } => Ok(Some(load_request::AttachInfo::CgroupAttachInfo(
+                CgroupAttachInfo {
+                    cgroup_path: cgroup_path.to_string(),
+                    attach_type: attach_type.to_string(),
+                },
+            ))),
+            LoadCommands::SkMsg {
+                map_owner_uuid,
+                map_name,
+            } => Ok(Some(load_request::AttachInfo::SkMsgAttachInfo(
+                SkMsgAttachInfo {
+                    map_owner_uuid: map_owner_uuid.clone(),
+                    map_name: map_name.to_string(),
+                },
+            ))),
+            LoadCommands::SkSkb {
+                map_owner_uuid,
+                map_name,
+                attach_type,
+            } => Ok(Some(load_request::AttachInfo::SkSkbAttachInfo(
+                SkSkbAttachInfo {
+                    map_owner_uuid: map_owner_uuid.clone(),
+                    map_name: map_name.to_string(),
+                    attach_type: attach_type.to_string(),
+                },
+            ))),
+            LoadCommands::RawTracepoint { tracepoint } => Ok(Some(
+                load_request::AttachInfo::RawTracepointAttachInfo(RawTracepointAttachInfo {
+                    tracepoint: tracepoint.to_string(),
+                }),
+            )),
+            LoadCommands::TpBtf { tracepoint } => Ok(Some(
+                load_request::AttachInfo::TpBtfAttachInfo(TpBtfAttachInfo {
+                    tracepoint: tracepoint.to_string(),
+                }),
+            )),
+            LoadCommands::PerfEvent {
+                event_type,
+                config,
+                sample_period,
+                sample_frequency,
+                cpus,
+                pid,
+            } => {
+                let sample_policy = match (sample_period, sample_frequency) {
+                    (Some(p), _) => SamplePolicy::SamplePeriod(*p),
+                    (None, Some(f)) => SamplePolicy::SampleFrequency(*f),
+                    (None, None) => {
+                        bail!("one of --sample-period or --sample-frequency is required")
+                    }
+                };
+                Ok(Some(load_request::AttachInfo::PerfEventAttachInfo(
+                    PerfEventAttachInfo {
+                        event_type: event_type.to_string(),
+                        config: *config,
+                        sample_policy: Some(sample_policy),
+                        cpus: cpus.clone().unwrap_or_default(),
+                        pid: *pid,
+                    },
+                )))
+            }
+            LoadCommands::SkLookup { netns } => Ok(Some(
+                load_request::AttachInfo::SkLookupAttachInfo(SkLookupAttachInfo {
+                    netns: netns.to_string(),
+                }),
+            )),
+            LoadCommands::SocketFilter => Ok(Some(
+                load_request::AttachInfo::SocketFilterAttachInfo(SocketFilterAttachInfo {
+                    pin_path: String::new(),
+                }),
+            )),
+            LoadCommands::FlowDissector { netns } => Ok(Some(
+                load_request::AttachInfo::FlowDissectorAttachInfo(FlowDissectorAttachInfo {
+                    netns: netns.to_string(),
+                }),
+            )),
+            LoadCommands::Iter { iter_type } => Ok(Some(load_request::AttachInfo::IterAttachInfo(
+                IterAttachInfo {
+                    iter_type: iter_type.to_string(),
+                },
+            ))),
         }
     }
 }
@@ -772,6 +1496,65 @@ impl Commands {
     }
 }
 
+// encode_map_bytes turns a key or value given on the command line into bytes,
+// integers taking size bytes.
+fn encode_map_bytes(s: &str, encoding: MapEncoding, size: usize) -> anyhow::Result<Vec<u8>> {
+    match encoding {
+        MapEncoding::Hex => {
+            let digits: String = s
+                .trim_start_matches("0x")
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            Vec::<u8>::from_hex(digits).with_context(|| format!("{s} is not valid hex"))
+        }
+        MapEncoding::Int => {
+            let n = match s.strip_prefix("0x") {
+                Some(h) => i128::from_str_radix(h, 16),
+                None => s.parse::<i128>(),
+            }
+            .with_context(|| format!("{s} is not an integer"))?;
+            if !matches!(size, 1 | 2 | 4 | 8) {
+                bail!("{size} byte keys and values can't be given as integers");
+            }
+            let bits = 8 * size as u32;
+            if n < -(1 << (bits - 1)) || n >= 1 << bits {
+                bail!("{s} does not fit in {size} bytes");
+            }
+            let n = n as u64;
+            Ok(match size {
+                1 => (n as u8).to_ne_bytes().to_vec(),
+                2 => (n as u16).to_ne_bytes().to_vec(),
+                4 => (n as u32).to_ne_bytes().to_vec(),
+                _ => n.to_ne_bytes().to_vec(),
+            })
+        }
+    }
+}
+
+// decode_map_bytes formats a key or value of size bytes for printing. Values
+// of per-CPU maps are printed for every CPU.
+fn decode_map_bytes(b: &[u8], encoding: MapEncoding, size: usize, per_cpu: bool) -> String {
+    if per_cpu && size > 0 {
+        let values = b
+            .chunks((size + 7) / 8 * 8)
+            .map(|c| decode_map_bytes(&c[..size.min(c.len())], encoding, size, false))
+            .join(", ");
+        return format!("[{values}]");
+    }
+    let int = match (encoding, b.len()) {
+        (MapEncoding::Int, 1) => Some(b[0] as u64),
+        (MapEncoding::Int, 2) => Some(u16::from_ne_bytes([b[0], b[1]]) as u64),
+        (MapEncoding::Int, 4) => Some(u32::from_ne_bytes(b.try_into().unwrap()) as u64),
+        (MapEncoding::Int, 8) => Some(u64::from_ne_bytes(b.try_into().unwrap())),
+        _ => None,
+    };
+    match int {
+        Some(n) => n.to_string(),
+        None => b.iter().map(|b| format!("{b:02x}")).join(" "),
+    }
+}
+
 fn parse_global_arg(global_arg: &str) -> Result<GlobalArg, std::io::Error> {
     let mut parts = global_arg.split('=');
 
@@ -920,6 +1703,71 @@ async fn execute_request(command: &Commands, channel: Channel) -> anyhow::Result
             });
             let _response = client.unload(request).await?.into_inner();
         }
+        Commands::Update(l) => {
+            // proceed_on values are named differently for XDP and TC, so the
+            // program type is needed to turn them into exit codes.
+            let proceed_on = if l.proceed_on.is_empty() {
+                vec![]
+            } else {
+                let request = tonic::Request::new(ListRequest {
+                    program_type: None,
+                    bpfd_programs_only: Some(true),
+                });
+                let response = client.list(request).await?.into_inner();
+                let prog = match response
+                    .results
+                    .into_iter()
+                    .find(|r| r.id.as_deref() == Some(l.id.as_str()))
+                {
+                    Some(p) => p,
+                    None => bail!("program {} does not exist", l.id),
+                };
+                match ProgramType::try_from(prog.program_type)? {
+                    ProgramType::Xdp => match XdpProceedOn::from_strings(&l.proceed_on) {
+                        Ok(p) => p.as_action_vec(),
+                        Err(e) => bail!("error parsing proceed_on {e}"),
+                    },
+                    ProgramType::Tc => match TcProceedOn::from_strings(&l.proceed_on) {
+                        Ok(p) => p.as_action_vec(),
+                        Err(e) => bail!("error parsing proceed_on {e}"),
+                    },
+                    other => bail!("{other} programs have no proceed-on"),
+                }
+            };
+            let request = tonic::Request::new(UpdateRequest {
+                id: l.id.to_string(),
+                priority: l.priority,
+                proceed_on,
+            });
+            let _response = client.update(request).await?.into_inner();
+        }
+        Commands::Enable { id } => {
+            let request = tonic::Request::new(SetEnabledRequest {
+                id: id.to_string(),
+                enabled: true,
+            });
+            let _response = client.set_enabled(request).await?.into_inner();
+        }
+        Commands::Disable { id } => {
+            let request = tonic::Request::new(SetEnabledRequest {
+                id: id.to_string(),
+                enabled: false,
+            });
+            let _response = client.set_enabled(request).await?.into_inner();
+        }
+        Commands::Replace(l) => {
+            let location = match &l.location {
+                ReplaceLocation::File { path } => replace_request::Location::File(path.to_string()),
+                ReplaceLocation::Image(i) => replace_request::Location::Image(i.try_into()?),
+            };
+            let request = tonic::Request::new(ReplaceRequest {
+                id: l.id.to_string(),
+                keep_id: l.keep_id,
+                location: Some(location),
+            });
+            let response = client.replace(request).await?.into_inner();
+            println!("{}", response.id);
+        }
         Commands::List(l) => {
             let prog_type_filter = l.program_type.map(|p| p as u32);
 
@@ -961,6 +1809,280 @@ async fn execute_request(command: &Commands, channel: Channel) -> anyhow::Result
 
             println!("Successfully downloaded bytecode");
         }
+        Commands::Iter {
+            command: IterCommands::Read { id },
+        } => {
+            let request = tonic::Request::new(ReadIteratorRequest { id: id.to_string() });
+            let mut stream = client.read_iterator(request).await?.into_inner();
+
+            let mut stdout = io::stdout().lock();
+            while let Some(chunk) = stream.message().await? {
+                stdout.write_all(&chunk.data)?;
+            }
+            stdout.flush()?;
+        }
+        Commands::Map {
+            command: MapCommands::List { id },
+        } => {
+            let request = tonic::Request::new(GetMapsRequest { id: id.to_string() });
+            let response = client.get_maps(request).await?.into_inner();
+
+            let mut table = Table::new();
+            table.load_preset(comfy_table::presets::NOTHING);
+            table.set_header(vec![
+                "Map ID",
+                "Name",
+                "Type",
+                "Key Size",
+                "Value Size",
+                "Max Entries",
+                "Flags",
+                "Pin Path",
+            ]);
+            for m in response.maps {
+                table.add_row(vec![
+                    m.map_id.to_string(),
+                    m.name,
+                    m.map_type,
+                    m.key_size.to_string(),
+                    m.value_size.to_string(),
+                    m.max_entries.to_string(),
+                    format!("{:#x}", m.flags),
+                    m.pin_path,
+                ]);
+            }
+            println!("{table}");
+        }
+        Commands::Map {
+            command: MapCommands::Get { map, key, raw },
+        } => {
+            let info = get_map_info(&mut client, &map.id, &map.map_name).await?;
+            let request = tonic::Request::new(MapLookupRequest {
+                id: map.id.to_string(),
+                map_name: map.map_name.to_string(),
+                key: encode_map_bytes(key, map.key_format, info.key_size as usize)?,
+            });
+            let response = client.map_lookup(request).await?.into_inner();
+            if !raw && !response.value_json.is_empty() {
+                println!("{}", response.value_json);
+            } else {
+                println!(
+                    "{}",
+                    decode_map_bytes(
+                        &response.value,
+                        map.value_format,
+                        info.value_size as usize,
+                        info.map_type.contains("percpu")
+                    )
+                );
+            }
+        }
+        Commands::Map {
+            command:
+                MapCommands::Set {
+                    map,
+                    key,
+                    value,
+                    flag,
+                },
+        } => {
+            let info = get_map_info(&mut client, &map.id, &map.map_name).await?;
+            let request = tonic::Request::new(MapUpdateRequest {
+                id: map.id.to_string(),
+                map_name: map.map_name.to_string(),
+                entries: vec![MapEntry {
+                    key: encode_map_bytes(key, map.key_format, info.key_size as usize)?,
+                    value: encode_map_bytes(value, map.value_format, info.value_size as usize)?,
+                    ..Default::default()
+                }],
+                flags: *flag as u64,
+            });
+            let _response = client.map_update(request).await?.into_inner();
+        }
+        Commands::Map {
+            command: MapCommands::Del { map, keys },
+        } => {
+            let info = get_map_info(&mut client, &map.id, &map.map_name).await?;
+            let keys = keys
+                .iter()
+                .map(|k| encode_map_bytes(k, map.key_format, info.key_size as usize))
+                .collect::<Result<Vec<_>, _>>()?;
+            let request = tonic::Request::new(MapDeleteRequest {
+                id: map.id.to_string(),
+                map_name: map.map_name.to_string(),
+                keys,
+            });
+            let _response = client.map_delete(request).await?.into_inner();
+        }
+        Commands::Map {
+            command:
+                MapCommands::Dump {
+                    map,
+                    batch_size,
+                    raw,
+                },
+        } => {
+            let info = get_map_info(&mut client, &map.id, &map.map_name).await?;
+            let request = tonic::Request::new(MapDumpRequest {
+                id: map.id.to_string(),
+                map_name: map.map_name.to_string(),
+                batch_size: batch_size.unwrap_or_default(),
+            });
+            let mut stream = client.map_dump(request).await?.into_inner();
+
+            // Maps with BTF are printed as a JSON array, like bpftool does.
+            let mut json = false;
+            while let Some(batch) = stream.message().await? {
+                for entry in batch.entries {
+                    if !raw && !entry.value_json.is_empty() {
+                        println!(
+                            "{}{{\"key\": {}, \"value\": {}}}",
+                            if json { "," } else { "[" },
+                            entry.key_json,
+                            entry.value_json
+                        );
+                        json = true;
+                        continue;
+                    }
+                    println!(
+                        "key: {}  value: {}",
+                        decode_map_bytes(&entry.key, map.key_format, info.key_size as usize, false),
+                        decode_map_bytes(
+                            &entry.value,
+                            map.value_format,
+                            info.value_size as usize,
+                            info.map_type.contains("percpu")
+                        )
+                    );
+                }
+            }
+            if json {
+                println!("]");
+            }
+        }
+        Commands::Map {
+            command:
+                MapCommands::Watch {
+                    map,
+                    interval,
+                    per_cpu,
+                    raw,
+                },
+        } => {
+            let info = get_map_info(&mut client, &map.id, &map.map_name).await?;
+            let request = tonic::Request::new(WatchMapRequest {
+                id: map.id.to_string(),
+                map_name: map.map_name.to_string(),
+                interval_ms: interval.unwrap_or_default(),
+                per_cpu: *per_cpu,
+            });
+            let mut stream = client.watch_map(request).await?.into_inner();
+
+            // Summed values are as long as the values of other maps.
+            let per_cpu = *per_cpu && info.map_type.contains("percpu");
+            let format = |entry: &MapEntry| {
+                if !raw && !entry.value_json.is_empty() {
+                    return format!(
+                        "{{\"key\": {}, \"value\": {}}}",
+                        entry.key_json, entry.value_json
+                    );
+                }
+                format!(
+                    "key: {}  value: {}",
+                    decode_map_bytes(&entry.key, map.key_format, info.key_size as usize, false),
+                    decode_map_bytes(
+                        &entry.value,
+                        map.value_format,
+                        info.value_size as usize,
+                        per_cpu
+                    )
+                )
+            };
+            while let Some(delta) = stream.message().await? {
+                let mut stdout = io::stdout().lock();
+                for entry in &delta.added {
+                    writeln!(stdout, "+ {}", format(entry))?;
+                }
+                for entry in &delta.changed {
+                    writeln!(stdout, "~ {}", format(entry))?;
+                }
+                for entry in &delta.removed {
+                    writeln!(stdout, "- {}", format(entry))?;
+                }
+                stdout.flush()?;
+            }
+        }
+        Commands::Map {
+            command: MapCommands::Tail { id, map_name, text },
+        } => {
+            let info = get_map_info(&mut client, id, map_name).await?;
+            match info.map_type.as_str() {
+                "ringbuf" => {
+                    let request = tonic::Request::new(SubscribeRingBufRequest {
+                        id: id.to_string(),
+                        map_name: map_name.to_string(),
+                    });
+                    let mut stream = client.subscribe_ring_buf(request).await?.into_inner();
+                    while let Some(batch) = stream.message().await? {
+                        print_records(&batch.records, *text, batch.dropped, 0)?;
+                    }
+                }
+                "perf_event_array" => {
+                    let request = tonic::Request::new(SubscribePerfEventArrayRequest {
+                        id: id.to_string(),
+                        map_name: map_name.to_string(),
+                    });
+                    let mut stream = client
+                        .subscribe_perf_event_array(request)
+                        .await?
+                        .into_inner();
+                    while let Some(batch) = stream.message().await? {
+                        print_records(&batch.records, *text, batch.dropped, batch.lost)?;
+                    }
+                }
+                t => bail!("can't tail {t} maps, only ringbuf and perf_event_array maps"),
+            }
+        }
     }
     Ok(())
 }
+
+// print_records prints the records of a map tail one per line, and the
+// records that were missed on stderr.
+fn print_records(records: &[Vec<u8>], text: bool, dropped: u64, lost: u64) -> io::Result<()> {
+    if dropped > 0 {
+        eprintln!("dropped {dropped} records, bpfctl didn't keep up");
+    }
+    if lost > 0 {
+        eprintln!("lost {lost} records, a perf buffer was full");
+    }
+    let mut stdout = io::stdout().lock();
+    for record in records {
+        if text {
+            let end = record.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            writeln!(stdout, "{}", String::from_utf8_lossy(&record[..end]))?;
+        } else {
+            writeln!(
+                stdout,
+                "{}",
+                decode_map_bytes(record, MapEncoding::Hex, record.len(), false)
+            )?;
+        }
+    }
+    stdout.flush()
+}
+
+// get_map_info returns the map of a map command, whose key and value sizes
+// are needed to encode integers.
+async fn get_map_info(
+    client: &mut LoaderClient<Channel>,
+    id: &str,
+    map_name: &str,
+) -> anyhow::Result<get_maps_response::MapInfo> {
+    let request = tonic::Request::new(GetMapsRequest { id: id.to_string() });
+    let response = client.get_maps(request).await?.into_inner();
+    match response.maps.into_iter().find(|m| m.name == map_name) {
+        Some(m) => Ok(m),
+        None => bail!("program {id} has no map {map_name}"),
+    }
+}
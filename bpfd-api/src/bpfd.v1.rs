@@ -57,6 +57,23 @@ pub struct XdpAttachInfo {
     pub position: i32,
     #[prost(int32, repeated, tag = "4")]
     pub proceed_on: ::prost::alloc::vec::Vec<i32>,
+    /// Set by bpfd for programs in an xdp.frags section, ignored on load.
+    #[prost(bool, tag = "5")]
+    pub frags: bool,
+    /// Keep the program until iface exists and is up, instead of failing
+    /// the load, and follow iface when it is recreated or renamed.
+    #[prost(bool, tag = "6")]
+    pub wait_for_iface: bool,
+    /// Path to the network namespace iface is in, e.g. /var/run/netns/<name>
+    /// or /proc/<pid>/ns/net. Empty for bpfd's own namespace.
+    #[prost(string, tag = "7")]
+    pub netns: ::prost::alloc::string::String,
+    /// Further interfaces to attach the program to along with iface, which
+    /// may be glob patterns like eth*, matched against the interfaces that
+    /// exist at load time. Every interface gets an attachment of its own,
+    /// which all share the maps of the program. Only used on load.
+    #[prost(string, repeated, tag = "8")]
+    pub ifaces: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -71,6 +88,20 @@ pub struct TcAttachInfo {
     pub direction: ::prost::alloc::string::String,
     #[prost(int32, repeated, tag = "5")]
     pub proceed_on: ::prost::alloc::vec::Vec<i32>,
+    /// Keep the program until iface exists and is up, instead of failing
+    /// the load, and follow iface when it is recreated or renamed.
+    #[prost(bool, tag = "6")]
+    pub wait_for_iface: bool,
+    /// Path to the network namespace iface is in, e.g. /var/run/netns/<name>
+    /// or /proc/<pid>/ns/net. Empty for bpfd's own namespace.
+    #[prost(string, tag = "7")]
+    pub netns: ::prost::alloc::string::String,
+    /// Further interfaces to attach the program to along with iface, which
+    /// may be glob patterns like eth*, matched against the interfaces that
+    /// exist at load time. Every interface gets an attachment of its own,
+    /// which all share the maps of the program. Only used on load.
+    #[prost(string, repeated, tag = "8")]
+    pub ifaces: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -108,10 +139,118 @@ pub struct UprobeAttachInfo {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FentryAttachInfo {
+    #[prost(string, tag = "1")]
+    pub fn_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FexitAttachInfo {
+    #[prost(string, tag = "1")]
+    pub fn_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LsmAttachInfo {
+    #[prost(string, tag = "1")]
+    pub hook: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CgroupAttachInfo {
+    #[prost(string, tag = "1")]
+    pub cgroup_path: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub attach_type: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SkMsgAttachInfo {
+    #[prost(string, optional, tag = "1")]
+    pub map_owner_uuid: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SkSkbAttachInfo {
+    #[prost(string, optional, tag = "1")]
+    pub map_owner_uuid: ::core::option::Option<::prost::alloc::string::String>,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub attach_type: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SkLookupAttachInfo {
+    #[prost(string, tag = "1")]
+    pub netns: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RawTracepointAttachInfo {
+    #[prost(string, tag = "1")]
+    pub tracepoint: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TpBtfAttachInfo {
+    #[prost(string, tag = "1")]
+    pub tracepoint: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PerfEventAttachInfo {
+    #[prost(string, tag = "1")]
+    pub event_type: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "2")]
+    pub config: u64,
+    #[prost(uint32, repeated, tag = "5")]
+    pub cpus: ::prost::alloc::vec::Vec<u32>,
+    #[prost(int32, optional, tag = "6")]
+    pub pid: ::core::option::Option<i32>,
+    #[prost(oneof = "perf_event_attach_info::SamplePolicy", tags = "3, 4")]
+    pub sample_policy: ::core::option::Option<perf_event_attach_info::SamplePolicy>,
+}
+/// Nested message and enum types in `PerfEventAttachInfo`.
+pub mod perf_event_attach_info {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum SamplePolicy {
+        #[prost(uint64, tag = "3")]
+        SamplePeriod(u64),
+        #[prost(uint64, tag = "4")]
+        SampleFrequency(u64),
+    }
+}
+/// Socket filter programs are only loaded and pinned by bpfd, applications
+/// attach them to their own sockets from pin_path. pin_path is set by bpfd and
+/// ignored on load.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SocketFilterAttachInfo {
+    #[prost(string, tag = "1")]
+    pub pin_path: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct FlowDissectorAttachInfo {
+    #[prost(string, tag = "1")]
+    pub netns: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct IterAttachInfo {
+    #[prost(string, tag = "1")]
+    pub iter_type: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct LoadRequest {
     #[prost(message, optional, tag = "1")]
     pub common: ::core::option::Option<LoadRequestCommon>,
-    #[prost(oneof = "load_request::AttachInfo", tags = "2, 3, 4, 5, 6")]
+    #[prost(oneof = "load_request::AttachInfo", tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 19, 20")]
     pub attach_info: ::core::option::Option<load_request::AttachInfo>,
 }
 /// Nested message and enum types in `LoadRequest`.
@@ -129,6 +268,32 @@ pub mod load_request {
         KprobeAttachInfo(super::KprobeAttachInfo),
         #[prost(message, tag = "6")]
         UprobeAttachInfo(super::UprobeAttachInfo),
+        #[prost(message, tag = "7")]
+        FentryAttachInfo(super::FentryAttachInfo),
+        #[prost(message, tag = "8")]
+        FexitAttachInfo(super::FexitAttachInfo),
+        #[prost(message, tag = "9")]
+        LsmAttachInfo(super::LsmAttachInfo),
+        #[prost(message, tag = "10")]
+        CgroupAttachInfo(super::CgroupAttachInfo),
+        #[prost(message, tag = "11")]
+        SkMsgAttachInfo(super::SkMsgAttachInfo),
+        #[prost(message, tag = "12")]
+        SkSkbAttachInfo(super::SkSkbAttachInfo),
+        #[prost(message, tag = "13")]
+        SkLookupAttachInfo(super::SkLookupAttachInfo),
+        #[prost(message, tag = "14")]
+        RawTracepointAttachInfo(super::RawTracepointAttachInfo),
+        #[prost(message, tag = "15")]
+        TpBtfAttachInfo(super::TpBtfAttachInfo),
+        #[prost(message, tag = "16")]
+        PerfEventAttachInfo(super::PerfEventAttachInfo),
+        #[prost(message, tag = "18")]
+        SocketFilterAttachInfo(super::SocketFilterAttachInfo),
+        #[prost(message, tag = "19")]
+        FlowDissectorAttachInfo(super::FlowDissectorAttachInfo),
+        #[prost(message, tag = "20")]
+        IterAttachInfo(super::IterAttachInfo),
     }
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
@@ -162,6 +327,22 @@ pub struct ListResponse {
 }
 /// Nested message and enum types in `ListResponse`.
 pub mod list_response {
+    /// One interface an XDP or TC program was loaded on, when it was loaded on
+    /// several of them at once.
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct Attachment {
+        #[prost(string, tag = "1")]
+        pub id: ::prost::alloc::string::String,
+        #[prost(string, tag = "2")]
+        pub iface: ::prost::alloc::string::String,
+        #[prost(uint32, tag = "3")]
+        pub bpf_id: u32,
+        #[prost(int32, tag = "4")]
+        pub position: i32,
+        #[prost(bool, tag = "5")]
+        pub pending: bool,
+    }
     #[allow(clippy::derive_partial_eq_without_eq)]
     #[derive(Clone, PartialEq, ::prost::Message)]
     pub struct ListResult {
@@ -204,9 +385,21 @@ pub mod list_response {
         pub bytes_memlock: u32,
         #[prost(uint32, tag = "27")]
         pub verified_insns: u32,
+        /// False for XDP and TC programs that are loaded, but left out of
+        /// their dispatcher.
+        #[prost(bool, tag = "43")]
+        pub enabled: bool,
+        /// True for XDP and TC programs that are waiting for their interface,
+        /// they are not loaded in the kernel yet.
+        #[prost(bool, tag = "44")]
+        pub pending: bool,
+        /// Every interface the program was loaded on, the first is the program
+        /// itself. Empty for programs loaded on a single interface.
+        #[prost(message, repeated, tag = "45")]
+        pub attachments: ::prost::alloc::vec::Vec<Attachment>,
         #[prost(oneof = "list_result::Location", tags = "3, 4, 5")]
         pub location: ::core::option::Option<list_result::Location>,
-        #[prost(oneof = "list_result::AttachInfo", tags = "11, 12, 13, 14, 15, 16")]
+        #[prost(oneof = "list_result::AttachInfo", tags = "11, 12, 13, 14, 15, 16, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 40, 41, 42")]
         pub attach_info: ::core::option::Option<list_result::AttachInfo>,
     }
     /// Nested message and enum types in `ListResult`.
@@ -236,6 +429,32 @@ pub mod list_response {
             KprobeAttachInfo(super::super::KprobeAttachInfo),
             #[prost(message, tag = "16")]
             UprobeAttachInfo(super::super::UprobeAttachInfo),
+            #[prost(message, tag = "29")]
+            FentryAttachInfo(super::super::FentryAttachInfo),
+            #[prost(message, tag = "30")]
+            FexitAttachInfo(super::super::FexitAttachInfo),
+            #[prost(message, tag = "31")]
+            LsmAttachInfo(super::super::LsmAttachInfo),
+            #[prost(message, tag = "32")]
+            CgroupAttachInfo(super::super::CgroupAttachInfo),
+            #[prost(message, tag = "33")]
+            SkMsgAttachInfo(super::super::SkMsgAttachInfo),
+            #[prost(message, tag = "34")]
+            SkSkbAttachInfo(super::super::SkSkbAttachInfo),
+            #[prost(message, tag = "35")]
+            SkLookupAttachInfo(super::super::SkLookupAttachInfo),
+            #[prost(message, tag = "36")]
+            RawTracepointAttachInfo(super::super::RawTracepointAttachInfo),
+            #[prost(message, tag = "37")]
+            TpBtfAttachInfo(super::super::TpBtfAttachInfo),
+            #[prost(message, tag = "38")]
+            PerfEventAttachInfo(super::super::PerfEventAttachInfo),
+            #[prost(message, tag = "40")]
+            SocketFilterAttachInfo(super::super::SocketFilterAttachInfo),
+            #[prost(message, tag = "41")]
+            FlowDissectorAttachInfo(super::super::FlowDissectorAttachInfo),
+            #[prost(message, tag = "42")]
+            IterAttachInfo(super::super::IterAttachInfo),
         }
     }
 }
@@ -248,6 +467,268 @@ pub struct PullBytecodeRequest {
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct PullBytecodeResponse {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReadIteratorRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+}
+/// The output of an iterator is streamed back in chunks, in the order it was
+/// read from the kernel.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReadIteratorResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(int32, optional, tag = "2")]
+    pub priority: ::core::option::Option<i32>,
+    /// Left unchanged when empty.
+    #[prost(int32, repeated, tag = "3")]
+    pub proceed_on: ::prost::alloc::vec::Vec<i32>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateResponse {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplaceRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    /// Keep the UUID and map pin path of the replaced program. Required when
+    /// other programs share the maps of the replaced program.
+    #[prost(bool, tag = "4")]
+    pub keep_id: bool,
+    #[prost(oneof = "replace_request::Location", tags = "2, 3")]
+    pub location: ::core::option::Option<replace_request::Location>,
+}
+/// Nested message and enum types in `ReplaceRequest`.
+pub mod replace_request {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Location {
+        #[prost(message, tag = "2")]
+        Image(super::BytecodeImage),
+        #[prost(string, tag = "3")]
+        File(::prost::alloc::string::String),
+    }
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ReplaceResponse {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetEnabledRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(bool, tag = "2")]
+    pub enabled: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SetEnabledResponse {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetMapsRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetMapsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub maps: ::prost::alloc::vec::Vec<get_maps_response::MapInfo>,
+}
+/// Nested message and enum types in `GetMapsResponse`.
+pub mod get_maps_response {
+    #[allow(clippy::derive_partial_eq_without_eq)]
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MapInfo {
+        #[prost(string, tag = "1")]
+        pub name: ::prost::alloc::string::String,
+        #[prost(string, tag = "2")]
+        pub map_type: ::prost::alloc::string::String,
+        #[prost(uint32, tag = "3")]
+        pub key_size: u32,
+        #[prost(uint32, tag = "4")]
+        pub value_size: u32,
+        #[prost(uint32, tag = "5")]
+        pub max_entries: u32,
+        #[prost(uint32, tag = "6")]
+        pub flags: u32,
+        #[prost(string, tag = "7")]
+        pub pin_path: ::prost::alloc::string::String,
+        #[prost(uint32, tag = "8")]
+        pub map_id: u32,
+    }
+}
+/// Values of per-CPU maps hold a value, padded to 8 bytes, for every
+/// possible CPU.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapEntry {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: ::prost::alloc::vec::Vec<u8>,
+    /// The key and value decoded into JSON, set by bpfd for maps whose BTF is
+    /// known.
+    #[prost(string, tag = "3")]
+    pub key_json: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub value_json: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapLookupRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "3")]
+    pub key: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapLookupResponse {
+    #[prost(bytes = "vec", tag = "1")]
+    pub value: ::prost::alloc::vec::Vec<u8>,
+    /// The value decoded into JSON, if the BTF of the map is known.
+    #[prost(string, tag = "2")]
+    pub value_json: ::prost::alloc::string::String,
+}
+/// Entries are written one at a time, in order, so an update is not atomic:
+/// when an entry can't be written, the entries before it stay written and the
+/// error tells how many those are.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapUpdateRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+    #[prost(message, repeated, tag = "3")]
+    pub entries: ::prost::alloc::vec::Vec<MapEntry>,
+    /// BPF_ANY (0), BPF_NOEXIST (1) or BPF_EXIST (2).
+    #[prost(uint64, tag = "4")]
+    pub flags: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapUpdateResponse {
+    /// The number of entries that were written.
+    #[prost(uint32, tag = "1")]
+    pub updated: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapDeleteRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", repeated, tag = "3")]
+    pub keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapDeleteResponse {
+    /// The number of requested keys that were found and deleted.
+    #[prost(uint32, tag = "1")]
+    pub deleted: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapDumpRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+    /// Entries per response, 256 if not set.
+    #[prost(uint32, tag = "3")]
+    pub batch_size: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MapDumpResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub entries: ::prost::alloc::vec::Vec<MapEntry>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRingBufRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribeRingBufResponse {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub records: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// Records this subscriber missed since the previous response, because it
+    /// didn't read them fast enough.
+    #[prost(uint64, tag = "2")]
+    pub dropped: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribePerfEventArrayRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SubscribePerfEventArrayResponse {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub records: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// Records this subscriber missed since the previous response, because it
+    /// didn't read them fast enough.
+    #[prost(uint64, tag = "2")]
+    pub dropped: u64,
+    /// Records the kernel could not write since the previous response,
+    /// because a perf buffer was full.
+    #[prost(uint64, tag = "3")]
+    pub lost: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchMapRequest {
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub map_name: ::prost::alloc::string::String,
+    /// Milliseconds between samples of the map, 1000 if not set.
+    #[prost(uint32, tag = "3")]
+    pub interval_ms: u32,
+    /// Send the values of per-CPU maps for every CPU, instead of their sum.
+    #[prost(bool, tag = "4")]
+    pub per_cpu: bool,
+}
+/// The first response holds every entry of the map as added, later ones are
+/// only sent when the map changed.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct WatchMapResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub added: ::prost::alloc::vec::Vec<MapEntry>,
+    #[prost(message, repeated, tag = "2")]
+    pub changed: ::prost::alloc::vec::Vec<MapEntry>,
+    /// The entries that are gone, with the last value seen.
+    #[prost(message, repeated, tag = "3")]
+    pub removed: ::prost::alloc::vec::Vec<MapEntry>,
+}
 /// Generated client implementations.
 pub mod loader_client {
     #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
@@ -415,61 +896,475 @@ pub mod loader_client {
                 .insert(GrpcMethod::new("bpfd.v1.Loader", "PullBytecode"));
             self.inner.unary(req, path, codec).await
         }
-    }
-}
-/// Generated server implementations.
-pub mod loader_server {
-    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
-    use tonic::codegen::*;
-    /// Generated trait containing gRPC methods that should be implemented for use with LoaderServer.
-    #[async_trait]
-    pub trait Loader: Send + Sync + 'static {
-        async fn load(
-            &self,
-            request: tonic::Request<super::LoadRequest>,
-        ) -> std::result::Result<tonic::Response<super::LoadResponse>, tonic::Status>;
-        async fn unload(
-            &self,
-            request: tonic::Request<super::UnloadRequest>,
-        ) -> std::result::Result<tonic::Response<super::UnloadResponse>, tonic::Status>;
-        async fn list(
-            &self,
-            request: tonic::Request<super::ListRequest>,
-        ) -> std::result::Result<tonic::Response<super::ListResponse>, tonic::Status>;
-        async fn pull_bytecode(
-            &self,
-            request: tonic::Request<super::PullBytecodeRequest>,
+        pub async fn read_iterator(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReadIteratorRequest>,
         ) -> std::result::Result<
-            tonic::Response<super::PullBytecodeResponse>,
+            tonic::Response<tonic::codec::Streaming<super::ReadIteratorResponse>>,
             tonic::Status,
-        >;
-    }
-    #[derive(Debug)]
-    pub struct LoaderServer<T: Loader> {
-        inner: _Inner<T>,
-        accept_compression_encodings: EnabledCompressionEncodings,
-        send_compression_encodings: EnabledCompressionEncodings,
-        max_decoding_message_size: Option<usize>,
-        max_encoding_message_size: Option<usize>,
-    }
-    struct _Inner<T>(Arc<T>);
-    impl<T: Loader> LoaderServer<T> {
-        pub fn new(inner: T) -> Self {
-            Self::from_arc(Arc::new(inner))
-        }
-        pub fn from_arc(inner: Arc<T>) -> Self {
-            let inner = _Inner(inner);
-            Self {
-                inner,
-                accept_compression_encodings: Default::default(),
-                send_compression_encodings: Default::default(),
-                max_decoding_message_size: None,
-                max_encoding_message_size: None,
-            }
-        }
-        pub fn with_interceptor<F>(
-            inner: T,
-            interceptor: F,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/ReadIterator",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "ReadIterator"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn update(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/Update",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "Update"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn replace(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ReplaceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReplaceResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/Replace",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "Replace"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn set_enabled(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SetEnabledRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SetEnabledResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/SetEnabled",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "SetEnabled"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn get_maps(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetMapsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetMapsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/GetMaps",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "GetMaps"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn map_lookup(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MapLookupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MapLookupResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/MapLookup",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "MapLookup"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn map_update(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MapUpdateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MapUpdateResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/MapUpdate",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "MapUpdate"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn map_delete(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MapDeleteRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MapDeleteResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/MapDelete",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "MapDelete"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn map_dump(
+            &mut self,
+            request: impl tonic::IntoRequest<super::MapDumpRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::MapDumpResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/MapDump",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "MapDump"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn subscribe_ring_buf(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribeRingBufRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SubscribeRingBufResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/SubscribeRingBuf",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "SubscribeRingBuf"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn subscribe_perf_event_array(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SubscribePerfEventArrayRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::SubscribePerfEventArrayResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/SubscribePerfEventArray",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "SubscribePerfEventArray"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+        pub async fn watch_map(
+            &mut self,
+            request: impl tonic::IntoRequest<super::WatchMapRequest>,
+        ) -> std::result::Result<
+            tonic::Response<tonic::codec::Streaming<super::WatchMapResponse>>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/bpfd.v1.Loader/WatchMap",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("bpfd.v1.Loader", "WatchMap"));
+            self.inner.server_streaming(req, path, codec).await
+        }
+    }
+}
+/// Generated server implementations.
+pub mod loader_server {
+    #![allow(unused_variables, dead_code, missing_docs, clippy::let_unit_value)]
+    use tonic::codegen::*;
+    /// Generated trait containing gRPC methods that should be implemented for use with LoaderServer.
+    #[async_trait]
+    pub trait Loader: Send + Sync + 'static {
+        async fn load(
+            &self,
+            request: tonic::Request<super::LoadRequest>,
+        ) -> std::result::Result<tonic::Response<super::LoadResponse>, tonic::Status>;
+        async fn unload(
+            &self,
+            request: tonic::Request<super::UnloadRequest>,
+        ) -> std::result::Result<tonic::Response<super::UnloadResponse>, tonic::Status>;
+        async fn list(
+            &self,
+            request: tonic::Request<super::ListRequest>,
+        ) -> std::result::Result<tonic::Response<super::ListResponse>, tonic::Status>;
+        async fn pull_bytecode(
+            &self,
+            request: tonic::Request<super::PullBytecodeRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::PullBytecodeResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the ReadIterator method.
+        type ReadIteratorStream: futures_core::Stream<
+                Item = std::result::Result<super::ReadIteratorResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn read_iterator(
+            &self,
+            request: tonic::Request<super::ReadIteratorRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::ReadIteratorStream>,
+            tonic::Status,
+        >;
+        async fn update(
+            &self,
+            request: tonic::Request<super::UpdateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::UpdateResponse>,
+            tonic::Status,
+        >;
+        async fn replace(
+            &self,
+            request: tonic::Request<super::ReplaceRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ReplaceResponse>,
+            tonic::Status,
+        >;
+        async fn set_enabled(
+            &self,
+            request: tonic::Request<super::SetEnabledRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::SetEnabledResponse>,
+            tonic::Status,
+        >;
+        async fn get_maps(
+            &self,
+            request: tonic::Request<super::GetMapsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::GetMapsResponse>,
+            tonic::Status,
+        >;
+        async fn map_lookup(
+            &self,
+            request: tonic::Request<super::MapLookupRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MapLookupResponse>,
+            tonic::Status,
+        >;
+        async fn map_update(
+            &self,
+            request: tonic::Request<super::MapUpdateRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MapUpdateResponse>,
+            tonic::Status,
+        >;
+        async fn map_delete(
+            &self,
+            request: tonic::Request<super::MapDeleteRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::MapDeleteResponse>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the MapDump method.
+        type MapDumpStream: futures_core::Stream<
+                Item = std::result::Result<super::MapDumpResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn map_dump(
+            &self,
+            request: tonic::Request<super::MapDumpRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::MapDumpStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the SubscribeRingBuf method.
+        type SubscribeRingBufStream: futures_core::Stream<
+                Item = std::result::Result<super::SubscribeRingBufResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn subscribe_ring_buf(
+            &self,
+            request: tonic::Request<super::SubscribeRingBufRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribeRingBufStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the SubscribePerfEventArray method.
+        type SubscribePerfEventArrayStream: futures_core::Stream<
+                Item = std::result::Result<super::SubscribePerfEventArrayResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn subscribe_perf_event_array(
+            &self,
+            request: tonic::Request<super::SubscribePerfEventArrayRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::SubscribePerfEventArrayStream>,
+            tonic::Status,
+        >;
+        /// Server streaming response type for the WatchMap method.
+        type WatchMapStream: futures_core::Stream<
+                Item = std::result::Result<super::WatchMapResponse, tonic::Status>,
+            >
+            + Send
+            + 'static;
+        async fn watch_map(
+            &self,
+            request: tonic::Request<super::WatchMapRequest>,
+        ) -> std::result::Result<
+            tonic::Response<Self::WatchMapStream>,
+            tonic::Status,
+        >;
+    }
+    #[derive(Debug)]
+    pub struct LoaderServer<T: Loader> {
+        inner: _Inner<T>,
+        accept_compression_encodings: EnabledCompressionEncodings,
+        send_compression_encodings: EnabledCompressionEncodings,
+        max_decoding_message_size: Option<usize>,
+        max_encoding_message_size: Option<usize>,
+    }
+    struct _Inner<T>(Arc<T>);
+    impl<T: Loader> LoaderServer<T> {
+        pub fn new(inner: T) -> Self {
+            Self::from_arc(Arc::new(inner))
+        }
+        pub fn from_arc(inner: Arc<T>) -> Self {
+            let inner = _Inner(inner);
+            Self {
+                inner,
+                accept_compression_encodings: Default::default(),
+                send_compression_encodings: Default::default(),
+                max_decoding_message_size: None,
+                max_encoding_message_size: None,
+            }
+        }
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
         ) -> InterceptedService<Self, F>
         where
             F: tonic::service::Interceptor,
@@ -525,20 +1420,335 @@ pub mod loader_server {
             match req.uri().path() {
                 "/bpfd.v1.Loader/Load" => {
                     #[allow(non_camel_case_types)]
-                    struct LoadSvc<T: Loader>(pub Arc<T>);
-                    impl<T: Loader> tonic::server::UnaryService<super::LoadRequest>
-                    for LoadSvc<T> {
-                        type Response = super::LoadResponse;
+                    struct LoadSvc<T: Loader>(pub Arc<T>);
+                    impl<T: Loader> tonic::server::UnaryService<super::LoadRequest>
+                    for LoadSvc<T> {
+                        type Response = super::LoadResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::LoadRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { (*inner).load(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = LoadSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/Unload" => {
+                    #[allow(non_camel_case_types)]
+                    struct UnloadSvc<T: Loader>(pub Arc<T>);
+                    impl<T: Loader> tonic::server::UnaryService<super::UnloadRequest>
+                    for UnloadSvc<T> {
+                        type Response = super::UnloadResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UnloadRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { (*inner).unload(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UnloadSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/List" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListSvc<T: Loader>(pub Arc<T>);
+                    impl<T: Loader> tonic::server::UnaryService<super::ListRequest>
+                    for ListSvc<T> {
+                        type Response = super::ListResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move { (*inner).list(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ListSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/PullBytecode" => {
+                    #[allow(non_camel_case_types)]
+                    struct PullBytecodeSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::PullBytecodeRequest>
+                    for PullBytecodeSvc<T> {
+                        type Response = super::PullBytecodeResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::PullBytecodeRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).pull_bytecode(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = PullBytecodeSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/ReadIterator" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReadIteratorSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::ServerStreamingService<super::ReadIteratorRequest>
+                    for ReadIteratorSvc<T> {
+                        type Response = super::ReadIteratorResponse;
+                        type ResponseStream = T::ReadIteratorStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReadIteratorRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).read_iterator(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReadIteratorSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/Update" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::UpdateRequest>
+                    for UpdateSvc<T> {
+                        type Response = super::UpdateResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).update(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = UpdateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/Replace" => {
+                    #[allow(non_camel_case_types)]
+                    struct ReplaceSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::ReplaceRequest>
+                    for ReplaceSvc<T> {
+                        type Response = super::ReplaceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ReplaceRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).replace(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = ReplaceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/SetEnabled" => {
+                    #[allow(non_camel_case_types)]
+                    struct SetEnabledSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::SetEnabledRequest>
+                    for SetEnabledSvc<T> {
+                        type Response = super::SetEnabledResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::LoadRequest>,
+                            request: tonic::Request<super::SetEnabledRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move { (*inner).load(request).await };
+                            let fut = async move {
+                                (*inner).set_enabled(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -549,7 +1759,7 @@ pub mod loader_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = LoadSvc(inner);
+                        let method = SetEnabledSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -565,22 +1775,26 @@ pub mod loader_server {
                     };
                     Box::pin(fut)
                 }
-                "/bpfd.v1.Loader/Unload" => {
+                "/bpfd.v1.Loader/GetMaps" => {
                     #[allow(non_camel_case_types)]
-                    struct UnloadSvc<T: Loader>(pub Arc<T>);
-                    impl<T: Loader> tonic::server::UnaryService<super::UnloadRequest>
-                    for UnloadSvc<T> {
-                        type Response = super::UnloadResponse;
+                    struct GetMapsSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::GetMapsRequest>
+                    for GetMapsSvc<T> {
+                        type Response = super::GetMapsResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::UnloadRequest>,
+                            request: tonic::Request<super::GetMapsRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move { (*inner).unload(request).await };
+                            let fut = async move {
+                                (*inner).get_maps(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -591,7 +1805,7 @@ pub mod loader_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = UnloadSvc(inner);
+                        let method = GetMapsSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -607,22 +1821,26 @@ pub mod loader_server {
                     };
                     Box::pin(fut)
                 }
-                "/bpfd.v1.Loader/List" => {
+                "/bpfd.v1.Loader/MapLookup" => {
                     #[allow(non_camel_case_types)]
-                    struct ListSvc<T: Loader>(pub Arc<T>);
-                    impl<T: Loader> tonic::server::UnaryService<super::ListRequest>
-                    for ListSvc<T> {
-                        type Response = super::ListResponse;
+                    struct MapLookupSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::MapLookupRequest>
+                    for MapLookupSvc<T> {
+                        type Response = super::MapLookupResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::ListRequest>,
+                            request: tonic::Request<super::MapLookupRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move { (*inner).list(request).await };
+                            let fut = async move {
+                                (*inner).map_lookup(request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -633,7 +1851,7 @@ pub mod loader_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = ListSvc(inner);
+                        let method = MapLookupSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -649,25 +1867,25 @@ pub mod loader_server {
                     };
                     Box::pin(fut)
                 }
-                "/bpfd.v1.Loader/PullBytecode" => {
+                "/bpfd.v1.Loader/MapUpdate" => {
                     #[allow(non_camel_case_types)]
-                    struct PullBytecodeSvc<T: Loader>(pub Arc<T>);
+                    struct MapUpdateSvc<T: Loader>(pub Arc<T>);
                     impl<
                         T: Loader,
-                    > tonic::server::UnaryService<super::PullBytecodeRequest>
-                    for PullBytecodeSvc<T> {
-                        type Response = super::PullBytecodeResponse;
+                    > tonic::server::UnaryService<super::MapUpdateRequest>
+                    for MapUpdateSvc<T> {
+                        type Response = super::MapUpdateResponse;
                         type Future = BoxFuture<
                             tonic::Response<Self::Response>,
                             tonic::Status,
                         >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<super::PullBytecodeRequest>,
+                            request: tonic::Request<super::MapUpdateRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
                             let fut = async move {
-                                (*inner).pull_bytecode(request).await
+                                (*inner).map_update(request).await
                             };
                             Box::pin(fut)
                         }
@@ -679,7 +1897,53 @@ pub mod loader_server {
                     let inner = self.inner.clone();
                     let fut = async move {
                         let inner = inner.0;
-                        let method = PullBytecodeSvc(inner);
+                        let method = MapUpdateSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/MapDelete" => {
+                    #[allow(non_camel_case_types)]
+                    struct MapDeleteSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::UnaryService<super::MapDeleteRequest>
+                    for MapDeleteSvc<T> {
+                        type Response = super::MapDeleteResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MapDeleteRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).map_delete(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = MapDeleteSvc(inner);
                         let codec = tonic::codec::ProstCodec::default();
                         let mut grpc = tonic::server::Grpc::new(codec)
                             .apply_compression_config(
@@ -695,6 +1959,194 @@ pub mod loader_server {
                     };
                     Box::pin(fut)
                 }
+                "/bpfd.v1.Loader/MapDump" => {
+                    #[allow(non_camel_case_types)]
+                    struct MapDumpSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::ServerStreamingService<super::MapDumpRequest>
+                    for MapDumpSvc<T> {
+                        type Response = super::MapDumpResponse;
+                        type ResponseStream = T::MapDumpStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::MapDumpRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).map_dump(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = MapDumpSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/SubscribeRingBuf" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribeRingBufSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::ServerStreamingService<super::SubscribeRingBufRequest>
+                    for SubscribeRingBufSvc<T> {
+                        type Response = super::SubscribeRingBufResponse;
+                        type ResponseStream = T::SubscribeRingBufStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribeRingBufRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).subscribe_ring_buf(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SubscribeRingBufSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/SubscribePerfEventArray" => {
+                    #[allow(non_camel_case_types)]
+                    struct SubscribePerfEventArraySvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::ServerStreamingService<super::SubscribePerfEventArrayRequest>
+                    for SubscribePerfEventArraySvc<T> {
+                        type Response = super::SubscribePerfEventArrayResponse;
+                        type ResponseStream = T::SubscribePerfEventArrayStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SubscribePerfEventArrayRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).subscribe_perf_event_array(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = SubscribePerfEventArraySvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/bpfd.v1.Loader/WatchMap" => {
+                    #[allow(non_camel_case_types)]
+                    struct WatchMapSvc<T: Loader>(pub Arc<T>);
+                    impl<
+                        T: Loader,
+                    > tonic::server::ServerStreamingService<super::WatchMapRequest>
+                    for WatchMapSvc<T> {
+                        type Response = super::WatchMapResponse;
+                        type ResponseStream = T::WatchMapStream;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::WatchMapRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).watch_map(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = WatchMapSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.server_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -7,7 +7,12 @@ use aya::programs::XdpFlags;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::util::directories::*;
+use crate::{util::directories::*, ImagePullPolicy};
+
+/// The XDP dispatcher image bpfd uses unless another one is configured.
+pub const XDP_DISPATCHER_IMAGE: &str = "quay.io/bpfd/xdp-dispatcher:v3";
+/// The TC dispatcher image bpfd uses unless another one is configured.
+pub const TC_DISPATCHER_IMAGE: &str = "quay.io/bpfd/tc-dispatcher:v1";
 
 #[derive(Debug, Deserialize, Default, Clone)]
 pub struct Config {
@@ -16,6 +21,10 @@ pub struct Config {
     pub interfaces: Option<HashMap<String, InterfaceConfig>>,
     #[serde(default)]
     pub grpc: Grpc,
+    #[serde(default)]
+    pub reconcile: Reconcile,
+    #[serde(default)]
+    pub dispatchers: Dispatchers,
 }
 
 #[derive(Debug, Error)]
@@ -80,12 +89,16 @@ fn default_client_key() -> String {
 
 #[derive(Debug, Deserialize, Copy, Clone)]
 pub struct InterfaceConfig {
+    #[serde(default)]
     pub xdp_mode: XdpMode,
+    #[serde(default)]
+    pub tc_mode: TcMode,
 }
 
-#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum XdpMode {
+    #[default]
     Skb,
     Drv,
     Hw,
@@ -111,6 +124,26 @@ impl ToString for XdpMode {
     }
 }
 
+/// TcMode selects how TC programs are attached to an interface. The
+/// dispatcher works on any kernel with clsact support, tcx links need
+/// Linux 6.6 or newer.
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TcMode {
+    #[default]
+    Dispatcher,
+    Tcx,
+}
+
+impl ToString for TcMode {
+    fn to_string(&self) -> String {
+        match self {
+            TcMode::Dispatcher => "dispatcher".to_string(),
+            TcMode::Tcx => "tcx".to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Grpc {
     #[serde(default)]
@@ -125,6 +158,50 @@ impl Default for Grpc {
     }
 }
 
+/// Reconcile sets how often bpfd checks that its dispatchers are still
+/// attached in the kernel.
+#[derive(Debug, Deserialize, Clone)]
+pub struct Reconcile {
+    /// Seconds between checks, 0 turns the checks off.
+    #[serde(default = "default_reconcile_interval")]
+    pub interval: u64,
+}
+
+impl Default for Reconcile {
+    fn default() -> Self {
+        Self {
+            interval: default_reconcile_interval(),
+        }
+    }
+}
+
+fn default_reconcile_interval() -> u64 {
+    10
+}
+
+/// Dispatchers sets where bpfd gets the bytecode of the XDP and TC
+/// dispatchers from.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Dispatchers {
+    #[serde(default)]
+    pub xdp: DispatcherSource,
+    #[serde(default)]
+    pub tc: DispatcherSource,
+}
+
+/// DispatcherSource is a bytecode image or a local object file, path wins
+/// if both are set. Unset fields keep the defaults of each dispatcher: the
+/// XDP dispatcher image is pulled if it is not present, and the TC
+/// dispatcher image is never pulled. With the Never pull policy, the
+/// dispatcher bundled with bpfd is used when the image is not in the local
+/// image store.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DispatcherSource {
+    pub image: Option<String>,
+    pub pull_policy: Option<ImagePullPolicy>,
+    pub path: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum Endpoint {
@@ -203,6 +280,48 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_config_reconcile_interval() {
+        let config = Config::from_str("").expect("error parsing toml input");
+        assert_eq!(config.reconcile.interval, 10);
+
+        let input = r#"
+        [reconcile]
+        interval = 0
+        "#;
+        let config: Config = toml::from_str(input).expect("error parsing toml input");
+        assert_eq!(config.reconcile.interval, 0);
+    }
+
+    #[test]
+    fn test_config_dispatchers() {
+        let config = Config::from_str("").expect("error parsing toml input");
+        assert!(config.dispatchers.xdp.image.is_none());
+        assert!(config.dispatchers.tc.pull_policy.is_none());
+
+        let input = r#"
+        [dispatchers.xdp]
+        image = "registry.local/bpfd/xdp-dispatcher:v2"
+        pull_policy = "Never"
+        [dispatchers.tc]
+        path = "/usr/lib/bpfd/tc_dispatcher.bpf.o"
+        "#;
+        let config: Config = toml::from_str(input).expect("error parsing toml input");
+        assert_eq!(
+            config.dispatchers.xdp.image.as_deref(),
+            Some("registry.local/bpfd/xdp-dispatcher:v2")
+        );
+        assert!(matches!(
+            config.dispatchers.xdp.pull_policy,
+            Some(ImagePullPolicy::Never)
+        ));
+        assert_eq!(
+            config.dispatchers.tc.path.as_deref(),
+            Some("/usr/lib/bpfd/tc_dispatcher.bpf.o")
+        );
+        assert!(config.dispatchers.tc.image.is_none());
+    }
+
     #[test]
     fn test_config_multiple_iface() {
         let input = r#"
@@ -236,6 +355,24 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_config_tc_mode() {
+        let input = r#"
+        [interfaces]
+          [interfaces.eth0]
+          tc_mode = "tcx"
+          [interfaces.eth1]
+          xdp_mode = "drv"
+        "#;
+        let config: Config = toml::from_str(input).expect("error parsing toml input");
+        let i = config
+            .interfaces
+            .expect("expected interfaces to be present");
+        assert_eq!(i.get("eth0").unwrap().tc_mode, TcMode::Tcx);
+        assert_eq!(i.get("eth0").unwrap().xdp_mode, XdpMode::Skb);
+        assert_eq!(i.get("eth1").unwrap().tc_mode, TcMode::Dispatcher);
+    }
+
     #[test]
     fn test_config_tls() {
         let input = r#"
@@ -29,6 +29,12 @@ pub enum ParseError {
     InvalidBytecodeImagePullPolicy { pull_policy: String },
     #[error("{probe} is not a valid probe type")]
     InvalidProbeType { probe: String },
+    #[error("{attach_type} is not a valid cgroup attach type")]
+    InvalidCgroupAttachType { attach_type: String },
+    #[error("{attach_type} is not a valid sk_skb attach type")]
+    InvalidSkSkbAttachType { attach_type: String },
+    #[error("{event_type} is not a valid perf event type")]
+    InvalidPerfEventType { event_type: String },
 }
 
 #[derive(ValueEnum, Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
@@ -248,6 +254,129 @@ impl std::fmt::Display for ProbeType {
     }
 }
 
+#[derive(ValueEnum, Copy, Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum CgroupAttachType {
+    // cgroup_skb
+    Ingress,
+    Egress,
+    // cgroup_sock
+    SockCreate,
+    SockRelease,
+    PostBind4,
+    PostBind6,
+    // cgroup_sock_addr
+    Bind4,
+    Bind6,
+    Connect4,
+    Connect6,
+    GetPeerName4,
+    GetPeerName6,
+    GetSockName4,
+    GetSockName6,
+    UdpSendMsg4,
+    UdpSendMsg6,
+    UdpRecvMsg4,
+    UdpRecvMsg6,
+    // sock_ops
+    SockOps,
+    // cgroup_sysctl
+    Sysctl,
+    // cgroup_device
+    Device,
+}
+
+impl CgroupAttachType {
+    /// The program type which is able to attach at this cgroup hook.
+    pub fn program_type(&self) -> ProgramType {
+        match self {
+            CgroupAttachType::Ingress | CgroupAttachType::Egress => ProgramType::CgroupSkb,
+            CgroupAttachType::SockCreate
+            | CgroupAttachType::SockRelease
+            | CgroupAttachType::PostBind4
+            | CgroupAttachType::PostBind6 => ProgramType::CgroupSock,
+            CgroupAttachType::Bind4
+            | CgroupAttachType::Bind6
+            | CgroupAttachType::Connect4
+            | CgroupAttachType::Connect6
+            | CgroupAttachType::GetPeerName4
+            | CgroupAttachType::GetPeerName6
+            | CgroupAttachType::GetSockName4
+            | CgroupAttachType::GetSockName6
+            | CgroupAttachType::UdpSendMsg4
+            | CgroupAttachType::UdpSendMsg6
+            | CgroupAttachType::UdpRecvMsg4
+            | CgroupAttachType::UdpRecvMsg6 => ProgramType::CgroupSockAddr,
+            CgroupAttachType::SockOps => ProgramType::SockOps,
+            CgroupAttachType::Sysctl => ProgramType::CgroupSysctl,
+            CgroupAttachType::Device => ProgramType::CgroupDevice,
+        }
+    }
+}
+
+impl TryFrom<String> for CgroupAttachType {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Ok(match value.as_str() {
+            "ingress" => CgroupAttachType::Ingress,
+            "egress" => CgroupAttachType::Egress,
+            "sock_create" => CgroupAttachType::SockCreate,
+            "sock_release" => CgroupAttachType::SockRelease,
+            "post_bind4" => CgroupAttachType::PostBind4,
+            "post_bind6" => CgroupAttachType::PostBind6,
+            "bind4" => CgroupAttachType::Bind4,
+            "bind6" => CgroupAttachType::Bind6,
+            "connect4" => CgroupAttachType::Connect4,
+            "connect6" => CgroupAttachType::Connect6,
+            "getpeername4" => CgroupAttachType::GetPeerName4,
+            "getpeername6" => CgroupAttachType::GetPeerName6,
+            "getsockname4" => CgroupAttachType::GetSockName4,
+            "getsockname6" => CgroupAttachType::GetSockName6,
+            "sendmsg4" => CgroupAttachType::UdpSendMsg4,
+            "sendmsg6" => CgroupAttachType::UdpSendMsg6,
+            "recvmsg4" => CgroupAttachType::UdpRecvMsg4,
+            "recvmsg6" => CgroupAttachType::UdpRecvMsg6,
+            "sock_ops" => CgroupAttachType::SockOps,
+            "sysctl" => CgroupAttachType::Sysctl,
+            "device" => CgroupAttachType::Device,
+            other => {
+                return Err(ParseError::InvalidCgroupAttachType {
+                    attach_type: other.to_string(),
+                })
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for CgroupAttachType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let v = match self {
+            CgroupAttachType::Ingress => "ingress",
+            CgroupAttachType::Egress => "egress",
+            CgroupAttachType::SockCreate => "sock_create",
+            CgroupAttachType::SockRelease => "sock_release",
+            CgroupAttachType::PostBind4 => "post_bind4",
+            CgroupAttachType::PostBind6 => "post_bind6",
+            CgroupAttachType::Bind4 => "bind4",
+            CgroupAttachType::Bind6 => "bind6",
+            CgroupAttachType::Connect4 => "connect4",
+            CgroupAttachType::Connect6 => "connect6",
+            CgroupAttachType::GetPeerName4 => "getpeername4",
+            CgroupAttachType::GetPeerName6 => "getpeername6",
+            CgroupAttachType::GetSockName4 => "getsockname4",
+            CgroupAttachType::GetSockName6 => "getsockname6",
+            CgroupAttachType::UdpSendMsg4 => "sendmsg4",
+            CgroupAttachType::UdpSendMsg6 => "sendmsg6",
+            CgroupAttachType::UdpRecvMsg4 => "recvmsg4",
+            CgroupAttachType::UdpRecvMsg6 => "recvmsg6",
+            CgroupAttachType::SockOps => "sock_ops",
+            CgroupAttachType::Sysctl => "sysctl",
+            CgroupAttachType::Device => "device",
+        };
+        write!(f, "{v}")
+    }
+}
+
 #[derive(Serialize, Deserialize, Copy, Clone, Debug)]
 pub enum XdpProceedOnEntry {
     Aborted,
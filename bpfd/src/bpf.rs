@@ -2,43 +2,100 @@
 // Copyright Authors of bpfd
 
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     convert::TryInto,
+    ffi::OsStr,
+    os::fd::{AsFd, AsRawFd, BorrowedFd},
     path::{Path, PathBuf},
+    pin::Pin,
+    time::Duration,
 };
 
 use anyhow::anyhow;
 use aya::{
+    maps::{MapData, MapType},
     programs::{
-        kprobe::KProbeLink, links::FdLink, loaded_programs, trace_point::TracePointLink,
-        uprobe::UProbeLink, KProbe, TracePoint, UProbe,
+        cgroup_skb::CgroupSkbAttachType,
+        cgroup_sock::CgroupSockAttachType,
+        cgroup_sock_addr::CgroupSockAddrAttachType,
+        fentry::FEntryLink,
+        fexit::FExitLink,
+        iter::IterLink,
+        kprobe::KProbeLink,
+        links::FdLink,
+        links::LinkOrder,
+        loaded_programs,
+        lsm::LsmLink,
+        perf_event::{PerfEventScope, PerfTypeId, SamplePolicy},
+        raw_trace_point::RawTracePointLink,
+        tc::TcAttachOptions,
+        tp_btf::BtfTracePointLink,
+        trace_point::TracePointLink,
+        uprobe::UProbeLink,
+        BtfTracePoint, CgroupAttachMode, CgroupDevice, CgroupSkb, CgroupSock, CgroupSockAddr,
+        CgroupSysctl, FEntry, FExit, FlowDissector, Iter, KProbe, Lsm, PerfEvent, ProgramError,
+        RawTracePoint, SchedClassifier, SkLookup, SkMsg, SkSkb, SkSkbKind, SockOps, SocketFilter,
+        TcAttachType, TracePoint, UProbe,
     },
-    BpfLoader,
+    util::online_cpus,
+    Btf, EbpfLoader,
 };
 use bpfd_api::{
-    config::Config,
+    config::{Config, TcMode},
     util::directories::*,
+    CgroupAttachType,
     ProbeType::{self, *},
-    ProgramType,
+    ProgramType, TcProceedOn, XdpProceedOn,
 };
-use log::{debug, info};
-use tokio::{fs, select, sync::mpsc};
+use log::{debug, info, warn};
+use nix::{libc, net::if_::if_nametoindex};
+use tokio::{
+    fs, select,
+    sync::mpsc,
+    time::{interval, MissedTickBehavior},
+};
+use tokio_stream::{Stream, StreamExt};
 use uuid::Uuid;
 
 use crate::{
     command::{
-        BpfMap, Command, Direction,
+        BpfMap, CgroupProgram, Command, Direction,
         Direction::{Egress, Ingress},
-        Program, PullBytecodeArgs, UnloadArgs,
+        Location, PerfEventProgram, PerfEventSamplePolicy, PerfEventType, Program,
+        PullBytecodeArgs, SkSkbAttachType, TcProgram, UnloadArgs,
     },
     errors::BpfdError,
-    multiprog::{Dispatcher, DispatcherId, DispatcherInfo, TcDispatcher, XdpDispatcher},
+    maps::{pinned_maps, MapInfo, PinnedMap},
+    multiprog::{
+        xdp_has_frags, Dispatcher, DispatcherId, DispatcherInfo, TcDispatcher, XdpDispatcher,
+        MAX_PROGRAMS,
+    },
+    netlink::{self, LinkEvent},
+    netns::in_netns,
     serve::shutdown_handler,
-    utils::{get_ifindex, set_dir_permissions},
+    utils::{
+        bpf_lsm_enabled, expand_ifaces, get_ifindex, section_name, set_dir_permissions, sys_bpf,
+    },
 };
 
 const MAPS_MODE: u32 = 0o0660;
 
+const BPF_PROG_ATTACH: libc::c_int = 8;
+const BPF_PROG_DETACH: libc::c_int = 9;
+const BPF_SK_SKB_STREAM_PARSER: u32 = 4;
+const BPF_SK_SKB_STREAM_VERDICT: u32 = 5;
+const BPF_SK_MSG_VERDICT: u32 = 7;
+
+// The part of bpf_attr used by BPF_PROG_ATTACH and BPF_PROG_DETACH.
+#[repr(C)]
+#[allow(dead_code)]
+struct ProgAttachAttr {
+    target_fd: u32,
+    attach_bpf_fd: u32,
+    attach_type: u32,
+    attach_flags: u32,
+}
+
 pub(crate) struct BpfManager {
     config: Config,
     dispatchers: HashMap<DispatcherId, Dispatcher>,
@@ -66,7 +123,35 @@ impl BpfManager {
             let mut program = Program::load(uuid)
                 .map_err(|e| BpfdError::Error(format!("cant read program state {e}")))?;
             // TODO: Should probably check for pinned prog on bpffs rather than assuming they are attached
-            program.set_attached();
+            // Programs waiting for their interface were never loaded.
+            if !program.pending() {
+                program.set_attached();
+            }
+            // Cgroup, flow_dissector and tcx programs only stay attached for as
+            // long as their link is pinned, and iterators are read through it.
+            // Cgroup programs are attached again if the link went away while
+            // bpfd was not running, warn about the others.
+            if (matches!(
+                program,
+                Program::Cgroup(_) | Program::FlowDissector(_) | Program::Iter(_)
+            ) || program.tcx())
+                && !Path::new(&format!("{RTDIR_FS}/prog_{uuid}_link")).exists()
+            {
+                match program {
+                    Program::Cgroup(ref p) => match reattach_cgroup_program(p, uuid) {
+                        Ok(()) => info!("reattached cgroup program {uuid} to {}", p.cgroup_path),
+                        Err(e) => warn!("unable to reattach cgroup program {uuid}: {e}"),
+                    },
+                    _ => warn!("link for program {uuid} is no longer pinned, program is detached"),
+                }
+            }
+            // Applications open socket_filter programs from their pin, so it
+            // has to still be there for the program to be of any use.
+            if let Program::SocketFilter(_) = program {
+                if !Path::new(&format!("{RTDIR_FS}/prog_{uuid}")).exists() {
+                    warn!("socket filter program {uuid} is no longer pinned");
+                }
+            }
             debug!("rebuilding state for program {}", uuid);
             self.rebuild_map_entry(uuid, program.data()?.map_owner_id());
             self.programs.insert(uuid, program);
@@ -87,43 +172,57 @@ impl BpfManager {
         direction: Option<Direction>,
         path: &str,
     ) -> Result<(), anyhow::Error> {
+        // Dispatchers in other network namespaces are in a netns_<id>
+        // directory of their own.
+        let mut dirs = vec![(PathBuf::from(path), None)];
         let mut dispatcher_dir = fs::read_dir(path).await?;
         while let Some(entry) = dispatcher_dir.next_entry().await? {
             let name = entry.file_name();
-            let parts: Vec<&str> = name.to_str().unwrap().split('_').collect();
-            if parts.len() != 2 {
-                continue;
+            if let Some(id) = name.to_str().and_then(|n| n.strip_prefix("netns_")) {
+                dirs.push((entry.path(), Some(id.parse::<u64>()?)));
             }
-            let if_index: u32 = parts[0].parse().unwrap();
-            let revision: u32 = parts[1].parse().unwrap();
-            match program_type {
-                ProgramType::Xdp => {
-                    let dispatcher = XdpDispatcher::load(if_index, revision).unwrap();
-                    self.dispatchers.insert(
-                        DispatcherId::Xdp(DispatcherInfo(if_index, None)),
-                        Dispatcher::Xdp(dispatcher),
-                    );
-                }
-                ProgramType::Tc => {
-                    if let Some(dir) = direction {
-                        let dispatcher = TcDispatcher::load(if_index, dir, revision).unwrap();
+        }
+
+        for (path, netns) in dirs {
+            let mut dispatcher_dir = fs::read_dir(path).await?;
+            while let Some(entry) = dispatcher_dir.next_entry().await? {
+                let name = entry.file_name();
+                let parts: Vec<&str> = name.to_str().unwrap().split('_').collect();
+                if parts.len() != 2 || parts[0] == "netns" {
+                    continue;
+                }
+                let if_index: u32 = parts[0].parse().unwrap();
+                let revision: u32 = parts[1].parse().unwrap();
+                match program_type {
+                    ProgramType::Xdp => {
+                        let dispatcher = XdpDispatcher::load(if_index, revision, netns).unwrap();
                         self.dispatchers.insert(
-                            DispatcherId::Tc(DispatcherInfo(if_index, direction)),
-                            Dispatcher::Tc(dispatcher),
+                            DispatcherId::Xdp(DispatcherInfo(if_index, None, netns)),
+                            Dispatcher::Xdp(dispatcher),
                         );
-                    } else {
-                        return Err(anyhow!("direction required for tc programs"));
                     }
+                    ProgramType::Tc => {
+                        if let Some(dir) = direction {
+                            let dispatcher =
+                                TcDispatcher::load(if_index, dir, revision, netns).unwrap();
+                            self.dispatchers.insert(
+                                DispatcherId::Tc(DispatcherInfo(if_index, direction, netns)),
+                                Dispatcher::Tc(dispatcher),
+                            );
+                        } else {
+                            return Err(anyhow!("direction required for tc programs"));
+                        }
 
-                    self.rebuild_multiattach_dispatcher(
-                        program_type,
-                        if_index,
-                        direction,
-                        DispatcherId::Tc(DispatcherInfo(if_index, direction)),
-                    )
-                    .await?;
+                        self.rebuild_multiattach_dispatcher(
+                            program_type,
+                            if_index,
+                            direction,
+                            DispatcherId::Tc(DispatcherInfo(if_index, direction, netns)),
+                        )
+                        .await?;
+                    }
+                    _ => return Err(anyhow!("invalid program type {:?}", program_type)),
                 }
-                _ => return Err(anyhow!("invalid program type {:?}", program_type)),
             }
         }
 
@@ -133,6 +232,13 @@ impl BpfManager {
     pub(crate) async fn add_program(&mut self, mut program: Program) -> Result<Uuid, BpfdError> {
         debug!("BpfManager::add_program()");
 
+        // Link events are only followed in bpfd's own network namespace.
+        if program.wait_for_iface() && program.netns().is_some() {
+            return Err(BpfdError::Error(
+                "wait_for_iface is only supported in bpfd's own network namespace".to_string(),
+            ));
+        }
+
         let uuid = match program.data()?.id() {
             Some(id) => {
                 debug!("Using provided program UUID: {}", id);
@@ -158,13 +264,37 @@ impl BpfManager {
 
         let result = match program {
             Program::Xdp(_) | Program::Tc(_) => {
-                program.set_if_index(get_ifindex(&program.if_name().unwrap())?);
-
-                self.add_multi_attach_program(program, uuid).await
-            }
-            Program::Tracepoint(_) | Program::Kprobe(_) | Program::Uprobe(_) => {
-                self.add_single_attach_program(program, uuid).await
+                let if_name = program.if_name().unwrap();
+                if program.wait_for_iface() {
+                    match iface_up(&if_name).await {
+                        Some(if_index) => {
+                            program.set_if_index(if_index);
+                            self.add_network_program(program, uuid).await
+                        }
+                        None => self.add_pending_program(program, uuid).await,
+                    }
+                } else {
+                    let if_index = in_netns(program.netns(), || get_ifindex(&if_name))??;
+                    program.set_if_index(if_index);
+                    self.add_network_program(program, uuid).await
+                }
             }
+            Program::Tracepoint(_)
+            | Program::Kprobe(_)
+            | Program::Uprobe(_)
+            | Program::Fentry(_)
+            | Program::Fexit(_)
+            | Program::Lsm(_)
+            | Program::Cgroup(_)
+            | Program::SkMsg(_)
+            | Program::SkSkb(_)
+            | Program::SkLookup(_)
+            | Program::RawTracepoint(_)
+            | Program::TpBtf(_)
+            | Program::PerfEvent(_)
+            | Program::SocketFilter(_)
+            | Program::FlowDissector(_)
+            | Program::Iter(_) => self.add_single_attach_program(program, uuid).await,
             Program::Unsupported(_) => panic!("Cannot add unsupported program"),
         };
 
@@ -179,6 +309,103 @@ impl BpfManager {
         result
     }
 
+    // add_program_to_ifaces loads an XDP or TC program on its own iface and on
+    // ifaces, which may be glob patterns. The first interface gets the program
+    // whose id is returned, every other one a member of its group, which uses
+    // its maps. If any of them fails, the whole group is unloaded again.
+    pub(crate) async fn add_program_to_ifaces(
+        &mut self,
+        program: Program,
+        ifaces: Vec<String>,
+    ) -> Result<Uuid, BpfdError> {
+        if ifaces.is_empty() {
+            return self.add_program(program).await;
+        }
+        debug!("BpfManager::add_program_to_ifaces() {ifaces:?}");
+        if !matches!(program, Program::Xdp(_) | Program::Tc(_)) {
+            return Err(BpfdError::Error(format!(
+                "{} programs can only be attached to one interface",
+                program.kind()
+            )));
+        }
+
+        let mut patterns: Vec<String> = program.if_name().into_iter().collect();
+        patterns.retain(|p| !p.is_empty());
+        patterns.extend(ifaces);
+        let mut if_names = in_netns(program.netns(), || expand_ifaces(&patterns))??.into_iter();
+
+        let mut primary = program.clone();
+        primary.set_if_name(if_names.next().expect("at least one interface matches"));
+        let id = self.add_program(primary).await?;
+
+        for if_name in if_names {
+            let mut member = program.clone();
+            member.set_if_name(if_name.clone());
+            member.set_group(Some(id));
+            let data = member.data_mut()?;
+            data.set_id(None);
+            if data.map_owner_id().is_none() {
+                data.set_map_owner_id(Some(id));
+            }
+            if let Err(e) = self.add_program(member).await {
+                warn!("unable to attach program {id} to {if_name}: {e}");
+                if let Err(e) = self.remove_program(id).await {
+                    warn!("unable to unload program {id}: {e}");
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(id)
+    }
+
+    // group_ids returns id, followed by the members of its group.
+    fn group_ids(&self, id: Uuid) -> Vec<Uuid> {
+        let mut ids = vec![id];
+        ids.extend(
+            self.programs
+                .iter()
+                .filter(|(_, p)| p.group() == Some(id))
+                .map(|(k, _)| *k),
+        );
+        ids
+    }
+
+    // add_network_program attaches an XDP or TC program, which has its
+    // if_index set, the way its interface is configured to.
+    async fn add_network_program(&mut self, program: Program, id: Uuid) -> Result<Uuid, BpfdError> {
+        match self.tc_mode(&program) {
+            TcMode::Dispatcher => self.add_multi_attach_program(program, id).await,
+            TcMode::Tcx => self.add_tcx_program(program, id).await,
+        }
+    }
+
+    // add_pending_program keeps an XDP or TC program, whose interface does not
+    // exist yet or is down, until sync_waiting_programs attaches it. Only the
+    // section name is checked for now, the program is loaded when it is
+    // attached.
+    async fn add_pending_program(
+        &mut self,
+        mut program: Program,
+        id: Uuid,
+    ) -> Result<Uuid, BpfdError> {
+        debug!("BpfManager::add_pending_program()");
+        // The program is only loaded once its interface is up, so for now it
+        // is only checked that the bytecode has it.
+        let program_bytes = program.data_mut()?.program_bytes().await?;
+        section_name(&program_bytes, program.data()?.name())?;
+
+        info!(
+            "{} is not up, program {id} is pending until it is",
+            program.if_name().unwrap()
+        );
+        program
+            .save(id)
+            .map_err(|e| BpfdError::Error(format!("unable to save program state: {e}")))?;
+        self.programs.insert(id, program);
+        Ok(id)
+    }
+
     pub(crate) async fn add_multi_attach_program(
         &mut self,
         mut program: Program,
@@ -191,7 +418,7 @@ impl BpfManager {
 
         // This load is just to verify the Section Name is valid.
         // The actual load is performed in the XDP or TC logic.
-        let mut ext_loader = BpfLoader::new()
+        let mut ext_loader = EbpfLoader::new()
             .allow_unsupported_maps()
             .extension(name)
             .map_pin_path(map_pin_path.expect("map_pin_path should be set"))
@@ -202,20 +429,43 @@ impl BpfManager {
             None => Err(BpfdError::SectionNameNotValid(name.to_owned())),
         }?;
 
+        // All XDP programs sharing a dispatcher have to agree on whether they
+        // handle multi-buffer packets, since the dispatcher is loaded for one
+        // or the other.
+        if let Program::Xdp(_) = program {
+            let frags = xdp_has_frags(&program_bytes, name)?;
+            let mismatch = self.programs.values().any(|p| match p {
+                Program::Xdp(p) => {
+                    p.if_index == program.if_index()
+                        && p.netns.as_ref().map(|n| n.id) == program.netns_id()
+                        && p.frags != frags
+                }
+                _ => false,
+            });
+            if mismatch {
+                return Err(BpfdError::XdpFragsMismatch(program.if_name().unwrap()));
+            }
+            if let Program::Xdp(ref mut p) = program {
+                p.frags = frags;
+            }
+        }
+
         // Calculate the next_available_id
         let next_available_id = self
             .programs
             .iter()
             .filter(|(_, p)| {
-                if p.kind() == program.kind() {
-                    p.if_index() == program.if_index() && p.direction() == program.direction()
+                if p.kind() == program.kind() && !p.tcx() {
+                    p.if_index() == program.if_index()
+                        && p.netns_id() == program.netns_id()
+                        && p.direction() == program.direction()
                 } else {
                     false
                 }
             })
             .collect::<HashMap<_, _>>()
             .len();
-        if next_available_id >= 10 {
+        if next_available_id >= MAX_PROGRAMS {
             return Err(BpfdError::TooManyPrograms);
         }
 
@@ -225,33 +475,22 @@ impl BpfManager {
         let if_index = program.if_index();
         let if_name = program.if_name().unwrap();
         let direction = program.direction();
+        let netns = program.netns_id();
 
         let did = program
             .dispatcher_id()
             .ok_or(BpfdError::DispatcherNotRequired)?;
 
         self.programs.insert(id, program);
-        self.sort_programs(program_type, if_index, direction);
-        let mut programs = self.collect_programs(program_type, if_index, direction);
-        let old_dispatcher = self.dispatchers.remove(&did);
-        let if_config = if let Some(ref i) = self.config.interfaces {
-            i.get(&if_name)
-        } else {
-            None
-        };
-        let next_revision = if let Some(ref old) = old_dispatcher {
-            old.next_revision()
-        } else {
-            1
-        };
-        let dispatcher = Dispatcher::new(if_config, &mut programs, next_revision, old_dispatcher)
+        self.sort_programs(program_type, if_index, direction, netns);
+        let mut programs = self.collect_programs(program_type, if_index, direction, netns);
+        self.replace_dispatcher(did, &if_name, &mut programs)
             .await
             .or_else(|e| {
                 let prog = self.programs.remove(&id).unwrap();
                 prog.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
                 Err(e)
             })?;
-        self.dispatchers.insert(did, dispatcher);
 
         // update programs with now populated kernel info
         // TODO this data flow should be optimized so that we don't have
@@ -269,6 +508,97 @@ impl BpfManager {
         Ok(id)
     }
 
+    fn tc_mode(&self, program: &Program) -> TcMode {
+        if program.kind() != ProgramType::Tc {
+            return TcMode::Dispatcher;
+        }
+        self.config
+            .interfaces
+            .as_ref()
+            .and_then(|i| i.get(&program.if_name().unwrap()))
+            .map_or(TcMode::default(), |c| c.tc_mode)
+    }
+
+    // tcx_anchor finds the bpfd managed tcx program that a new program with
+    // the given priority and name has to run in front of, so that tcx keeps
+    // the (priority, name) order the TC dispatcher would have used. None means
+    // the program goes last.
+    fn tcx_anchor(
+        &self,
+        if_index: Option<u32>,
+        netns: Option<u64>,
+        direction: Direction,
+        priority: i32,
+        name: &str,
+    ) -> Option<Uuid> {
+        self.programs
+            .iter()
+            .filter(|(_, p)| {
+                p.tcx()
+                    && p.if_index() == if_index
+                    && p.netns_id() == netns
+                    && p.direction() == Some(direction)
+                    && (p.priority().unwrap(), p.name()) > (priority, name)
+            })
+            .min_by_key(|(_, p)| (p.priority(), p.name().to_owned()))
+            .map(|(id, _)| *id)
+    }
+
+    pub(crate) async fn add_tcx_program(
+        &mut self,
+        mut p: Program,
+        id: Uuid,
+    ) -> Result<Uuid, BpfdError> {
+        debug!("BpfManager::add_tcx_program()");
+        let program_bytes = p.data_mut()?.program_bytes().await?;
+        let name = p.data()?.name();
+        let map_pin_path = p.data()?.map_pin_path();
+
+        let mut loader = EbpfLoader::new();
+
+        for (key, value) in p.data()?.global_data() {
+            loader.set_global(key, value.as_slice(), true);
+        }
+
+        let mut loader = loader
+            .allow_unsupported_maps()
+            .map_pin_path(map_pin_path.expect("map_pin_path should be set"))
+            .load(&program_bytes)?;
+
+        let raw_program = loader
+            .program_mut(name)
+            .ok_or(BpfdError::SectionNameNotValid(name.to_owned()))?;
+
+        let res = match p {
+            Program::Tc(ref mut program) => {
+                let anchor = self.tcx_anchor(
+                    program.if_index,
+                    program.netns.as_ref().map(|n| n.id),
+                    program.direction,
+                    program.priority,
+                    program.data.name(),
+                );
+                let res = attach_tcx_program(raw_program, program, anchor, id);
+                if res.is_err() {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                }
+                res
+            }
+            _ => panic!("not a tc program"),
+        };
+
+        if res.is_ok() {
+            self.programs.insert(id, p);
+            self.programs
+                .get(&id)
+                .unwrap()
+                .save(id)
+                .map_err(|_| BpfdError::Error("unable to persist program data".to_string()))?;
+        };
+
+        res
+    }
+
     pub(crate) async fn add_single_attach_program(
         &mut self,
         mut p: Program,
@@ -279,7 +609,7 @@ impl BpfManager {
         let name = p.data()?.name();
         let map_pin_path = p.data()?.map_pin_path();
 
-        let mut loader = BpfLoader::new();
+        let mut loader = EbpfLoader::new();
 
         for (key, value) in p.data()?.global_data() {
             loader.set_global(key, value.as_slice(), true);
@@ -435,6 +765,220 @@ impl BpfManager {
 
                 Ok(id)
             }
+            Program::RawTracepoint(ref mut program) => {
+                // Raw tracepoints are named without their category.
+                if program.tracepoint.contains('/') {
+                    return Err(BpfdError::InvalidAttach(program.tracepoint.to_string()));
+                }
+
+                let raw_tracepoint: &mut RawTracePoint = raw_program.try_into()?;
+                raw_tracepoint.load()?;
+
+                program
+                    .data
+                    .set_kernel_info(Some(raw_tracepoint.program_info()?.try_into()?));
+
+                let link_id = raw_tracepoint.attach(&program.tracepoint).or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::BpfProgramError(e))
+                })?;
+
+                let owned_link: RawTracePointLink = raw_tracepoint.take_link(link_id)?;
+                let fd_link: FdLink = owned_link.into();
+                fd_link
+                    .pin(format!("{RTDIR_FS}/prog_{}_link", id))
+                    .map_err(BpfdError::UnableToPinLink)?;
+
+                raw_tracepoint
+                    .pin(format!("{RTDIR_FS}/prog_{id}"))
+                    .or_else(|e| {
+                        p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                        Err(BpfdError::UnableToPinProgram(e))
+                    })?;
+
+                Ok(id)
+            }
+            Program::TpBtf(ref mut program) => {
+                let btf = Btf::from_sys_fs()?;
+                let tp_btf: &mut BtfTracePoint = raw_program.try_into()?;
+                tp_btf.load(&program.tracepoint, &btf)?;
+
+                program
+                    .data
+                    .set_kernel_info(Some(tp_btf.program_info()?.try_into()?));
+
+                let link_id = tp_btf.attach().or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::BpfProgramError(e))
+                })?;
+
+                let owned_link: BtfTracePointLink = tp_btf.take_link(link_id)?;
+                let fd_link: FdLink = owned_link.into();
+                fd_link
+                    .pin(format!("{RTDIR_FS}/prog_{}_link", id))
+                    .map_err(BpfdError::UnableToPinLink)?;
+
+                tp_btf.pin(format!("{RTDIR_FS}/prog_{id}")).or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::UnableToPinProgram(e))
+                })?;
+
+                Ok(id)
+            }
+            Program::Fentry(ref mut program) => {
+                let btf = Btf::from_sys_fs()?;
+                let fentry: &mut FEntry = raw_program.try_into()?;
+                fentry.load(&program.fn_name, &btf)?;
+
+                program
+                    .data
+                    .set_kernel_info(Some(fentry.program_info()?.try_into()?));
+
+                let link_id = fentry.attach().or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::BpfProgramError(e))
+                })?;
+
+                let owned_link: FEntryLink = fentry.take_link(link_id)?;
+                let fd_link: FdLink = owned_link.into();
+                fd_link
+                    .pin(format!("{RTDIR_FS}/prog_{}_link", id))
+                    .or_else(|e| {
+                        p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                        Err(BpfdError::UnableToPinLink(e))
+                    })?;
+
+                fentry.pin(format!("{RTDIR_FS}/prog_{id}")).or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::UnableToPinProgram(e))
+                })?;
+
+                Ok(id)
+            }
+            Program::Fexit(ref mut program) => {
+                let btf = Btf::from_sys_fs()?;
+                let fexit: &mut FExit = raw_program.try_into()?;
+                fexit.load(&program.fn_name, &btf)?;
+
+                program
+                    .data
+                    .set_kernel_info(Some(fexit.program_info()?.try_into()?));
+
+                let link_id = fexit.attach().or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::BpfProgramError(e))
+                })?;
+
+                let owned_link: FExitLink = fexit.take_link(link_id)?;
+                let fd_link: FdLink = owned_link.into();
+                fd_link
+                    .pin(format!("{RTDIR_FS}/prog_{}_link", id))
+                    .or_else(|e| {
+                        p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                        Err(BpfdError::UnableToPinLink(e))
+                    })?;
+
+                fexit.pin(format!("{RTDIR_FS}/prog_{id}")).or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::UnableToPinProgram(e))
+                })?;
+
+                Ok(id)
+            }
+            Program::Lsm(ref mut program) => {
+                if !bpf_lsm_enabled().await? {
+                    return Err(BpfdError::BpfLsmNotEnabled);
+                }
+
+                // The hook is resolved against vmlinux BTF before the program
+                // is handed to the kernel, so a missing hook surfaces as a BTF
+                // error here.
+                let btf = Btf::from_sys_fs()?;
+                let lsm: &mut Lsm = raw_program.try_into()?;
+                lsm.load(&program.hook, &btf).map_err(|e| match e {
+                    ProgramError::Btf(_) => BpfdError::InvalidLsmHook(program.hook.to_string()),
+                    e => BpfdError::BpfProgramError(e),
+                })?;
+
+                program
+                    .data
+                    .set_kernel_info(Some(lsm.program_info()?.try_into()?));
+
+                let link_id = lsm.attach().or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::BpfProgramError(e))
+                })?;
+
+                let owned_link: LsmLink = lsm.take_link(link_id)?;
+                let fd_link: FdLink = owned_link.into();
+                fd_link
+                    .pin(format!("{RTDIR_FS}/prog_{}_link", id))
+                    .or_else(|e| {
+                        p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                        Err(BpfdError::UnableToPinLink(e))
+                    })?;
+
+                lsm.pin(format!("{RTDIR_FS}/prog_{id}")).or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::UnableToPinProgram(e))
+                })?;
+
+                Ok(id)
+            }
+            Program::Iter(ref mut program) => {
+                let btf = Btf::from_sys_fs()?;
+                let iter: &mut Iter = raw_program.try_into()?;
+                iter.load(&program.iter_type, &btf)?;
+
+                program
+                    .data
+                    .set_kernel_info(Some(iter.program_info()?.try_into()?));
+
+                let link_id = iter.attach().or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::BpfProgramError(e))
+                })?;
+
+                // The pinned link is what ReadIterator creates new iterator
+                // instances from.
+                let owned_link: IterLink = iter.take_link(link_id)?;
+                let fd_link: FdLink = owned_link.into();
+                fd_link
+                    .pin(format!("{RTDIR_FS}/prog_{}_link", id))
+                    .map_err(BpfdError::UnableToPinLink)?;
+
+                iter.pin(format!("{RTDIR_FS}/prog_{id}")).or_else(|e| {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                    Err(BpfdError::UnableToPinProgram(e))
+                })?;
+
+                Ok(id)
+            }
+            Program::Cgroup(ref mut program) => {
+                let res = attach_cgroup_program(raw_program, program, id);
+                if res.is_err() {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                }
+                res
+            }
+            Program::PerfEvent(ref mut program) => {
+                let res = attach_perf_event_program(raw_program, program, id);
+                if res.is_err() {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                }
+                res
+            }
+            Program::SkMsg(_)
+            | Program::SkSkb(_)
+            | Program::SkLookup(_)
+            | Program::SocketFilter(_)
+            | Program::FlowDissector(_) => {
+                let res = attach_socket_program(raw_program, &mut p, id);
+                if res.is_err() {
+                    p.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
+                }
+                res
+            }
             _ => panic!("not a supported single attach program"),
         };
 
@@ -453,18 +997,52 @@ impl BpfManager {
     }
 
     pub(crate) async fn remove_program(&mut self, id: Uuid) -> Result<(), BpfdError> {
-        debug!("BpfManager::remove_program() id: {id}");
+        // The members of a group go first, since they use the maps of the
+        // program they were loaded along with.
+        for member in self.group_ids(id).into_iter().skip(1) {
+            self.remove_one_program(member).await?;
+        }
+        self.remove_one_program(id).await
+    }
+
+    async fn remove_one_program(&mut self, id: Uuid) -> Result<(), BpfdError> {
+        debug!("BpfManager::remove_one_program() id: {id}");
         let prog = self.programs.remove(&id).unwrap();
 
         let map_owner_id = prog.data()?.map_owner_id();
 
+        // sk_msg and sk_skb programs have no pinned link, so they have to be
+        // detached explicitly while the program is still pinned.
+        if let Program::SkMsg(_) | Program::SkSkb(_) = prog {
+            if let Err(e) = detach_sock_map_program(id, &prog) {
+                warn!("unable to detach program {id} from its sockmap: {e}");
+            }
+        }
+
         prog.delete(id).map_err(BpfdError::BpfdProgramDeleteError)?;
 
         match prog {
+            // Pending programs are not attached anywhere yet.
+            _ if prog.pending() => (),
+            // tcx programs are detached by unpinning their link above.
+            Program::Tc(TcProgram { tcx: true, .. }) => (),
             Program::Xdp(_) | Program::Tc(_) => self.remove_multi_attach_program(prog).await?,
             Program::Tracepoint(_)
             | Program::Kprobe(_)
             | Program::Uprobe(_)
+            | Program::Fentry(_)
+            | Program::Fexit(_)
+            | Program::Lsm(_)
+            | Program::Cgroup(_)
+            | Program::SkMsg(_)
+            | Program::SkSkb(_)
+            | Program::SkLookup(_)
+            | Program::RawTracepoint(_)
+            | Program::TpBtf(_)
+            | Program::PerfEvent(_)
+            | Program::SocketFilter(_)
+            | Program::FlowDissector(_)
+            | Program::Iter(_)
             | Program::Unsupported(_) => (),
         }
 
@@ -482,8 +1060,10 @@ impl BpfManager {
             .programs
             .iter()
             .filter(|(_, p)| {
-                if p.kind() == program.kind() {
-                    p.if_index() == program.if_index() && p.direction() == program.direction()
+                if p.kind() == program.kind() && !p.tcx() {
+                    p.if_index() == program.if_index()
+                        && p.netns_id() == program.netns_id()
+                        && p.direction() == program.direction()
                 } else {
                     false
                 }
@@ -496,26 +1076,309 @@ impl BpfManager {
             .dispatcher_id()
             .ok_or(BpfdError::DispatcherNotRequired)?;
 
-        let mut old_dispatcher = self.dispatchers.remove(&did);
+        if next_available_id == 0 {
+            if let Some(mut old) = self.dispatchers.remove(&did) {
+                // Delete the dispatcher
+                return old.delete(true);
+            }
+        }
+
+        let program_type = program.kind();
+        let if_index = program.if_index();
+        let if_name = program.if_name().unwrap();
+        let direction = program.direction();
+        let netns = program.netns_id();
+
+        self.sort_programs(program_type, if_index, direction, netns);
+
+        let mut programs = self.collect_programs(program_type, if_index, direction, netns);
+
+        // Only disabled programs are left, which do not need a dispatcher.
+        if programs.is_empty() {
+            if let Some(mut old) = self.dispatchers.remove(&did) {
+                return old.delete(true);
+            }
+            return Ok(());
+        }
+
+        self.replace_dispatcher(did, &if_name, &mut programs).await
+    }
+
+    // update_program changes the priority and proceed_on of an XDP or TC
+    // program in place. The dispatcher is rebuilt at its next revision with
+    // the already attached extensions, so the program keeps its id and maps.
+    pub(crate) async fn update_program(
+        &mut self,
+        id: Uuid,
+        priority: Option<i32>,
+        proceed_on: Option<Vec<i32>>,
+    ) -> Result<(), BpfdError> {
+        debug!("BpfManager::update_program() id: {id}");
+        let prog = self
+            .programs
+            .get_mut(&id)
+            .ok_or(BpfdError::ProgramNotFound(id))?;
+        if prog.pending() {
+            return Err(BpfdError::ProgramPending(id));
+        }
+        let old_prog = prog.clone();
+
+        match prog {
+            Program::Xdp(p) => {
+                if let Some(proceed_on) = proceed_on {
+                    p.proceed_on = XdpProceedOn::from_int32s(proceed_on).map_err(|e| {
+                        BpfdError::Error(format!("failed to parse proceed_on: {e}"))
+                    })?;
+                }
+                if let Some(priority) = priority {
+                    p.priority = priority;
+                }
+            }
+            Program::Tc(p) if !p.tcx => {
+                if let Some(proceed_on) = proceed_on {
+                    p.proceed_on = TcProceedOn::from_int32s(proceed_on).map_err(|e| {
+                        BpfdError::Error(format!("failed to parse proceed_on: {e}"))
+                    })?;
+                }
+                if let Some(priority) = priority {
+                    p.priority = priority;
+                }
+            }
+            _ => return Err(BpfdError::NotUpdatable(id)),
+        }
+
+        if let Err(e) = self.rebuild_dispatcher(&old_prog).await {
+            self.programs.insert(id, old_prog.clone());
+            self.sort_programs(
+                old_prog.kind(),
+                old_prog.if_index(),
+                old_prog.direction(),
+                old_prog.netns_id(),
+            );
+            return Err(e);
+        }
+
+        if let Some(p) = self.programs.get(&id) {
+            p.save(id)
+                .map_err(|e| BpfdError::Error(format!("unable to save program state: {e}")))?;
+        };
+
+        Ok(())
+    }
+
+    // set_program_enabled takes an XDP or TC program out of its dispatcher, or
+    // puts it back in. A disabled program stays loaded and pinned, so its maps
+    // and id are kept while it does not run.
+    pub(crate) async fn set_program_enabled(
+        &mut self,
+        id: Uuid,
+        enabled: bool,
+    ) -> Result<(), BpfdError> {
+        debug!("BpfManager::set_program_enabled() id: {id} enabled: {enabled}");
+        let prog = self
+            .programs
+            .get_mut(&id)
+            .ok_or(BpfdError::ProgramNotFound(id))?;
+        if !matches!(
+            prog,
+            Program::Xdp(_) | Program::Tc(TcProgram { tcx: false, .. })
+        ) {
+            return Err(BpfdError::NotUpdatable(id));
+        }
+        if prog.pending() {
+            return Err(BpfdError::ProgramPending(id));
+        }
+        if prog.enabled() == enabled {
+            return Ok(());
+        }
+        prog.set_enabled(enabled);
+        let prog = prog.clone();
+
+        if let Err(e) = self.rebuild_dispatcher(&prog).await {
+            if let Some(p) = self.programs.get_mut(&id) {
+                p.set_enabled(!enabled);
+            }
+            self.sort_programs(
+                prog.kind(),
+                prog.if_index(),
+                prog.direction(),
+                prog.netns_id(),
+            );
+            return Err(e);
+        }
+
+        if let Some(p) = self.programs.get(&id) {
+            p.save(id)
+                .map_err(|e| BpfdError::Error(format!("unable to save program state: {e}")))?;
+        };
+
+        Ok(())
+    }
+
+    // replace_program swaps the XDP or TC program id for the same program built
+    // from location. The new program is attached in the old one's slot of a new
+    // dispatcher revision, and only then is the old program unloaded. The new
+    // dispatcher replaces the old one in one step, through the link of an XDP
+    // dispatcher or the filter of a TC dispatcher.
+    pub(crate) async fn replace_program(
+        &mut self,
+        id: Uuid,
+        location: Location,
+        keep_id: bool,
+    ) -> Result<Uuid, BpfdError> {
+        debug!("BpfManager::replace_program() id: {id}");
+        let old_prog = self
+            .programs
+            .get(&id)
+            .ok_or(BpfdError::ProgramNotFound(id))?
+            .clone();
+        if !matches!(
+            old_prog,
+            Program::Xdp(_) | Program::Tc(TcProgram { tcx: false, .. })
+        ) {
+            return Err(BpfdError::NotUpdatable(id));
+        }
+        if old_prog.pending() {
+            return Err(BpfdError::ProgramPending(id));
+        }
+        if !old_prog.enabled() {
+            return Err(BpfdError::ProgramDisabled(id));
+        }
+        if old_prog.group().is_some() || self.group_ids(id).len() > 1 {
+            return Err(BpfdError::ProgramGrouped(id));
+        }
+        // The new program gets maps of its own unless it keeps the id, which
+        // would leave the programs that use the old program's maps behind.
+        if !keep_id
+            && old_prog
+                .data()?
+                .maps_used_by()
+                .map_or(false, |used_by| used_by.iter().any(|u| *u != id))
+        {
+            return Err(BpfdError::MapsShared(id));
+        }
+
+        let new_id = if keep_id { id } else { Uuid::new_v4() };
+        let map_owner_id = old_prog.data()?.map_owner_id();
+
+        let mut program = old_prog.clone();
+        let data = program.data_mut()?;
+        data.set_location(location);
+        data.set_id(Some(new_id));
+        data.set_kernel_info(None);
+        let program_bytes = data.program_bytes().await?;
+        match program {
+            Program::Xdp(ref mut p) => {
+                if xdp_has_frags(&program_bytes, p.data.name())? != p.frags {
+                    return Err(BpfdError::XdpFragsMismatch(p.iface.clone()));
+                }
+                p.attached = false;
+            }
+            Program::Tc(ref mut p) => p.attached = false,
+            _ => (),
+        }
+
+        // The new program is pinned where the old one is, so the old pin is
+        // moved out of the way until the old program can go.
+        let pin = format!("{RTDIR_FS}/prog_{id}");
+        let old_pin = format!("{RTDIR_FS}/prog_{id}_replaced");
+        if keep_id {
+            fs::rename(&pin, &old_pin)
+                .await
+                .map_err(|e| BpfdError::Error(format!("can't move program pin: {e}")))?;
+        } else {
+            let map_pin_path = self.manage_map_pin_path(new_id, map_owner_id).await?;
+            program.data_mut()?.set_map_pin_path(Some(map_pin_path));
+        }
 
-        if let Some(ref mut old) = old_dispatcher {
-            if next_available_id == 0 {
-                // Delete the dispatcher
-                return old.delete(true);
+        self.programs.remove(&id);
+        self.programs.insert(new_id, program);
+        if let Err(e) = self.rebuild_dispatcher(&old_prog).await {
+            self.programs.remove(&new_id);
+            self.programs.insert(id, old_prog.clone());
+            self.sort_programs(
+                old_prog.kind(),
+                old_prog.if_index(),
+                old_prog.direction(),
+                old_prog.netns_id(),
+            );
+            if keep_id {
+                let _ = fs::rename(&old_pin, &pin).await;
+            } else {
+                let _ = fs::remove_file(format!("{RTDIR_FS}/prog_{new_id}")).await;
+                let _ = self.cleanup_map_pin_path(new_id, map_owner_id).await;
             }
+            return Err(e);
+        }
+
+        // The old dispatcher is gone, so this drops the last reference to the
+        // old program.
+        if keep_id {
+            fs::remove_file(&old_pin)
+                .await
+                .map_err(|e| BpfdError::Error(format!("can't remove program pin: {e}")))?;
+        } else {
+            old_prog
+                .delete(id)
+                .map_err(BpfdError::BpfdProgramDeleteError)?;
+            let map_pin_path = calc_map_pin_path(new_id, map_owner_id).1;
+            self.save_map(new_id, map_owner_id, &map_pin_path).await?;
+            self.delete_map(id, map_owner_id).await?;
         }
 
+        if let Some(p) = self.programs.get_mut(&new_id) {
+            p.set_attached();
+            p.save(new_id)
+                .map_err(|e| BpfdError::Error(format!("unable to save program state: {e}")))?;
+        };
+
+        Ok(new_id)
+    }
+
+    // rebuild_dispatcher replaces the dispatcher program is attached through
+    // with its next revision, built from the enabled programs that are in
+    // self.programs now. The dispatcher is removed if none are left.
+    async fn rebuild_dispatcher(&mut self, program: &Program) -> Result<(), BpfdError> {
         let program_type = program.kind();
         let if_index = program.if_index();
         let if_name = program.if_name().unwrap();
         let direction = program.direction();
+        let netns = program.netns_id();
+
+        let did = program
+            .dispatcher_id()
+            .ok_or(BpfdError::DispatcherNotRequired)?;
+
+        self.sort_programs(program_type, if_index, direction, netns);
+        let mut programs = self.collect_programs(program_type, if_index, direction, netns);
+        if programs.is_empty() {
+            if let Some(mut old) = self.dispatchers.remove(&did) {
+                old.delete(true)?;
+            }
+            return Ok(());
+        }
 
-        self.sort_programs(program_type, if_index, direction);
+        self.replace_dispatcher(did, &if_name, &mut programs)
+            .await?;
 
-        let mut programs = self.collect_programs(program_type, if_index, direction);
+        programs.iter().for_each(|(i, p)| {
+            self.programs.insert(i.to_owned(), p.to_owned());
+        });
+        Ok(())
+    }
 
+    // replace_dispatcher builds the next revision of the dispatcher did for
+    // programs and attaches it in place of the current one. The current
+    // revision is kept when that fails, so it can still be updated or removed.
+    async fn replace_dispatcher(
+        &mut self,
+        did: DispatcherId,
+        if_name: &str,
+        programs: &mut [(Uuid, Program)],
+    ) -> Result<(), BpfdError> {
+        let mut old_dispatcher = self.dispatchers.remove(&did);
         let if_config = if let Some(ref i) = self.config.interfaces {
-            i.get(&if_name)
+            i.get(if_name)
         } else {
             None
         };
@@ -525,10 +1388,26 @@ impl BpfManager {
             1
         };
         debug!("next_revision = {next_revision}");
-        let dispatcher =
-            Dispatcher::new(if_config, &mut programs, next_revision, old_dispatcher).await?;
-        self.dispatchers.insert(did, dispatcher);
-        Ok(())
+        match Dispatcher::new(
+            if_config,
+            &self.config.dispatchers,
+            programs,
+            next_revision,
+            old_dispatcher.as_mut(),
+        )
+        .await
+        {
+            Ok(dispatcher) => {
+                self.dispatchers.insert(did, dispatcher);
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(old) = old_dispatcher {
+                    self.dispatchers.insert(did, old);
+                }
+                Err(e)
+            }
+        }
     }
 
     pub(crate) async fn rebuild_multiattach_dispatcher(
@@ -539,40 +1418,29 @@ impl BpfManager {
         did: DispatcherId,
     ) -> Result<(), BpfdError> {
         debug!("BpfManager::rebuild_multiattach_dispatcher() for program type {program_type} on if_index {if_index}");
-        let mut old_dispatcher = self.dispatchers.remove(&did);
-
-        if let Some(ref mut old) = old_dispatcher {
+        if let Some(if_name) = self.dispatchers.get_mut(&did).map(|d| d.if_name()) {
             debug!("Rebuild Multiattach Dispatcher for {did:?}");
             let if_index = Some(if_index);
+            let netns = match did {
+                DispatcherId::Xdp(DispatcherInfo(_, _, n))
+                | DispatcherId::Tc(DispatcherInfo(_, _, n)) => n,
+            };
 
-            self.sort_programs(program_type, if_index, direction);
-            let mut programs = self.collect_programs(program_type, if_index, direction);
+            self.sort_programs(program_type, if_index, direction, netns);
+            let mut programs = self.collect_programs(program_type, if_index, direction, netns);
 
             debug!("programs loaded: {}", programs.len());
 
             // The following checks should have been done when the dispatcher was built, but check again to confirm
             if programs.is_empty() {
+                let mut old = self.dispatchers.remove(&did).unwrap();
                 return old.delete(true);
-            } else if programs.len() > 10 {
+            } else if programs.len() > MAX_PROGRAMS {
                 return Err(BpfdError::TooManyPrograms);
             }
 
-            let if_name = old.if_name();
-            let if_config = if let Some(ref i) = self.config.interfaces {
-                i.get(&if_name)
-            } else {
-                None
-            };
-
-            let next_revision = if let Some(ref old) = old_dispatcher {
-                old.next_revision()
-            } else {
-                1
-            };
-
-            let dispatcher =
-                Dispatcher::new(if_config, &mut programs, next_revision, old_dispatcher).await?;
-            self.dispatchers.insert(did, dispatcher);
+            self.replace_dispatcher(did, &if_name, &mut programs)
+                .await?;
         } else {
             debug!("No dispatcher found in rebuild_multiattach_dispatcher() for {did:?}");
         }
@@ -582,9 +1450,19 @@ impl BpfManager {
     pub(crate) fn list_programs(&mut self) -> Result<Vec<Program>, BpfdError> {
         debug!("BpfManager::list_programs()");
 
+        // Pending programs are not in the kernel yet, so they are listed on
+        // their own.
+        let pending: Vec<Program> = self
+            .programs
+            .values()
+            .filter(|p| p.pending())
+            .cloned()
+            .collect();
+
         let mut bpfd_progs: HashMap<u32, Program> = self
             .programs
             .values()
+            .filter(|p| !p.pending())
             .map(|p| {
                 let kernel_info = p
                     .data()
@@ -596,7 +1474,16 @@ impl BpfManager {
             })
             .collect();
 
+        // Dispatchers chained behind the first one on a hook, and the programs
+        // chaining them, are internal to bpfd and not listed.
+        let chain_ids: HashSet<u32> = self
+            .dispatchers
+            .values()
+            .flat_map(|d| d.chain_program_ids())
+            .collect();
+
         loaded_programs()
+            .filter(|p| !matches!(p, Ok(p) if chain_ids.contains(&p.id())))
             .map(|p| {
                 let prog = p.map_err(BpfdError::BpfProgramError)?;
                 let prog_id = prog.id();
@@ -606,6 +1493,7 @@ impl BpfManager {
                     None => Ok(Program::Unsupported(prog.try_into()?)),
                 }
             })
+            .chain(pending.into_iter().map(Ok))
             .collect()
     }
 
@@ -614,13 +1502,17 @@ impl BpfManager {
         program_type: ProgramType,
         if_index: Option<u32>,
         direction: Option<Direction>,
+        netns: Option<u64>,
     ) {
         let mut extensions = self
             .programs
             .iter_mut()
             .filter_map(|(k, v)| {
-                if v.kind() == program_type {
-                    if v.if_index() == if_index && v.direction() == direction {
+                if v.kind() == program_type && !v.tcx() {
+                    if v.if_index() == if_index
+                        && v.direction() == direction
+                        && v.netns_id() == netns
+                    {
                         Some((k, v))
                     } else {
                         None
@@ -629,6 +1521,15 @@ impl BpfManager {
                     None
                 }
             })
+            .filter_map(|(k, v)| {
+                // Disabled programs have no slot in the dispatcher.
+                if v.enabled() {
+                    Some((k, v))
+                } else {
+                    v.set_position(None);
+                    None
+                }
+            })
             .collect::<Vec<(&Uuid, &mut Program)>>();
 
         extensions.sort_by_key(|(_, b)| {
@@ -651,10 +1552,17 @@ impl BpfManager {
         program_type: ProgramType,
         if_index: Option<u32>,
         direction: Option<Direction>,
+        netns: Option<u64>,
     ) -> Vec<(Uuid, Program)> {
         let mut results = vec![];
         for (k, v) in self.programs.iter() {
-            if v.kind() == program_type && v.if_index() == if_index && v.direction() == direction {
+            if v.kind() == program_type
+                && !v.tcx()
+                && v.enabled()
+                && v.if_index() == if_index
+                && v.direction() == direction
+                && v.netns_id() == netns
+            {
                 results.push((k.to_owned(), v.clone()))
             }
         }
@@ -675,6 +1583,20 @@ impl BpfManager {
     }
 
     pub(crate) async fn process_commands(&mut self) {
+        // tokio intervals can not be zero, which turns reconciling off instead.
+        let reconcile_enabled = self.config.reconcile.interval > 0;
+        let mut reconcile = interval(Duration::from_secs(self.config.reconcile.interval.max(1)));
+        reconcile.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut link_events: Pin<Box<dyn Stream<Item = LinkEvent> + Send>> =
+            match netlink::link_events() {
+                Ok(events) => Box::pin(events),
+                Err(e) => {
+                    warn!("programs will not follow their interfaces: {e}");
+                    Box::pin(tokio_stream::pending())
+                }
+            };
+        // Interfaces may have come, gone or changed while bpfd was not running.
+        self.sync_waiting_programs().await;
         loop {
             // Start receiving messages
             select! {
@@ -686,7 +1608,7 @@ impl BpfManager {
                 Some(cmd) = self.commands.recv() => {
                     match cmd {
                         Command::Load(args) => {
-                            let res = self.add_program(args.program).await;
+                            let res = self.add_program_to_ifaces(args.program, args.ifaces).await;
                             // Ignore errors as they'll be propagated to caller in the RPC status
                             let _ = args.responder.send(res);
                         },
@@ -697,13 +1619,324 @@ impl BpfManager {
                             let _ = responder.send(progs);
                         }
                         Command::PullBytecode (args) => self.pull_bytecode(args).await.unwrap(),
+                        Command::ReadIterator(args) => {
+                            let res = self.read_iterator(args.id);
+                            // Ignore errors as they'll be propagated to caller in the RPC status
+                            let _ = args.responder.send(res);
+                        }
+                        Command::Update(args) => {
+                            let res = self.update_group(args.id, args.priority, args.proceed_on).await;
+                            // Ignore errors as they'll be propagated to caller in the RPC status
+                            let _ = args.responder.send(res);
+                        }
+                        Command::SetEnabled(args) => {
+                            let res = self.set_group_enabled(args.id, args.enabled).await;
+                            // Ignore errors as they'll be propagated to caller in the RPC status
+                            let _ = args.responder.send(res);
+                        }
+                        Command::GetMaps(args) => {
+                            let res = self.get_maps(args.id);
+                            // Ignore errors as they'll be propagated to caller in the RPC status
+                            let _ = args.responder.send(res);
+                        }
+                        Command::OpenMap(args) => {
+                            let res = self.open_map(args.id, &args.name);
+                            // Ignore errors as they'll be propagated to caller in the RPC status
+                            let _ = args.responder.send(res);
+                        }
+                        Command::Replace(args) => {
+                            let res = self.replace_program(args.id, args.location, args.keep_id).await;
+                            // Ignore errors as they'll be propagated to caller in the RPC status
+                            let _ = args.responder.send(res);
+                        }
                     }
                 }
+                Some(event) = link_events.next() => self.link_changed(event).await,
+                _ = reconcile.tick(), if reconcile_enabled => self.reconcile_dispatchers().await,
             }
         }
         info!("Stopping processing commands");
     }
 
+    // link_changed syncs the programs loaded with wait_for_iface, when a link
+    // event is about their interface.
+    async fn link_changed(&mut self, event: LinkEvent) {
+        debug!("BpfManager::link_changed() {event:?}");
+        let affected = self.programs.values().any(|p| {
+            p.wait_for_iface()
+                && (p.if_index() == Some(event.if_index)
+                    || (!event.removed && p.if_name() == event.if_name))
+        });
+        if affected {
+            self.sync_waiting_programs().await;
+        }
+    }
+
+    // sync_waiting_programs keeps the programs loaded with wait_for_iface on
+    // the link that has their interface name while it is up. Programs are
+    // detached from a link that was removed, renamed or set down, and attached
+    // once a link with their interface name is up, so they follow an interface
+    // that is recreated with a new if_index and stay pending while it is down.
+    async fn sync_waiting_programs(&mut self) {
+        let mut up: HashMap<String, Option<u32>> = HashMap::new();
+
+        let attached: Vec<(Uuid, String, Option<u32>)> = self
+            .programs
+            .iter()
+            .filter(|(_, p)| p.wait_for_iface() && !p.pending())
+            .map(|(id, p)| (*id, p.if_name().unwrap(), p.if_index()))
+            .collect();
+        let mut stale = vec![];
+        for (id, if_name, if_index) in attached {
+            if cached_iface_up(&mut up, &if_name).await != if_index {
+                stale.push(id);
+            }
+        }
+        if !stale.is_empty() {
+            self.detach_programs(stale).await;
+        }
+
+        let pending: Vec<(Uuid, String)> = self
+            .programs
+            .iter()
+            .filter(|(_, p)| p.wait_for_iface() && p.pending())
+            .map(|(id, p)| (*id, p.if_name().unwrap()))
+            .collect();
+
+        for (id, if_name) in pending {
+            let Some(if_index) = cached_iface_up(&mut up, &if_name).await else {
+                continue;
+            };
+
+            let Some(mut program) = self.programs.remove(&id) else {
+                continue;
+            };
+            let waiting = program.clone();
+            program.set_if_index(if_index);
+            info!("{if_name} is up, attaching program {id}");
+            if let Err(e) = self.add_network_program(program, id).await {
+                warn!("unable to attach program {id} to {if_name}, it stays pending: {e}");
+                self.programs.remove(&id);
+                if let Err(e) = waiting.save(id) {
+                    warn!("unable to save state of program {id}: {e}");
+                }
+                self.programs.insert(id, waiting);
+            }
+        }
+    }
+
+    // detach_programs takes XDP and TC programs off the link they are attached
+    // to, and leaves them pending. Their dispatchers are rebuilt without them if
+    // the link is still there, or removed if it is gone.
+    async fn detach_programs(&mut self, ids: Vec<Uuid>) {
+        let mut dids = HashSet::new();
+        for id in ids {
+            let Some(program) = self.programs.get_mut(&id) else {
+                continue;
+            };
+            warn!(
+                "{} is gone or down, program {id} is pending until it is up",
+                program.if_name().unwrap()
+            );
+            if !program.tcx() {
+                if let Some(did) = program.dispatcher_id() {
+                    dids.insert(did);
+                }
+            }
+            // Unpinning drops the program, and detaches it if it uses tcx.
+            if let Err(e) = program.delete(id) {
+                warn!("unable to remove pins of program {id}: {e}");
+            }
+            program.set_pending();
+            if let Err(e) = program.save(id) {
+                warn!("unable to save state of program {id}: {e}");
+            }
+        }
+
+        for did in dids {
+            let (program_type, if_index, direction) = match did {
+                DispatcherId::Xdp(DispatcherInfo(i, d, _)) => (ProgramType::Xdp, i, d),
+                DispatcherId::Tc(DispatcherInfo(i, d, _)) => (ProgramType::Tc, i, d),
+            };
+            match netlink::link_state(if_index).await {
+                Ok(Some(_)) => {
+                    if let Err(e) = self
+                        .rebuild_multiattach_dispatcher(program_type, if_index, direction, did)
+                        .await
+                    {
+                        warn!("unable to rebuild {program_type} dispatcher on {if_index}: {e}");
+                    }
+                }
+                Ok(None) => {
+                    if let Some(mut dispatcher) = self.dispatchers.remove(&did) {
+                        if let Err(e) = dispatcher.delete(true) {
+                            warn!("unable to remove {program_type} dispatcher on {if_index}: {e}");
+                        }
+                    }
+                }
+                Err(e) => warn!("unable to check link {if_index}: {e}"),
+            }
+        }
+    }
+
+    // reconcile_dispatchers checks that every dispatcher is still attached, as
+    // they can be removed outside of bpfd, e.g. with `tc filter del` or by
+    // deleting the interface. A dispatcher is re-attached if its interface is
+    // still there. Otherwise it is removed, which leaves its programs loaded,
+    // but detached.
+    async fn reconcile_dispatchers(&mut self) {
+        let mut detached = vec![];
+        for (did, dispatcher) in self.dispatchers.iter_mut() {
+            let if_index = match did {
+                DispatcherId::Xdp(DispatcherInfo(i, _, _))
+                | DispatcherId::Tc(DispatcherInfo(i, _, _)) => *i,
+            };
+            // The interface is gone as well if its namespace is.
+            let if_name = dispatcher.if_name();
+            let current = in_netns(dispatcher.netns(), || if_nametoindex(if_name.as_str()));
+            if !matches!(current, Ok(Ok(i)) if i == if_index) {
+                detached.push((did.clone(), false));
+                continue;
+            }
+            match dispatcher.is_attached().await {
+                Ok(true) => (),
+                Ok(false) => detached.push((did.clone(), true)),
+                Err(e) => warn!("unable to check if dispatcher {did:?} is attached: {e}"),
+            }
+        }
+
+        for (did, iface_exists) in detached {
+            let (program_type, if_index, direction) = match did {
+                DispatcherId::Xdp(DispatcherInfo(i, d, _)) => (ProgramType::Xdp, i, d),
+                DispatcherId::Tc(DispatcherInfo(i, d, _)) => (ProgramType::Tc, i, d),
+            };
+            let Some(dispatcher) = self.dispatchers.get_mut(&did) else {
+                continue;
+            };
+            let if_name = dispatcher.if_name();
+
+            if !iface_exists {
+                warn!(
+                    "interface {if_name} is gone, removing its {program_type} dispatcher, its programs stay loaded but are detached"
+                );
+                if let Err(e) = dispatcher.delete(true) {
+                    warn!("unable to remove {program_type} dispatcher of {if_name}: {e}");
+                }
+                self.dispatchers.remove(&did);
+                for (id, p) in self.programs.iter_mut() {
+                    if p.pending() || p.tcx() || p.dispatcher_id().as_ref() != Some(&did) {
+                        continue;
+                    }
+                    p.set_detached();
+                    if let Err(e) = p.save(*id) {
+                        warn!("unable to save state of program {id}: {e}");
+                    }
+                }
+                continue;
+            }
+
+            warn!("{program_type} dispatcher on {if_name} was removed outside of bpfd, re-attaching it");
+            let res = match dispatcher.forget_link() {
+                Ok(()) => {
+                    self.rebuild_multiattach_dispatcher(program_type, if_index, direction, did)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = res {
+                warn!("unable to re-attach {program_type} dispatcher on {if_name}: {e}");
+            }
+        }
+    }
+
+    // read_iterator creates a new instance of the iterator program id from its
+    // pinned link. Reading the returned file runs the iterator, so that is left
+    // to the caller.
+    fn read_iterator(&self, id: Uuid) -> Result<std::fs::File, BpfdError> {
+        match self.programs.get(&id) {
+            Some(Program::Iter(_)) => {
+                let fd_link = FdLink::from_pin(format!("{RTDIR_FS}/prog_{id}_link"))?;
+                let link: IterLink = fd_link.into();
+                Ok(link.into_file()?)
+            }
+            Some(_) => Err(BpfdError::NotAnIterator(id)),
+            None => Err(BpfdError::ProgramNotFound(id)),
+        }
+    }
+
+    // pending_member returns the first program of the group of id that is
+    // waiting for its interface. Groups are only updated, enabled or disabled
+    // as a whole, which can't be done to such a program yet.
+    fn pending_member(&self, id: Uuid) -> Option<Uuid> {
+        self.group_ids(id)
+            .into_iter()
+            .find(|i| self.programs.get(i).map_or(false, |p| p.pending()))
+    }
+
+    // update_group updates every program of the group of id, stopping at the
+    // first one that fails.
+    async fn update_group(
+        &mut self,
+        id: Uuid,
+        priority: Option<i32>,
+        proceed_on: Option<Vec<i32>>,
+    ) -> Result<(), BpfdError> {
+        if let Some(pending) = self.pending_member(id) {
+            return Err(BpfdError::ProgramPending(pending));
+        }
+        for id in self.group_ids(id) {
+            self.update_program(id, priority, proceed_on.clone())
+                .await?;
+        }
+        Ok(())
+    }
+
+    // set_group_enabled enables or disables every program of the group of id,
+    // stopping at the first one that fails.
+    async fn set_group_enabled(&mut self, id: Uuid, enabled: bool) -> Result<(), BpfdError> {
+        if let Some(pending) = self.pending_member(id) {
+            return Err(BpfdError::ProgramPending(pending));
+        }
+        for id in self.group_ids(id) {
+            self.set_program_enabled(id, enabled).await?;
+        }
+        Ok(())
+    }
+
+    // get_maps returns the maps pinned for the program id, which may be
+    // owned by another program it shares them with.
+    fn get_maps(&self, id: Uuid) -> Result<Vec<MapInfo>, BpfdError> {
+        let program = self
+            .programs
+            .get(&id)
+            .ok_or(BpfdError::ProgramNotFound(id))?;
+        match program.data()?.map_pin_path() {
+            Some(path) => pinned_maps(path),
+            None => Ok(vec![]),
+        }
+    }
+
+    // open_map opens the map name of the program id for the RPC thread, so
+    // large maps can be read without holding up other commands.
+    fn open_map(&self, id: Uuid, name: &str) -> Result<PinnedMap, BpfdError> {
+        let program = self
+            .programs
+            .get(&id)
+            .ok_or(BpfdError::ProgramNotFound(id))?;
+        let not_found = || BpfdError::MapNotFound(id, name.to_string());
+        // The name must not lead out of the map pin path.
+        if Path::new(name).file_name() != Some(OsStr::new(name)) {
+            return Err(not_found());
+        }
+        let data = program.data()?;
+        let path = data
+            .map_pin_path()
+            .map(|p| p.join(name))
+            .filter(|p| p.exists())
+            .ok_or_else(not_found)?;
+        Ok(PinnedMap::open(&path)?.with_btf())
+    }
+
     async fn unload_command(&mut self, args: UnloadArgs) -> anyhow::Result<()> {
         let res = self.remove_program(args.id).await;
         // Ignore errors as they'll be propagated to caller in the RPC status
@@ -863,6 +2096,532 @@ impl BpfManager {
     }
 }
 
+// attach_cgroup_program loads a cgroup program and attaches it to the cgroup
+// at program.cgroup_path. Programs are always attached in multi mode so they
+// coexist with anything else already attached to the same cgroup. The link is
+// pinned so that the program stays attached across bpfd restarts.
+fn attach_cgroup_program(
+    raw_program: &mut aya::programs::Program,
+    program: &mut CgroupProgram,
+    id: Uuid,
+) -> Result<Uuid, BpfdError> {
+    let cgroup = std::fs::File::open(&program.cgroup_path)
+        .map_err(|_| BpfdError::InvalidAttach(program.cgroup_path.to_string()))?;
+    let mode = CgroupAttachMode::AllowMultiple;
+
+    let link = match program.attach_type.program_type() {
+        ProgramType::CgroupSkb => {
+            let attach_type = cgroup_skb_attach_type(program.attach_type)?;
+            cgroup_link(raw_program, |p: &mut CgroupSkb| {
+                p.load()?;
+                let link_id = p.attach(cgroup, attach_type, mode)?;
+                p.take_link(link_id)
+            })?
+        }
+        ProgramType::CgroupSock => cgroup_link(raw_program, |p: &mut CgroupSock| {
+            p.load()?;
+            let link_id = p.attach(cgroup, mode)?;
+            p.take_link(link_id)
+        })?,
+        ProgramType::CgroupSockAddr => cgroup_link(raw_program, |p: &mut CgroupSockAddr| {
+            p.load()?;
+            let link_id = p.attach(cgroup, mode)?;
+            p.take_link(link_id)
+        })?,
+        ProgramType::SockOps => cgroup_link(raw_program, |p: &mut SockOps| {
+            p.load()?;
+            let link_id = p.attach(cgroup, mode)?;
+            p.take_link(link_id)
+        })?,
+        ProgramType::CgroupSysctl => cgroup_link(raw_program, |p: &mut CgroupSysctl| {
+            p.load()?;
+            let link_id = p.attach(cgroup, mode)?;
+            p.take_link(link_id)
+        })?,
+        ProgramType::CgroupDevice => cgroup_link(raw_program, |p: &mut CgroupDevice| {
+            p.load()?;
+            let link_id = p.attach(cgroup, mode)?;
+            p.take_link(link_id)
+        })?,
+        t => {
+            return Err(BpfdError::Error(format!(
+                "{t} is not a cgroup program type"
+            )))
+        }
+    };
+
+    program
+        .data
+        .set_kernel_info(Some(raw_program.info()?.try_into()?));
+    link.pin(format!("{RTDIR_FS}/prog_{id}_link"))
+        .map_err(BpfdError::UnableToPinLink)?;
+    raw_program
+        .pin(format!("{RTDIR_FS}/prog_{id}"))
+        .map_err(BpfdError::UnableToPinProgram)?;
+
+    Ok(id)
+}
+
+// reattach_cgroup_program attaches a cgroup program that is still pinned, but
+// whose link went away while bpfd was not running, back to its cgroup and
+// pins the new link.
+fn reattach_cgroup_program(program: &CgroupProgram, id: Uuid) -> Result<(), BpfdError> {
+    let cgroup = std::fs::File::open(&program.cgroup_path)
+        .map_err(|_| BpfdError::InvalidAttach(program.cgroup_path.to_string()))?;
+    let mode = CgroupAttachMode::AllowMultiple;
+    let prog_path = format!("{RTDIR_FS}/prog_{id}");
+
+    let link = match program.attach_type.program_type() {
+        ProgramType::CgroupSkb => {
+            let attach_type = cgroup_skb_attach_type(program.attach_type)?;
+            let mut p = CgroupSkb::from_pin(prog_path, attach_type)?;
+            pinned_cgroup_link(&mut p, |p| {
+                let link_id = p.attach(cgroup, attach_type, mode)?;
+                p.take_link(link_id)
+            })?
+        }
+        ProgramType::CgroupSock => {
+            let attach_type = cgroup_sock_attach_type(program.attach_type)?;
+            let mut p = CgroupSock::from_pin(prog_path, attach_type)?;
+            pinned_cgroup_link(&mut p, |p| {
+                let link_id = p.attach(cgroup, mode)?;
+                p.take_link(link_id)
+            })?
+        }
+        ProgramType::CgroupSockAddr => {
+            let attach_type = cgroup_sock_addr_attach_type(program.attach_type)?;
+            let mut p = CgroupSockAddr::from_pin(prog_path, attach_type)?;
+            pinned_cgroup_link(&mut p, |p| {
+                let link_id = p.attach(cgroup, mode)?;
+                p.take_link(link_id)
+            })?
+        }
+        ProgramType::SockOps => pinned_cgroup_link(&mut SockOps::from_pin(prog_path)?, |p| {
+            let link_id = p.attach(cgroup, mode)?;
+            p.take_link(link_id)
+        })?,
+        ProgramType::CgroupSysctl => {
+            pinned_cgroup_link(&mut CgroupSysctl::from_pin(prog_path)?, |p| {
+                let link_id = p.attach(cgroup, mode)?;
+                p.take_link(link_id)
+            })?
+        }
+        ProgramType::CgroupDevice => {
+            pinned_cgroup_link(&mut CgroupDevice::from_pin(prog_path)?, |p| {
+                let link_id = p.attach(cgroup, mode)?;
+                p.take_link(link_id)
+            })?
+        }
+        t => {
+            return Err(BpfdError::Error(format!(
+                "{t} is not a cgroup program type"
+            )))
+        }
+    };
+
+    link.pin(format!("{RTDIR_FS}/prog_{id}_link"))
+        .map_err(BpfdError::UnableToPinLink)?;
+
+    Ok(())
+}
+
+// cgroup_link runs attach on raw_program as a P and returns the bpf_link the
+// program was attached with.
+fn cgroup_link<P, L>(
+    raw_program: &mut aya::programs::Program,
+    attach: impl FnOnce(&mut P) -> Result<L, ProgramError>,
+) -> Result<FdLink, BpfdError>
+where
+    for<'a> &'a mut P: TryFrom<&'a mut aya::programs::Program, Error = ProgramError>,
+    L: TryInto<FdLink>,
+{
+    pinned_cgroup_link(raw_program.try_into()?, attach)
+}
+
+// pinned_cgroup_link runs attach on an already typed cgroup program and
+// returns the bpf_link the program was attached with.
+fn pinned_cgroup_link<P, L>(
+    program: &mut P,
+    attach: impl FnOnce(&mut P) -> Result<L, ProgramError>,
+) -> Result<FdLink, BpfdError>
+where
+    L: TryInto<FdLink>,
+{
+    attach(program)?
+        .try_into()
+        .map_err(|_| BpfdError::Error("cgroup link is not a bpf_link".to_string()))
+}
+
+fn cgroup_skb_attach_type(t: CgroupAttachType) -> Result<CgroupSkbAttachType, BpfdError> {
+    match t {
+        CgroupAttachType::Ingress => Ok(CgroupSkbAttachType::Ingress),
+        CgroupAttachType::Egress => Ok(CgroupSkbAttachType::Egress),
+        t => Err(BpfdError::InvalidAttach(t.to_string())),
+    }
+}
+
+fn cgroup_sock_attach_type(t: CgroupAttachType) -> Result<CgroupSockAttachType, BpfdError> {
+    match t {
+        CgroupAttachType::SockCreate => Ok(CgroupSockAttachType::SockCreate),
+        CgroupAttachType::SockRelease => Ok(CgroupSockAttachType::SockRelease),
+        CgroupAttachType::PostBind4 => Ok(CgroupSockAttachType::PostBind4),
+        CgroupAttachType::PostBind6 => Ok(CgroupSockAttachType::PostBind6),
+        t => Err(BpfdError::InvalidAttach(t.to_string())),
+    }
+}
+
+fn cgroup_sock_addr_attach_type(
+    t: CgroupAttachType,
+) -> Result<CgroupSockAddrAttachType, BpfdError> {
+    match t {
+        CgroupAttachType::Bind4 => Ok(CgroupSockAddrAttachType::Bind4),
+        CgroupAttachType::Bind6 => Ok(CgroupSockAddrAttachType::Bind6),
+        CgroupAttachType::Connect4 => Ok(CgroupSockAddrAttachType::Connect4),
+        CgroupAttachType::Connect6 => Ok(CgroupSockAddrAttachType::Connect6),
+        CgroupAttachType::GetPeerName4 => Ok(CgroupSockAddrAttachType::GetPeerName4),
+        CgroupAttachType::GetPeerName6 => Ok(CgroupSockAddrAttachType::GetPeerName6),
+        CgroupAttachType::GetSockName4 => Ok(CgroupSockAddrAttachType::GetSockName4),
+        CgroupAttachType::GetSockName6 => Ok(CgroupSockAddrAttachType::GetSockName6),
+        CgroupAttachType::UdpSendMsg4 => Ok(CgroupSockAddrAttachType::UDPSendMsg4),
+        CgroupAttachType::UdpSendMsg6 => Ok(CgroupSockAddrAttachType::UDPSendMsg6),
+        CgroupAttachType::UdpRecvMsg4 => Ok(CgroupSockAddrAttachType::UDPRecvMsg4),
+        CgroupAttachType::UdpRecvMsg6 => Ok(CgroupSockAddrAttachType::UDPRecvMsg6),
+        t => Err(BpfdError::InvalidAttach(t.to_string())),
+    }
+}
+
+// attach_tcx_program loads a TC program and attaches it with a tcx link in
+// front of anchor, or at the end of the chain when there is no anchor. The
+// link is pinned, unpinning it on unload detaches the program.
+fn attach_tcx_program(
+    raw_program: &mut aya::programs::Program,
+    program: &mut TcProgram,
+    anchor: Option<Uuid>,
+    id: Uuid,
+) -> Result<Uuid, BpfdError> {
+    let attach_type = match program.direction {
+        Direction::Ingress => TcAttachType::Ingress,
+        Direction::Egress => TcAttachType::Egress,
+    };
+    let order = match anchor {
+        Some(next) => {
+            let next = SchedClassifier::from_pin(format!("{RTDIR_FS}/prog_{next}"))?;
+            LinkOrder::before_program(&next)?
+        }
+        None => LinkOrder::last(),
+    };
+
+    let classifier: &mut SchedClassifier = raw_program.try_into()?;
+    classifier.load()?;
+    program
+        .data
+        .set_kernel_info(Some(classifier.program_info()?.try_into()?));
+
+    let link_id = in_netns(program.netns.as_ref(), || {
+        classifier.attach_with_options(
+            &program.iface,
+            attach_type,
+            TcAttachOptions::TcxOrder(order),
+        )
+    })??;
+    let fd_link: FdLink = classifier
+        .take_link(link_id)?
+        .try_into()
+        .map_err(|_| BpfdError::Error("tc link is not a tcx link".to_string()))?;
+    fd_link
+        .pin(format!("{RTDIR_FS}/prog_{id}_link"))
+        .map_err(BpfdError::UnableToPinLink)?;
+
+    classifier
+        .pin(format!("{RTDIR_FS}/prog_{id}"))
+        .map_err(BpfdError::UnableToPinProgram)?;
+
+    program.tcx = true;
+    program.attached = true;
+
+    Ok(id)
+}
+
+// attach_perf_event_program loads a perf_event program and attaches it to one
+// perf event per CPU, each with its own pinned link. Unpinning the links on
+// unload detaches the program from all of them.
+fn attach_perf_event_program(
+    raw_program: &mut aya::programs::Program,
+    program: &mut PerfEventProgram,
+    id: Uuid,
+) -> Result<Uuid, BpfdError> {
+    let perf_type = match program.event_type {
+        PerfEventType::Hardware => PerfTypeId::Hardware,
+        PerfEventType::Software => PerfTypeId::Software,
+        PerfEventType::Tracepoint => PerfTypeId::TracePoint,
+        PerfEventType::HwCache => PerfTypeId::HwCache,
+        PerfEventType::Raw => PerfTypeId::Raw,
+        PerfEventType::Breakpoint => PerfTypeId::Breakpoint,
+    };
+    let sample_policy = match program.sample_policy {
+        PerfEventSamplePolicy::Period(p) => SamplePolicy::Period(p),
+        PerfEventSamplePolicy::Frequency(f) => SamplePolicy::Frequency(f),
+    };
+
+    let perf_event: &mut PerfEvent = raw_program.try_into()?;
+    perf_event.load()?;
+    program
+        .data
+        .set_kernel_info(Some(perf_event.program_info()?.try_into()?));
+
+    // A pid without any cpus follows the process wherever it runs, which
+    // only needs a single event.
+    if let (Some(pid), true) = (program.pid, program.cpus.is_empty()) {
+        let link_id = perf_event.attach(
+            perf_type,
+            program.config,
+            PerfEventScope::OneProcessAnyCpu { pid },
+            sample_policy,
+            false,
+        )?;
+        let fd_link: FdLink = perf_event
+            .take_link(link_id)?
+            .try_into()
+            .map_err(|_| BpfdError::Error("perf event link is not a bpf_link".to_string()))?;
+        fd_link
+            .pin(format!("{RTDIR_FS}/prog_{id}_link"))
+            .map_err(BpfdError::UnableToPinLink)?;
+    } else {
+        let cpus = if program.cpus.is_empty() {
+            online_cpus().map_err(|e| BpfdError::Error(format!("unable to get cpus: {e:?}")))?
+        } else {
+            program.cpus.clone()
+        };
+
+        for cpu in cpus {
+            let scope = match program.pid {
+                Some(pid) => PerfEventScope::OneProcessOneCpu { cpu, pid },
+                None => PerfEventScope::AllProcessesOneCpu { cpu },
+            };
+            let link_id =
+                perf_event.attach(perf_type, program.config, scope, sample_policy, false)?;
+            let fd_link: FdLink = perf_event
+                .take_link(link_id)?
+                .try_into()
+                .map_err(|_| BpfdError::Error("perf event link is not a bpf_link".to_string()))?;
+            fd_link
+                .pin(format!("{RTDIR_FS}/prog_{id}_link_{cpu}"))
+                .map_err(BpfdError::UnableToPinLink)?;
+            program.attached_cpus.push(cpu);
+        }
+    }
+
+    perf_event
+        .pin(format!("{RTDIR_FS}/prog_{id}"))
+        .map_err(BpfdError::UnableToPinProgram)?;
+
+    Ok(id)
+}
+
+// attach_socket_program loads and attaches sk_msg, sk_skb, sk_lookup,
+// socket_filter and flow_dissector programs. sk_msg and sk_skb programs are
+// attached to a sockmap or sockhash with BPF_PROG_ATTACH and stay attached for
+// as long as the map exists, so unlike sk_lookup there is no link to pin for
+// them. socket_filter programs are only loaded and pinned, applications
+// attach them to their own sockets from the program pin.
+fn attach_socket_program(
+    raw_program: &mut aya::programs::Program,
+    p: &mut Program,
+    id: Uuid,
+) -> Result<Uuid, BpfdError> {
+    let prog_path = format!("{RTDIR_FS}/prog_{id}");
+
+    match p {
+        Program::SkMsg(program) => {
+            let map_path = sock_map_pin_path(
+                id,
+                program.map_owner_id.or(program.data.map_owner_id()),
+                &program.map_name,
+            );
+            let sk_msg: &mut SkMsg = raw_program.try_into()?;
+            sk_msg.load()?;
+            program
+                .data
+                .set_kernel_info(Some(sk_msg.program_info()?.try_into()?));
+
+            sock_map_prog(
+                BPF_PROG_ATTACH,
+                &map_path,
+                sk_msg.fd()?.as_fd(),
+                BPF_SK_MSG_VERDICT,
+            )?;
+
+            sk_msg
+                .pin(prog_path)
+                .map_err(BpfdError::UnableToPinProgram)?;
+        }
+        Program::SkSkb(program) => {
+            let map_path = sock_map_pin_path(
+                id,
+                program.map_owner_id.or(program.data.map_owner_id()),
+                &program.map_name,
+            );
+            let sk_skb: &mut SkSkb = raw_program.try_into()?;
+            sk_skb.load()?;
+            program
+                .data
+                .set_kernel_info(Some(sk_skb.program_info()?.try_into()?));
+
+            sock_map_prog(
+                BPF_PROG_ATTACH,
+                &map_path,
+                sk_skb.fd()?.as_fd(),
+                sk_skb_attach_type(program.attach_type),
+            )?;
+
+            sk_skb
+                .pin(prog_path)
+                .map_err(BpfdError::UnableToPinProgram)?;
+        }
+        Program::SkLookup(program) => {
+            let netns = std::fs::File::open(&program.netns)
+                .map_err(|_| BpfdError::InvalidAttach(program.netns.to_string()))?;
+            let sk_lookup: &mut SkLookup = raw_program.try_into()?;
+            sk_lookup.load()?;
+            program
+                .data
+                .set_kernel_info(Some(sk_lookup.program_info()?.try_into()?));
+
+            let link_id = sk_lookup.attach(netns)?;
+            let fd_link: FdLink = sk_lookup.take_link(link_id)?.into();
+            fd_link
+                .pin(format!("{RTDIR_FS}/prog_{id}_link"))
+                .map_err(BpfdError::UnableToPinLink)?;
+
+            sk_lookup
+                .pin(prog_path)
+                .map_err(BpfdError::UnableToPinProgram)?;
+        }
+        Program::SocketFilter(program) => {
+            let socket_filter: &mut SocketFilter = raw_program.try_into()?;
+            socket_filter.load()?;
+            program
+                .data
+                .set_kernel_info(Some(socket_filter.program_info()?.try_into()?));
+
+            socket_filter
+                .pin(prog_path)
+                .map_err(BpfdError::UnableToPinProgram)?;
+        }
+        Program::FlowDissector(program) => {
+            let netns = std::fs::File::open(&program.netns)
+                .map_err(|_| BpfdError::InvalidAttach(program.netns.to_string()))?;
+            let flow_dissector: &mut FlowDissector = raw_program.try_into()?;
+            flow_dissector.load()?;
+            program
+                .data
+                .set_kernel_info(Some(flow_dissector.program_info()?.try_into()?));
+
+            let link_id = flow_dissector.attach(netns)?;
+            let fd_link: FdLink = flow_dissector.take_link(link_id)?.into();
+            fd_link
+                .pin(format!("{RTDIR_FS}/prog_{id}_link"))
+                .map_err(BpfdError::UnableToPinLink)?;
+
+            flow_dissector
+                .pin(prog_path)
+                .map_err(BpfdError::UnableToPinProgram)?;
+        }
+        _ => panic!("not a socket program"),
+    }
+
+    Ok(id)
+}
+
+// detach_sock_map_program detaches a sk_msg or sk_skb program from the map it
+// was attached to, using the pinned program and the map.
+fn detach_sock_map_program(id: Uuid, program: &Program) -> Result<(), BpfdError> {
+    let prog_path = format!("{RTDIR_FS}/prog_{id}");
+
+    match program {
+        Program::SkMsg(p) => {
+            let map_path =
+                sock_map_pin_path(id, p.map_owner_id.or(p.data.map_owner_id()), &p.map_name);
+            let sk_msg = SkMsg::from_pin(prog_path)?;
+            sock_map_prog(
+                BPF_PROG_DETACH,
+                &map_path,
+                sk_msg.fd()?.as_fd(),
+                BPF_SK_MSG_VERDICT,
+            )?;
+        }
+        Program::SkSkb(p) => {
+            let map_path =
+                sock_map_pin_path(id, p.map_owner_id.or(p.data.map_owner_id()), &p.map_name);
+            let kind = match p.attach_type {
+                SkSkbAttachType::StreamParser => SkSkbKind::StreamParser,
+                SkSkbAttachType::StreamVerdict => SkSkbKind::StreamVerdict,
+            };
+            let sk_skb = SkSkb::from_pin(prog_path, kind)?;
+            sock_map_prog(
+                BPF_PROG_DETACH,
+                &map_path,
+                sk_skb.fd()?.as_fd(),
+                sk_skb_attach_type(p.attach_type),
+            )?;
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+fn sk_skb_attach_type(attach_type: SkSkbAttachType) -> u32 {
+    match attach_type {
+        SkSkbAttachType::StreamParser => BPF_SK_SKB_STREAM_PARSER,
+        SkSkbAttachType::StreamVerdict => BPF_SK_SKB_STREAM_VERDICT,
+    }
+}
+
+// sock_map_prog runs BPF_PROG_ATTACH or BPF_PROG_DETACH for the program
+// prog_fd on the sockmap or sockhash pinned at map_path. Such attachments
+// belong to the map, so unlike a link nothing has to be kept open for the
+// program to stay attached, and the program and map fds are all that is
+// needed to detach it again, also after bpfd was restarted. aya's attach
+// can't be used for this, as it detaches the program again once the loaded
+// object goes away.
+fn sock_map_prog(
+    cmd: libc::c_int,
+    map_path: &Path,
+    prog_fd: BorrowedFd<'_>,
+    attach_type: u32,
+) -> Result<(), BpfdError> {
+    with_sock_map(map_path, |map_fd| {
+        let mut attr = ProgAttachAttr {
+            target_fd: map_fd.as_raw_fd() as u32,
+            attach_bpf_fd: prog_fd.as_raw_fd() as u32,
+            attach_type,
+            attach_flags: 0,
+        };
+        sys_bpf(cmd, &mut attr)
+    })?
+    .map(|_| ())
+    .map_err(|e| BpfdError::Error(format!("unable to attach or detach program: {e}")))
+}
+
+// sock_map_pin_path returns where the sockmap or sockhash named map_name is
+// pinned. The map lives under the map pin path of map_owner_id, or under the
+// program's own map pin path when no owner is given.
+fn sock_map_pin_path(id: Uuid, map_owner_id: Option<Uuid>, map_name: &str) -> PathBuf {
+    let (_, map_pin_path) = calc_map_pin_path(id, map_owner_id);
+    map_pin_path.join(map_name)
+}
+
+// with_sock_map opens the map pinned at path and hands its fd to f. Anything
+// other than a sockmap or sockhash is rejected before f is called.
+fn with_sock_map<T>(path: &Path, f: impl FnOnce(BorrowedFd<'_>) -> T) -> Result<T, BpfdError> {
+    let map = MapData::from_pin(path)?;
+    match map.info()?.map_type() {
+        Ok(MapType::SockMap | MapType::SockHash) => Ok(f(map.fd().as_fd())),
+        _ => Err(BpfdError::NotASockMap(path.display().to_string())),
+    }
+}
+
 // map_index is a UUID. It is either the programs UUID, or the UUID
 // of another program that map_owner_uuid references.
 // This function also returns a bool, which indicates if the input UUID
@@ -888,6 +2647,29 @@ pub fn calc_map_pin_path(id: Uuid, map_owner_uuid: Option<Uuid>) -> (bool, PathB
     )
 }
 
+// iface_up returns the if_index of iface, if it exists and is up.
+async fn iface_up(iface: &str) -> Option<u32> {
+    let if_index = if_nametoindex(iface).ok()?;
+    match netlink::link_state(if_index).await {
+        Ok(up) => up.filter(|up| *up).map(|_| if_index),
+        Err(e) => {
+            warn!("unable to get the state of {iface}: {e}");
+            None
+        }
+    }
+}
+
+// cached_iface_up is iface_up, asking the kernel only once per iface for all
+// the programs of one sync.
+async fn cached_iface_up(up: &mut HashMap<String, Option<u32>>, iface: &str) -> Option<u32> {
+    if let Some(if_index) = up.get(iface) {
+        return *if_index;
+    }
+    let if_index = iface_up(iface).await;
+    up.insert(iface.to_string(), if_index);
+    if_index
+}
+
 #[cfg(test)]
 mod tests {
     use uuid::{uuid, Uuid};
@@ -962,4 +2744,18 @@ mod tests {
             assert_eq!(map_pin_path, t.o_map_pin_path);
         }
     }
+
+    #[test]
+    fn test_sock_map_pin_path() {
+        const UUID_1: Uuid = uuid!("67e55044-10b1-426f-9247-bb680e5fe0c8");
+        const UUID_2: Uuid = uuid!("084282a5-a43f-41c3-8f85-c302dc90e091");
+        assert_eq!(
+            sock_map_pin_path(UUID_1, None, "sock_ops_map"),
+            PathBuf::from(format!("{RTDIR_FS_MAPS}/{UUID_1}/sock_ops_map"))
+        );
+        assert_eq!(
+            sock_map_pin_path(UUID_2, Some(UUID_1), "sock_ops_map"),
+            PathBuf::from(format!("{RTDIR_FS_MAPS}/{UUID_1}/sock_ops_map"))
+        );
+    }
 }
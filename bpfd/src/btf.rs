@@ -0,0 +1,854 @@
+// SPDX-License-Identifier: (MIT OR Apache-2.0)
+// Copyright Authors of bpfd
+
+//! The BTF of the keys and values of maps, used to show map entries as JSON
+//! the way `bpftool map dump` does. The kernel keeps the BTF a map was
+//! created with, and the ids of its key and value types in it, so the types
+//! are looked up there whenever a map is opened.
+//!
+//! aya-obj can parse BTF, but it keeps the types it parsed to itself, so
+//! their members, sizes and encodings can't be walked from outside of it.
+//! Only the type section is parsed here, into descriptions of the key and
+//! value types that the rest of this module decodes and encodes.
+use std::{
+    io, mem,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+use nix::libc;
+use serde_json::{json, Map, Value};
+
+use crate::{errors::BpfdError, utils::sys_bpf};
+
+const BPF_OBJ_GET_INFO_BY_FD: libc::c_int = 15;
+const BPF_BTF_GET_FD_BY_ID: libc::c_int = 19;
+
+const BTF_MAGIC: u16 = 0xeb9f;
+const HEADER_LEN: usize = 24;
+
+const BTF_KIND_INT: u32 = 1;
+const BTF_KIND_PTR: u32 = 2;
+const BTF_KIND_ARRAY: u32 = 3;
+const BTF_KIND_STRUCT: u32 = 4;
+const BTF_KIND_UNION: u32 = 5;
+const BTF_KIND_ENUM: u32 = 6;
+const BTF_KIND_FWD: u32 = 7;
+const BTF_KIND_TYPEDEF: u32 = 8;
+const BTF_KIND_VOLATILE: u32 = 9;
+const BTF_KIND_CONST: u32 = 10;
+const BTF_KIND_RESTRICT: u32 = 11;
+const BTF_KIND_FUNC: u32 = 12;
+const BTF_KIND_FUNC_PROTO: u32 = 13;
+const BTF_KIND_VAR: u32 = 14;
+const BTF_KIND_DATASEC: u32 = 15;
+const BTF_KIND_FLOAT: u32 = 16;
+const BTF_KIND_DECL_TAG: u32 = 17;
+const BTF_KIND_TYPE_TAG: u32 = 18;
+const BTF_KIND_ENUM64: u32 = 19;
+
+const BTF_INT_SIGNED: u32 = 1;
+const BTF_INT_BOOL: u32 = 4;
+
+// Types nest this deep at most, which also stops types that refer to
+// themselves other than through a pointer.
+const MAX_DEPTH: usize = 32;
+
+/// How to decode a key or value, resolved from BTF.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TypeDesc {
+    Int {
+        size: u32,
+        signed: bool,
+        bool: bool,
+    },
+    Ptr,
+    Float {
+        size: u32,
+    },
+    Array {
+        elem: Box<TypeDesc>,
+        elem_size: u32,
+        len: u32,
+    },
+    Struct {
+        size: u32,
+        members: Vec<Member>,
+    },
+    Enum {
+        size: u32,
+        signed: bool,
+        values: Vec<(String, i64)>,
+    },
+    /// Anything else is shown as its bytes.
+    Bytes {
+        size: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Member {
+    name: String,
+    bit_offset: u32,
+    // Only set for bitfields.
+    bit_size: u32,
+    ty: TypeDesc,
+}
+
+/// The types of the keys and values of a map.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MapBtf {
+    pub(crate) key: TypeDesc,
+    pub(crate) value: TypeDesc,
+}
+
+// The part of struct bpf_map_info up to the BTF of the map.
+#[repr(C, align(8))]
+#[derive(Default)]
+#[allow(dead_code)]
+struct BpfMapInfo {
+    map_type: u32,
+    id: u32,
+    key_size: u32,
+    value_size: u32,
+    max_entries: u32,
+    map_flags: u32,
+    name: [u8; 16],
+    ifindex: u32,
+    btf_vmlinux_value_type_id: u32,
+    netns_dev: u64,
+    netns_ino: u64,
+    btf_id: u32,
+    btf_key_type_id: u32,
+    btf_value_type_id: u32,
+}
+
+// struct bpf_btf_info
+#[repr(C, align(8))]
+#[derive(Default)]
+#[allow(dead_code)]
+struct BpfBtfInfo {
+    btf: u64,
+    btf_size: u32,
+    id: u32,
+    name: u64,
+    name_len: u32,
+    kernel_btf: u32,
+}
+
+// The part of union bpf_attr used by BPF_OBJ_GET_INFO_BY_FD.
+#[repr(C, align(8))]
+#[derive(Default)]
+#[allow(dead_code)]
+struct InfoByFdAttr {
+    bpf_fd: u32,
+    info_len: u32,
+    info: u64,
+}
+
+// The part of union bpf_attr used by BPF_BTF_GET_FD_BY_ID.
+#[repr(C)]
+#[derive(Default)]
+#[allow(dead_code)]
+struct GetFdByIdAttr {
+    id: u32,
+    next_id: u32,
+    open_flags: u32,
+}
+
+struct BtfType {
+    name_off: u32,
+    kind: u32,
+    kind_flag: bool,
+    // The size of the type, or the type it refers to.
+    size_or_type: u32,
+    // What follows struct btf_type.
+    extra: Vec<u32>,
+}
+
+struct Btf {
+    types: Vec<BtfType>,
+    strings: Vec<u8>,
+}
+
+/// Returns the key and value types of map, if it was created with BTF.
+pub(crate) fn map_btf(map: BorrowedFd<'_>) -> Result<Option<MapBtf>, BpfdError> {
+    let mut info = BpfMapInfo::default();
+    obj_info(map, &mut info)
+        .map_err(|e| BpfdError::Error(format!("can't get info of map: {e}")))?;
+    if info.btf_id == 0 || info.btf_value_type_id == 0 {
+        return Ok(None);
+    }
+    let btf = Btf::parse(&kernel_btf(info.btf_id)?)?;
+    Ok(Some(MapBtf {
+        key: btf.desc(info.btf_key_type_id, 0),
+        value: btf.desc(info.btf_value_type_id, 0),
+    }))
+}
+
+// kernel_btf returns the raw data of the BTF object id, as the kernel keeps it.
+fn kernel_btf(id: u32) -> Result<Vec<u8>, BpfdError> {
+    let error = |e: io::Error| BpfdError::Error(format!("can't read BTF {id}: {e}"));
+    let mut attr = GetFdByIdAttr {
+        id,
+        ..Default::default()
+    };
+    let fd = sys_bpf(BPF_BTF_GET_FD_BY_ID, &mut attr).map_err(error)?;
+    // SAFETY: BPF_BTF_GET_FD_BY_ID returns a new file descriptor that nothing
+    // else owns.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) };
+
+    // The first call only gets the size of the BTF.
+    let mut info = BpfBtfInfo::default();
+    obj_info(fd.as_fd(), &mut info).map_err(error)?;
+    let mut data = vec![0u8; info.btf_size as usize];
+    let mut info = BpfBtfInfo {
+        btf: data.as_mut_ptr() as u64,
+        btf_size: data.len() as u32,
+        ..Default::default()
+    };
+    obj_info(fd.as_fd(), &mut info).map_err(error)?;
+    Ok(data)
+}
+
+// obj_info fills info, a struct bpf_*_info, for the BPF object fd.
+fn obj_info<T>(fd: BorrowedFd<'_>, info: &mut T) -> io::Result<()> {
+    let mut attr = InfoByFdAttr {
+        bpf_fd: fd.as_raw_fd() as u32,
+        info_len: mem::size_of::<T>() as u32,
+        info: info as *mut T as u64,
+    };
+    sys_bpf(BPF_OBJ_GET_INFO_BY_FD, &mut attr)?;
+    Ok(())
+}
+
+impl Btf {
+    fn parse(data: &[u8]) -> Result<Self, BpfdError> {
+        let invalid = |what: &str| BpfdError::Error(format!("invalid BTF: {what}"));
+        if data.len() < HEADER_LEN {
+            return Err(invalid("too short"));
+        }
+        let big_endian = match [data[0], data[1]] {
+            m if u16::from_le_bytes(m) == BTF_MAGIC => false,
+            m if u16::from_be_bytes(m) == BTF_MAGIC => true,
+            _ => return Err(invalid("bad magic")),
+        };
+        let word = |off: usize| -> Option<u32> {
+            let b: [u8; 4] = data.get(off..off + 4)?.try_into().ok()?;
+            Some(if big_endian {
+                u32::from_be_bytes(b)
+            } else {
+                u32::from_le_bytes(b)
+            })
+        };
+        let hdr_len = word(4).unwrap() as usize;
+        let section = |off: usize, len: usize| {
+            let start = hdr_len + word(off).unwrap() as usize;
+            let len = word(len).unwrap() as usize;
+            data.get(start..start + len)
+                .map(|_| (start, start + len))
+                .ok_or_else(|| invalid("section out of bounds"))
+        };
+        let (types_start, types_end) = section(8, 12)?;
+        let (str_start, str_end) = section(16, 20)?;
+
+        let mut types = vec![];
+        let mut off = types_start;
+        while off < types_end {
+            let mut next = || {
+                let w = word(off).filter(|_| off + 4 <= types_end);
+                off += 4;
+                w.ok_or_else(|| invalid("type out of bounds"))
+            };
+            let name_off = next()?;
+            let info = next()?;
+            let size_or_type = next()?;
+            let kind = (info >> 24) & 0x1f;
+            let vlen = (info & 0xffff) as usize;
+            let extra_words = match kind {
+                BTF_KIND_INT | BTF_KIND_VAR | BTF_KIND_DECL_TAG => 1,
+                BTF_KIND_ARRAY => 3,
+                BTF_KIND_STRUCT | BTF_KIND_UNION | BTF_KIND_DATASEC | BTF_KIND_ENUM64 => 3 * vlen,
+                BTF_KIND_ENUM | BTF_KIND_FUNC_PROTO => 2 * vlen,
+                BTF_KIND_PTR | BTF_KIND_FWD | BTF_KIND_TYPEDEF | BTF_KIND_VOLATILE
+                | BTF_KIND_CONST | BTF_KIND_RESTRICT | BTF_KIND_FUNC | BTF_KIND_FLOAT
+                | BTF_KIND_TYPE_TAG => 0,
+                _ => return Err(invalid(&format!("unknown kind {kind}"))),
+            };
+            let extra = (0..extra_words)
+                .map(|_| next())
+                .collect::<Result<Vec<_>, _>>()?;
+            types.push(BtfType {
+                name_off,
+                kind,
+                kind_flag: info >> 31 == 1,
+                size_or_type,
+                extra,
+            });
+        }
+
+        Ok(Btf {
+            types,
+            strings: data[str_start..str_end].to_vec(),
+        })
+    }
+
+    fn get(&self, id: u32) -> Option<&BtfType> {
+        // Type 0 is void.
+        self.types.get((id as usize).checked_sub(1)?)
+    }
+
+    fn name(&self, off: u32) -> String {
+        let s = self.strings.get(off as usize..).unwrap_or_default();
+        let end = s.iter().position(|b| *b == 0).unwrap_or(s.len());
+        String::from_utf8_lossy(&s[..end]).to_string()
+    }
+
+    fn skip_modifiers(&self, mut id: u32) -> u32 {
+        for _ in 0..MAX_DEPTH {
+            match self.get(id) {
+                Some(t)
+                    if matches!(
+                        t.kind,
+                        BTF_KIND_TYPEDEF
+                            | BTF_KIND_VOLATILE
+                            | BTF_KIND_CONST
+                            | BTF_KIND_RESTRICT
+                            | BTF_KIND_TYPE_TAG
+                    ) =>
+                {
+                    id = t.size_or_type
+                }
+                _ => break,
+            }
+        }
+        id
+    }
+
+    fn size(&self, id: u32, depth: usize) -> u32 {
+        let id = self.skip_modifiers(id);
+        let Some(t) = self.get(id) else { return 0 };
+        match t.kind {
+            BTF_KIND_PTR => 8,
+            BTF_KIND_ARRAY if depth < MAX_DEPTH => {
+                self.size(t.extra[0], depth + 1).saturating_mul(t.extra[2])
+            }
+            BTF_KIND_INT | BTF_KIND_STRUCT | BTF_KIND_UNION | BTF_KIND_ENUM | BTF_KIND_ENUM64
+            | BTF_KIND_FLOAT => t.size_or_type,
+            _ => 0,
+        }
+    }
+
+    fn desc(&self, id: u32, depth: usize) -> TypeDesc {
+        let id = self.skip_modifiers(id);
+        let Some(t) = self.get(id).filter(|_| depth < MAX_DEPTH) else {
+            return TypeDesc::Bytes {
+                size: self.size(id, 0),
+            };
+        };
+        match t.kind {
+            BTF_KIND_INT => {
+                let encoding = (t.extra[0] >> 24) & 0xf;
+                TypeDesc::Int {
+                    size: t.size_or_type,
+                    signed: encoding & BTF_INT_SIGNED != 0,
+                    bool: encoding & BTF_INT_BOOL != 0,
+                }
+            }
+            BTF_KIND_PTR => TypeDesc::Ptr,
+            BTF_KIND_FLOAT => TypeDesc::Float {
+                size: t.size_or_type,
+            },
+            BTF_KIND_ARRAY => TypeDesc::Array {
+                elem: Box::new(self.desc(t.extra[0], depth + 1)),
+                elem_size: self.size(t.extra[0], 0),
+                len: t.extra[2],
+            },
+            BTF_KIND_STRUCT | BTF_KIND_UNION => TypeDesc::Struct {
+                size: t.size_or_type,
+                members: t
+                    .extra
+                    .chunks(3)
+                    .map(|m| {
+                        let (bit_offset, bit_size) = if t.kind_flag {
+                            (m[2] & 0xffffff, m[2] >> 24)
+                        } else {
+                            (m[2], 0)
+                        };
+                        Member {
+                            name: self.name(m[0]),
+                            bit_offset,
+                            bit_size,
+                            ty: self.desc(m[1], depth + 1),
+                        }
+                    })
+                    .collect(),
+            },
+            BTF_KIND_ENUM => TypeDesc::Enum {
+                size: t.size_or_type,
+                signed: t.kind_flag,
+                values: t
+                    .extra
+                    .chunks(2)
+                    .map(|v| {
+                        let value = if t.kind_flag {
+                            v[1] as i32 as i64
+                        } else {
+                            v[1] as i64
+                        };
+                        (self.name(v[0]), value)
+                    })
+                    .collect(),
+            },
+            BTF_KIND_ENUM64 => TypeDesc::Enum {
+                size: t.size_or_type,
+                signed: t.kind_flag,
+                values: t
+                    .extra
+                    .chunks(3)
+                    .map(|v| (self.name(v[0]), ((v[2] as u64) << 32 | v[1] as u64) as i64))
+                    .collect(),
+            },
+            _ => TypeDesc::Bytes {
+                size: self.size(id, 0),
+            },
+        }
+    }
+}
+
+impl TypeDesc {
+    fn size(&self) -> usize {
+        match self {
+            TypeDesc::Int { size, .. }
+            | TypeDesc::Float { size }
+            | TypeDesc::Struct { size, .. }
+            | TypeDesc::Enum { size, .. }
+            | TypeDesc::Bytes { size } => *size as usize,
+            TypeDesc::Ptr => 8,
+            TypeDesc::Array { elem_size, len, .. } => *elem_size as usize * *len as usize,
+        }
+    }
+
+    /// Decodes data, which holds a value of this type, into JSON.
+    pub(crate) fn to_json(&self, data: &[u8]) -> Value {
+        let Some(data) = data.get(..self.size()) else {
+            return Value::Null;
+        };
+        match self {
+            TypeDesc::Int { bool: true, .. } => json!(data.iter().any(|b| *b != 0)),
+            TypeDesc::Int { size, signed, .. } => int_json(read_uint(data), *size * 8, *signed),
+            TypeDesc::Ptr => int_json(read_uint(data), 64, false),
+            TypeDesc::Float { size: 4 } => json!(f32::from_ne_bytes(data.try_into().unwrap())),
+            TypeDesc::Float { size: 8 } => json!(f64::from_ne_bytes(data.try_into().unwrap())),
+            TypeDesc::Enum {
+                size,
+                signed,
+                values,
+            } => enum_json(read_uint(data), *size * 8, *signed, values),
+            TypeDesc::Array {
+                elem,
+                elem_size,
+                len,
+            } => {
+                // Like bpftool, arrays of bytes holding a string are shown
+                // as one.
+                if let TypeDesc::Int {
+                    size: 1,
+                    bool: false,
+                    ..
+                } = **elem
+                {
+                    if let Some(s) = c_string(data) {
+                        return json!(s);
+                    }
+                }
+                let elem_size = *elem_size as usize;
+                Value::Array(
+                    (0..*len as usize)
+                        .map(|i| elem.to_json(&data[i * elem_size..]))
+                        .collect(),
+                )
+            }
+            TypeDesc::Struct { members, .. } => {
+                let mut fields = Map::new();
+                for m in members {
+                    let value = if m.bit_size > 0 {
+                        m.bitfield_json(data)
+                    } else {
+                        data.get(m.bit_offset as usize / 8..)
+                            .map_or(Value::Null, |d| m.ty.to_json(d))
+                    };
+                    match value {
+                        // The fields of anonymous structs and unions are
+                        // shown as fields of the struct holding them.
+                        Value::Object(inner) if m.name.is_empty() => fields.extend(inner),
+                        value => {
+                            fields.insert(m.name.clone(), value);
+                        }
+                    }
+                }
+                Value::Object(fields)
+            }
+            TypeDesc::Float { .. } | TypeDesc::Bytes { .. } => {
+                json!(data.iter().map(|b| format!("{b:#04x}")).collect::<Vec<_>>())
+            }
+        }
+    }
+
+    /// Adds the integers of the value in other to those of the value in
+    /// data, as when summing the values of a per-CPU map. Bitfields and
+    /// anything that isn't an integer keep their value in data.
+    pub(crate) fn add(&self, data: &mut [u8], other: &[u8]) {
+        let size = self.size();
+        if data.len() < size || other.len() < size {
+            return;
+        }
+        match self {
+            TypeDesc::Int { bool: false, .. } => {
+                let sum = read_uint(&data[..size]).wrapping_add(read_uint(&other[..size]));
+                write_uint(&mut data[..size], sum);
+            }
+            TypeDesc::Array {
+                elem,
+                elem_size,
+                len,
+            } => {
+                let elem_size = *elem_size as usize;
+                for i in 0..*len as usize {
+                    elem.add(&mut data[i * elem_size..], &other[i * elem_size..]);
+                }
+            }
+            TypeDesc::Struct { members, .. } => {
+                for m in members.iter().filter(|m| m.bit_size == 0) {
+                    let start = m.bit_offset as usize / 8;
+                    m.ty.add(&mut data[start..], &other[start..]);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Member {
+    fn bitfield_json(&self, data: &[u8]) -> Value {
+        let start = self.bit_offset as usize / 8;
+        let shift = self.bit_offset % 8;
+        let len = (shift + self.bit_size + 7) as usize / 8;
+        let Some(bytes) = data.get(start..start + len).filter(|_| len <= 16) else {
+            return Value::Null;
+        };
+        let raw = (read_uint(bytes) >> shift) & (u128::MAX >> (128 - self.bit_size));
+        match &self.ty {
+            TypeDesc::Int { bool: true, .. } => json!(raw != 0),
+            TypeDesc::Int { signed, .. } => int_json(raw, self.bit_size, *signed),
+            TypeDesc::Enum { signed, values, .. } => enum_json(raw, self.bit_size, *signed, values),
+            _ => int_json(raw, self.bit_size, false),
+        }
+    }
+}
+
+// read_uint reads an integer of up to 16 bytes in host byte order.
+fn read_uint(data: &[u8]) -> u128 {
+    let mut b = [0u8; 16];
+    let n = data.len().min(16);
+    if cfg!(target_endian = "little") {
+        b[..n].copy_from_slice(&data[..n]);
+        u128::from_le_bytes(b)
+    } else {
+        b[16 - n..].copy_from_slice(&data[..n]);
+        u128::from_be_bytes(b)
+    }
+}
+
+// write_uint writes the low bytes of v to data, in host byte order.
+fn write_uint(data: &mut [u8], v: u128) {
+    let n = data.len().min(16);
+    if cfg!(target_endian = "little") {
+        data[..n].copy_from_slice(&v.to_le_bytes()[..n]);
+    } else {
+        data[..n].copy_from_slice(&v.to_be_bytes()[16 - n..]);
+    }
+}
+
+fn sign_extend(raw: u128, bits: u32) -> i128 {
+    match bits {
+        1..=127 => ((raw << (128 - bits)) as i128) >> (128 - bits),
+        _ => raw as i128,
+    }
+}
+
+// int_json shows integers that don't fit in 64 bits in hex, like bpftool.
+fn int_json(raw: u128, bits: u32, signed: bool) -> Value {
+    if signed {
+        let v = sign_extend(raw, bits);
+        i64::try_from(v).map_or_else(|_| json!(format!("{v:#x}")), |v| json!(v))
+    } else {
+        u64::try_from(raw).map_or_else(|_| json!(format!("{raw:#x}")), |v| json!(v))
+    }
+}
+
+fn enum_json(raw: u128, bits: u32, signed: bool, values: &[(String, i64)]) -> Value {
+    let v = if signed {
+        sign_extend(raw, bits) as i64
+    } else {
+        raw as i64
+    };
+    match values.iter().find(|(_, value)| *value == v) {
+        Some((name, _)) => json!(name),
+        None => int_json(raw, bits, signed),
+    }
+}
+
+// c_string returns a char array as a string, if it holds printable
+// characters up to a NUL.
+fn c_string(data: &[u8]) -> Option<String> {
+    let end = data.iter().position(|b| *b == 0).filter(|end| *end > 0)?;
+    let s = &data[..end];
+    s.iter()
+        .all(|b| b.is_ascii_graphic() || *b == b' ')
+        .then(|| String::from_utf8_lossy(s).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds little endian BTF, one type at a time.
+    struct Builder {
+        types: Vec<u32>,
+        strings: Vec<u8>,
+        count: u32,
+    }
+
+    impl Builder {
+        fn new() -> Self {
+            Builder {
+                types: vec![],
+                strings: vec![0],
+                count: 0,
+            }
+        }
+
+        fn str(&mut self, s: &str) -> u32 {
+            let off = self.strings.len() as u32;
+            self.strings.extend(s.as_bytes());
+            self.strings.push(0);
+            off
+        }
+
+        fn add(
+            &mut self,
+            name: &str,
+            kind: u32,
+            vlen: u32,
+            size_or_type: u32,
+            extra: &[u32],
+        ) -> u32 {
+            let name_off = if name.is_empty() { 0 } else { self.str(name) };
+            self.types
+                .extend([name_off, kind << 24 | vlen, size_or_type]);
+            self.types.extend(extra);
+            self.count += 1;
+            self.count
+        }
+
+        fn build(&self) -> Vec<u8> {
+            let type_len = 4 * self.types.len() as u32;
+            let mut btf = vec![0x9f, 0xeb, 1, 0];
+            for w in [24, 0, type_len, type_len, self.strings.len() as u32] {
+                btf.extend(w.to_le_bytes());
+            }
+            for w in &self.types {
+                btf.extend(w.to_le_bytes());
+            }
+            btf.extend(&self.strings);
+            btf
+        }
+    }
+
+    #[test]
+    fn test_bpf_info_layout() {
+        // The sizes in the kernel UAPI headers.
+        assert_eq!(mem::size_of::<BpfBtfInfo>(), 32);
+        assert_eq!(mem::size_of::<InfoByFdAttr>(), 16);
+        assert_eq!(mem::size_of::<GetFdByIdAttr>(), 12);
+        assert_eq!(mem::size_of::<BpfMapInfo>(), 80);
+    }
+
+    #[test]
+    fn test_map_types() {
+        let mut b = Builder::new();
+        let u32_t = b.add("unsigned int", BTF_KIND_INT, 0, 4, &[32]);
+        let u64_t = b.add("long long unsigned int", BTF_KIND_INT, 0, 8, &[64]);
+        let char_t = b.add("char", BTF_KIND_INT, 0, 1, &[BTF_INT_SIGNED << 24 | 8]);
+        let comm_t = b.add("", BTF_KIND_ARRAY, 0, 0, &[char_t, u32_t, 8]);
+        let (pass, drop) = (b.str("PASS"), b.str("DROP"));
+        let action_t = b.add("action", BTF_KIND_ENUM, 2, 4, &[pass, 0, drop, 1]);
+        let members = [
+            b.str("packets"),
+            u32_t,
+            0,
+            b.str("action"),
+            action_t,
+            32,
+            b.str("bytes"),
+            u64_t,
+            64,
+            b.str("comm"),
+            comm_t,
+            128,
+        ];
+        let stats_t = b.add("stats", BTF_KIND_STRUCT, 4, 24, &members);
+        let key_ptr = b.add("", BTF_KIND_PTR, 0, u32_t, &[]);
+        let value_ptr = b.add("", BTF_KIND_PTR, 0, stats_t, &[]);
+        let members = [b.str("key"), key_ptr, 0, b.str("value"), value_ptr, 64];
+        let def_t = b.add("", BTF_KIND_STRUCT, 2, 16, &members);
+        let var = b.add("counters", BTF_KIND_VAR, 0, def_t, &[1]);
+        b.add(".maps", BTF_KIND_DATASEC, 1, 16, &[var, 0, 16]);
+        let const_t = b.add("", BTF_KIND_CONST, 0, u64_t, &[]);
+        let typedef_t = b.add("__u64", BTF_KIND_TYPEDEF, 0, const_t, &[]);
+
+        // The kernel has the ids of the key and value types of a map.
+        let btf = Btf::parse(&b.build()).unwrap();
+        let counters = MapBtf {
+            key: btf.desc(u32_t, 0),
+            value: btf.desc(stats_t, 0),
+        };
+        assert_eq!(counters.key.to_json(&7u32.to_ne_bytes()), json!(7));
+        let mut value = vec![];
+        value.extend(42u32.to_ne_bytes());
+        value.extend(1u32.to_ne_bytes());
+        value.extend(1500u64.to_ne_bytes());
+        value.extend(b"bpfd\0\0\0\0");
+        assert_eq!(
+            counters.value.to_json(&value),
+            json!({"packets": 42, "action": "DROP", "bytes": 1500, "comm": "bpfd"})
+        );
+        // Too short for the type.
+        assert_eq!(counters.value.to_json(&value[..8]), Value::Null);
+
+        // Modifiers and typedefs are looked through.
+        assert_eq!(
+            btf.desc(typedef_t, 0).to_json(&u64::MAX.to_ne_bytes()),
+            json!(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_to_json() {
+        let bitfields = TypeDesc::Struct {
+            size: 4,
+            members: vec![
+                Member {
+                    name: "a".to_string(),
+                    bit_offset: 0,
+                    bit_size: 3,
+                    ty: TypeDesc::Int {
+                        size: 4,
+                        signed: false,
+                        bool: false,
+                    },
+                },
+                Member {
+                    name: "b".to_string(),
+                    bit_offset: 3,
+                    bit_size: 5,
+                    ty: TypeDesc::Int {
+                        size: 4,
+                        signed: true,
+                        bool: false,
+                    },
+                },
+            ],
+        };
+        assert_eq!(
+            bitfields.to_json(&[0b1010_1110, 0, 0, 0]),
+            json!({"a": 6, "b": -11})
+        );
+
+        let bytes = TypeDesc::Array {
+            elem: Box::new(TypeDesc::Int {
+                size: 1,
+                signed: false,
+                bool: false,
+            }),
+            elem_size: 1,
+            len: 4,
+        };
+        assert_eq!(bytes.to_json(&[0, 1, 2, 3]), json!([0, 1, 2, 3]));
+        assert_eq!(bytes.to_json(&[b'a', b'b', 0, 0]), json!("ab"));
+
+        let signed = TypeDesc::Int {
+            size: 2,
+            signed: true,
+            bool: false,
+        };
+        assert_eq!(signed.to_json(&(-2i16).to_ne_bytes()), json!(-2));
+
+        let wide = TypeDesc::Int {
+            size: 16,
+            signed: false,
+            bool: false,
+        };
+        assert_eq!(
+            wide.to_json(&(1u128 << 64).to_ne_bytes()),
+            json!("0x10000000000000000")
+        );
+    }
+
+    #[test]
+    fn test_add() {
+        let int = |size| TypeDesc::Int {
+            size,
+            signed: false,
+            bool: false,
+        };
+        let counters = TypeDesc::Struct {
+            size: 16,
+            members: vec![
+                Member {
+                    name: "packets".to_string(),
+                    bit_offset: 0,
+                    bit_size: 0,
+                    ty: int(8),
+                },
+                Member {
+                    name: "flags".to_string(),
+                    bit_offset: 64,
+                    bit_size: 4,
+                    ty: int(4),
+                },
+                Member {
+                    name: "drops".to_string(),
+                    bit_offset: 96,
+                    bit_size: 0,
+                    ty: TypeDesc::Array {
+                        elem: Box::new(int(2)),
+                        elem_size: 2,
+                        len: 2,
+                    },
+                },
+            ],
+        };
+        let value = |packets: u64, flags: u32, drops: [u16; 2]| {
+            let mut v = packets.to_ne_bytes().to_vec();
+            v.extend(flags.to_ne_bytes());
+            v.extend(drops[0].to_ne_bytes());
+            v.extend(drops[1].to_ne_bytes());
+            v
+        };
+
+        let mut sum = value(u64::MAX, 1, [1, 2]);
+        counters.add(&mut sum, &value(3, 2, [10, 20]));
+        assert_eq!(sum, value(2, 1, [11, 22]));
+    }
+
+    #[test]
+    fn test_invalid_btf() {
+        assert!(Btf::parse(&[0; 8]).is_err());
+        let mut b = Builder::new();
+        b.add("unsigned int", BTF_KIND_INT, 0, 4, &[32]);
+        let mut btf = b.build();
+        btf.truncate(btf.len() - 20);
+        assert!(Btf::parse(&btf).is_err());
+    }
+}
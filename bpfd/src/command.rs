@@ -12,7 +12,7 @@ use std::{
 use aya::programs::ProgramInfo as AyaProgInfo;
 use bpfd_api::{
     util::directories::{RTDIR_FS, RTDIR_PROGRAMS},
-    ParseError, ProgramType, TcProceedOn, XdpProceedOn,
+    CgroupAttachType, ParseError, ProgramType, TcProceedOn, XdpProceedOn,
 };
 use chrono::{prelude::DateTime, Local};
 use serde::{Deserialize, Serialize};
@@ -21,7 +21,9 @@ use uuid::Uuid;
 
 use crate::{
     errors::BpfdError,
+    maps::{MapInfo, PinnedMap},
     multiprog::{DispatcherId, DispatcherInfo},
+    netns::Netns,
     oci_utils::{image_manager::get_bytecode_from_image_store, BytecodeImage},
 };
 
@@ -40,11 +42,20 @@ pub(crate) enum Command {
         responder: Responder<Result<Vec<Program>, BpfdError>>,
     },
     PullBytecode(PullBytecodeArgs),
+    ReadIterator(ReadIteratorArgs),
+    Update(UpdateArgs),
+    Replace(ReplaceArgs),
+    SetEnabled(SetEnabledArgs),
+    GetMaps(GetMapsArgs),
+    OpenMap(OpenMapArgs),
 }
 
 #[derive(Debug)]
 pub(crate) struct LoadArgs {
     pub(crate) program: Program,
+    /// Further interfaces, or glob patterns of them, to attach an XDP or TC
+    /// program to on top of its own.
+    pub(crate) ifaces: Vec<String>,
     pub(crate) responder: Responder<Result<Uuid, BpfdError>>,
 }
 
@@ -55,6 +66,19 @@ pub(crate) enum Program {
     Tracepoint(TracepointProgram),
     Kprobe(KprobeProgram),
     Uprobe(UprobeProgram),
+    Fentry(FentryProgram),
+    Fexit(FexitProgram),
+    Lsm(LsmProgram),
+    Cgroup(CgroupProgram),
+    SkMsg(SkMsgProgram),
+    SkSkb(SkSkbProgram),
+    SkLookup(SkLookupProgram),
+    RawTracepoint(RawTracepointProgram),
+    TpBtf(TpBtfProgram),
+    PerfEvent(PerfEventProgram),
+    SocketFilter(SocketFilterProgram),
+    FlowDissector(FlowDissectorProgram),
+    Iter(IterProgram),
     Unsupported(KernelProgramInfo),
 }
 
@@ -64,6 +88,48 @@ pub(crate) struct UnloadArgs {
     pub(crate) responder: Responder<Result<(), BpfdError>>,
 }
 
+#[derive(Debug)]
+pub(crate) struct ReadIteratorArgs {
+    pub(crate) id: Uuid,
+    pub(crate) responder: Responder<Result<fs::File, BpfdError>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct UpdateArgs {
+    pub(crate) id: Uuid,
+    pub(crate) priority: Option<i32>,
+    pub(crate) proceed_on: Option<Vec<i32>>,
+    pub(crate) responder: Responder<Result<(), BpfdError>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ReplaceArgs {
+    pub(crate) id: Uuid,
+    pub(crate) location: Location,
+    pub(crate) keep_id: bool,
+    pub(crate) responder: Responder<Result<Uuid, BpfdError>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SetEnabledArgs {
+    pub(crate) id: Uuid,
+    pub(crate) enabled: bool,
+    pub(crate) responder: Responder<Result<(), BpfdError>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct GetMapsArgs {
+    pub(crate) id: Uuid,
+    pub(crate) responder: Responder<Result<Vec<MapInfo>, BpfdError>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct OpenMapArgs {
+    pub(crate) id: Uuid,
+    pub(crate) name: String,
+    pub(crate) responder: Responder<Result<PinnedMap, BpfdError>>,
+}
+
 #[derive(Debug)]
 pub(crate) struct PullBytecodeArgs {
     pub(crate) image: BytecodeImage,
@@ -127,9 +193,85 @@ impl std::fmt::Display for Direction {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Copy, Clone)]
+pub(crate) enum SkSkbAttachType {
+    StreamParser,
+    StreamVerdict,
+}
+
+impl TryFrom<String> for SkSkbAttachType {
+    type Error = ParseError;
+
+    fn try_from(v: String) -> Result<Self, Self::Error> {
+        match v.as_str() {
+            "stream_parser" => Ok(Self::StreamParser),
+            "stream_verdict" => Ok(Self::StreamVerdict),
+            m => Err(ParseError::InvalidSkSkbAttachType {
+                attach_type: m.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for SkSkbAttachType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkSkbAttachType::StreamParser => f.write_str("stream_parser"),
+            SkSkbAttachType::StreamVerdict => f.write_str("stream_verdict"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Copy, Clone)]
+pub(crate) enum PerfEventType {
+    Hardware,
+    Software,
+    Tracepoint,
+    HwCache,
+    Raw,
+    Breakpoint,
+}
+
+impl TryFrom<String> for PerfEventType {
+    type Error = ParseError;
+
+    fn try_from(v: String) -> Result<Self, Self::Error> {
+        match v.as_str() {
+            "hardware" => Ok(Self::Hardware),
+            "software" => Ok(Self::Software),
+            "tracepoint" => Ok(Self::Tracepoint),
+            "hw_cache" => Ok(Self::HwCache),
+            "raw" => Ok(Self::Raw),
+            "breakpoint" => Ok(Self::Breakpoint),
+            m => Err(ParseError::InvalidPerfEventType {
+                event_type: m.to_string(),
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for PerfEventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PerfEventType::Hardware => f.write_str("hardware"),
+            PerfEventType::Software => f.write_str("software"),
+            PerfEventType::Tracepoint => f.write_str("tracepoint"),
+            PerfEventType::HwCache => f.write_str("hw_cache"),
+            PerfEventType::Raw => f.write_str("raw"),
+            PerfEventType::Breakpoint => f.write_str("breakpoint"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Copy, Clone)]
+pub(crate) enum PerfEventSamplePolicy {
+    Period(u64),
+    Frequency(u64),
+}
+
 /// KernelProgramInfo stores information about ALL bpf programs loaded
 /// on a system.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub(crate) struct KernelProgramInfo {
     pub(crate) id: u32,
     pub(crate) name: String,
@@ -156,19 +298,27 @@ impl TryFrom<AyaProgInfo> for KernelProgramInfo {
                 .name_as_str()
                 .expect("Program name is not valid unicode")
                 .to_string(),
-            program_type: prog.program_type(),
-            loaded_at: DateTime::<Local>::from(prog.loaded_at())
-                .format("%Y-%m-%dT%H:%M:%S%z")
-                .to_string(),
+            program_type: prog.program_type().map_err(BpfdError::BpfProgramError)? as u32,
+            loaded_at: prog
+                .loaded_at()
+                .map(|t| {
+                    DateTime::<Local>::from(t)
+                        .format("%Y-%m-%dT%H:%M:%S%z")
+                        .to_string()
+                })
+                .unwrap_or_default(),
             tag: format!("{:x}", prog.tag()),
-            gpl_compatible: prog.gpl_compatible(),
-            map_ids: prog.map_ids().map_err(BpfdError::BpfProgramError)?,
-            btf_id: prog.btf_id().map_or(0, |n| n.into()),
-            bytes_xlated: prog.size_translated(),
+            gpl_compatible: prog.gpl_compatible().unwrap_or_default(),
+            map_ids: prog
+                .map_ids()
+                .map_err(BpfdError::BpfProgramError)?
+                .unwrap_or_default(),
+            btf_id: prog.btf_id().unwrap_or_default(),
+            bytes_xlated: prog.size_translated().unwrap_or_default(),
             jited: prog.size_jitted() != 0,
             bytes_jited: prog.size_jitted(),
             bytes_memlock: prog.memory_locked().map_err(BpfdError::BpfProgramError)?,
-            verified_insns: prog.verified_instruction_count(),
+            verified_insns: prog.verified_instruction_count().unwrap_or_default(),
         })
     }
 }
@@ -222,6 +372,10 @@ impl ProgramData {
         self.id = id
     }
 
+    pub(crate) fn set_location(&mut self, location: Location) {
+        self.location = location
+    }
+
     pub(crate) fn set_kernel_info(&mut self, info: Option<KernelProgramInfo>) {
         self.kernel_info = info
     }
@@ -246,6 +400,10 @@ impl ProgramData {
         self.map_owner_id
     }
 
+    pub(crate) fn set_map_owner_id(&mut self, id: Option<Uuid>) {
+        self.map_owner_id = id
+    }
+
     pub(crate) fn set_maps_used_by(&mut self, used_by: Option<Vec<Uuid>>) {
         self.maps_used_by = used_by
     }
@@ -294,6 +452,21 @@ pub(crate) struct XdpProgram {
     pub(crate) current_position: Option<usize>,
     pub(crate) if_index: Option<u32>,
     pub(crate) attached: bool,
+    // the program is in an xdp.frags section and handles multi-buffer packets
+    #[serde(default)]
+    pub(crate) frags: bool,
+    // left out of the dispatcher, but still loaded and pinned
+    #[serde(default)]
+    pub(crate) disabled: bool,
+    // kept pending while iface is missing or down, instead of failing
+    #[serde(default)]
+    pub(crate) wait_for_iface: bool,
+    // the network namespace iface is in, None for bpfd's own
+    #[serde(default)]
+    pub(crate) netns: Option<Netns>,
+    // the program this one was loaded along with, for another of its interfaces
+    #[serde(default)]
+    pub(crate) group: Option<Uuid>,
 }
 
 impl XdpProgram {
@@ -302,6 +475,8 @@ impl XdpProgram {
         priority: i32,
         iface: String,
         proceed_on: XdpProceedOn,
+        wait_for_iface: bool,
+        netns: Option<Netns>,
     ) -> Self {
         Self {
             data,
@@ -311,6 +486,11 @@ impl XdpProgram {
             current_position: None,
             if_index: None,
             attached: false,
+            frags: false,
+            disabled: false,
+            wait_for_iface,
+            netns,
+            group: None,
         }
     }
 }
@@ -328,6 +508,21 @@ pub(crate) struct TcProgram {
     pub(crate) current_position: Option<usize>,
     pub(crate) if_index: Option<u32>,
     pub(crate) attached: bool,
+    // attached with its own tcx link instead of through the dispatcher
+    #[serde(default)]
+    pub(crate) tcx: bool,
+    // left out of the dispatcher, but still loaded and pinned
+    #[serde(default)]
+    pub(crate) disabled: bool,
+    // kept pending while iface is missing or down, instead of failing
+    #[serde(default)]
+    pub(crate) wait_for_iface: bool,
+    // the network namespace iface is in, None for bpfd's own
+    #[serde(default)]
+    pub(crate) netns: Option<Netns>,
+    // the program this one was loaded along with, for another of its interfaces
+    #[serde(default)]
+    pub(crate) group: Option<Uuid>,
 }
 
 impl TcProgram {
@@ -337,6 +532,8 @@ impl TcProgram {
         iface: String,
         proceed_on: TcProceedOn,
         direction: Direction,
+        wait_for_iface: bool,
+        netns: Option<Netns>,
     ) -> Self {
         Self {
             data,
@@ -347,6 +544,11 @@ impl TcProgram {
             current_position: None,
             if_index: None,
             attached: false,
+            tcx: false,
+            disabled: false,
+            wait_for_iface,
+            netns,
+            group: None,
         }
     }
 }
@@ -364,6 +566,107 @@ impl TracepointProgram {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct RawTracepointProgram {
+    pub(crate) data: ProgramData,
+    // known at load time
+    pub(crate) tracepoint: String,
+}
+
+impl RawTracepointProgram {
+    pub(crate) fn new(data: ProgramData, tracepoint: String) -> Self {
+        Self { data, tracepoint }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct TpBtfProgram {
+    pub(crate) data: ProgramData,
+    // known at load time
+    pub(crate) tracepoint: String,
+}
+
+impl TpBtfProgram {
+    pub(crate) fn new(data: ProgramData, tracepoint: String) -> Self {
+        Self { data, tracepoint }
+    }
+}
+
+/// PerfEventProgram is attached to a perf event on every CPU in cpus, or on
+/// every online CPU when cpus is empty. When pid is set without any cpus a
+/// single event following the process across CPUs is used instead.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct PerfEventProgram {
+    pub(crate) data: ProgramData,
+    // known at load time
+    pub(crate) event_type: PerfEventType,
+    pub(crate) config: u64,
+    pub(crate) sample_policy: PerfEventSamplePolicy,
+    pub(crate) cpus: Vec<u32>,
+    // None samples any process
+    pub(crate) pid: Option<u32>,
+    // populated after load, one pinned link per CPU
+    pub(crate) attached_cpus: Vec<u32>,
+}
+
+impl PerfEventProgram {
+    pub(crate) fn new(
+        data: ProgramData,
+        event_type: PerfEventType,
+        config: u64,
+        sample_policy: PerfEventSamplePolicy,
+        cpus: Vec<u32>,
+        pid: Option<u32>,
+    ) -> Self {
+        Self {
+            data,
+            event_type,
+            config,
+            sample_policy,
+            cpus,
+            pid,
+            attached_cpus: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct SocketFilterProgram {
+    pub(crate) data: ProgramData,
+}
+
+impl SocketFilterProgram {
+    pub(crate) fn new(data: ProgramData) -> Self {
+        Self { data }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct FlowDissectorProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) netns: String,
+}
+
+impl FlowDissectorProgram {
+    pub(crate) fn new(data: ProgramData, netns: String) -> Self {
+        Self { data, netns }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct IterProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) iter_type: String,
+}
+
+impl IterProgram {
+    pub(crate) fn new(data: ProgramData, iter_type: String) -> Self {
+        Self { data, iter_type }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct KprobeProgram {
     pub(crate) data: ProgramData,
@@ -426,6 +729,128 @@ impl UprobeProgram {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct FentryProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) fn_name: String,
+}
+
+impl FentryProgram {
+    pub(crate) fn new(data: ProgramData, fn_name: String) -> Self {
+        Self { data, fn_name }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct FexitProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) fn_name: String,
+}
+
+impl FexitProgram {
+    pub(crate) fn new(data: ProgramData, fn_name: String) -> Self {
+        Self { data, fn_name }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct LsmProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) hook: String,
+}
+
+impl LsmProgram {
+    pub(crate) fn new(data: ProgramData, hook: String) -> Self {
+        Self { data, hook }
+    }
+}
+
+/// CgroupProgram covers every program type which attaches to a cgroup,
+/// the attach type determines which one it is.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct CgroupProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) cgroup_path: String,
+    pub(crate) attach_type: CgroupAttachType,
+}
+
+impl CgroupProgram {
+    pub(crate) fn new(
+        data: ProgramData,
+        cgroup_path: String,
+        attach_type: CgroupAttachType,
+    ) -> Self {
+        Self {
+            data,
+            cgroup_path,
+            attach_type,
+        }
+    }
+}
+
+/// SkMsgProgram is attached to a sockmap or sockhash. The map is found under
+/// the map pin path of map_owner_id, or of the program itself when unset.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct SkMsgProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) map_owner_id: Option<Uuid>,
+    pub(crate) map_name: String,
+}
+
+impl SkMsgProgram {
+    pub(crate) fn new(data: ProgramData, map_owner_id: Option<Uuid>, map_name: String) -> Self {
+        Self {
+            data,
+            map_owner_id,
+            map_name,
+        }
+    }
+}
+
+/// SkSkbProgram is attached to a sockmap or sockhash, see SkMsgProgram.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct SkSkbProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) map_owner_id: Option<Uuid>,
+    pub(crate) map_name: String,
+    pub(crate) attach_type: SkSkbAttachType,
+}
+
+impl SkSkbProgram {
+    pub(crate) fn new(
+        data: ProgramData,
+        map_owner_id: Option<Uuid>,
+        map_name: String,
+        attach_type: SkSkbAttachType,
+    ) -> Self {
+        Self {
+            data,
+            map_owner_id,
+            map_name,
+            attach_type,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct SkLookupProgram {
+    pub(crate) data: ProgramData,
+    // Known at load time
+    pub(crate) netns: String,
+}
+
+impl SkLookupProgram {
+    pub(crate) fn new(data: ProgramData, netns: String) -> Self {
+        Self { data, netns }
+    }
+}
+
 impl Program {
     pub(crate) fn kind(&self) -> ProgramType {
         match self {
@@ -434,6 +859,19 @@ impl Program {
             Program::Tracepoint(_) => ProgramType::Tracepoint,
             Program::Kprobe(_) => ProgramType::Probe,
             Program::Uprobe(_) => ProgramType::Probe,
+            Program::Fentry(_) => ProgramType::Tracing,
+            Program::Fexit(_) => ProgramType::Tracing,
+            Program::Lsm(_) => ProgramType::Lsm,
+            Program::Cgroup(p) => p.attach_type.program_type(),
+            Program::SkMsg(_) => ProgramType::SkMsg,
+            Program::SkSkb(_) => ProgramType::SkSkb,
+            Program::SkLookup(_) => ProgramType::SkLookup,
+            Program::RawTracepoint(_) => ProgramType::RawTracepoint,
+            Program::TpBtf(_) => ProgramType::Tracing,
+            Program::PerfEvent(_) => ProgramType::PerfEvent,
+            Program::SocketFilter(_) => ProgramType::SocketFilter,
+            Program::FlowDissector(_) => ProgramType::FlowDissector,
+            Program::Iter(_) => ProgramType::Tracing,
             Program::Unsupported(i) => i.program_type.try_into().unwrap(),
         }
     }
@@ -443,10 +881,12 @@ impl Program {
             Program::Xdp(p) => Some(DispatcherId::Xdp(DispatcherInfo(
                 p.if_index.expect("if_index should be known at this point"),
                 None,
+                p.netns.as_ref().map(|n| n.id),
             ))),
             Program::Tc(p) => Some(DispatcherId::Tc(DispatcherInfo(
                 p.if_index.expect("if_index should be known at this point"),
                 Some(p.direction),
+                p.netns.as_ref().map(|n| n.id),
             ))),
             _ => None,
         }
@@ -459,6 +899,19 @@ impl Program {
             Program::Tc(p) => Ok(&mut p.data),
             Program::Kprobe(p) => Ok(&mut p.data),
             Program::Uprobe(p) => Ok(&mut p.data),
+            Program::Fentry(p) => Ok(&mut p.data),
+            Program::Fexit(p) => Ok(&mut p.data),
+            Program::Lsm(p) => Ok(&mut p.data),
+            Program::Cgroup(p) => Ok(&mut p.data),
+            Program::SkMsg(p) => Ok(&mut p.data),
+            Program::SkSkb(p) => Ok(&mut p.data),
+            Program::SkLookup(p) => Ok(&mut p.data),
+            Program::RawTracepoint(p) => Ok(&mut p.data),
+            Program::TpBtf(p) => Ok(&mut p.data),
+            Program::PerfEvent(p) => Ok(&mut p.data),
+            Program::SocketFilter(p) => Ok(&mut p.data),
+            Program::FlowDissector(p) => Ok(&mut p.data),
+            Program::Iter(p) => Ok(&mut p.data),
             Program::Unsupported(_) => Err(BpfdError::Error(
                 "Unsupported program type has no ProgramData".to_string(),
             )),
@@ -472,6 +925,19 @@ impl Program {
             Program::Tc(p) => Ok(&p.data),
             Program::Kprobe(p) => Ok(&p.data),
             Program::Uprobe(p) => Ok(&p.data),
+            Program::Fentry(p) => Ok(&p.data),
+            Program::Fexit(p) => Ok(&p.data),
+            Program::Lsm(p) => Ok(&p.data),
+            Program::Cgroup(p) => Ok(&p.data),
+            Program::SkMsg(p) => Ok(&p.data),
+            Program::SkSkb(p) => Ok(&p.data),
+            Program::SkLookup(p) => Ok(&p.data),
+            Program::RawTracepoint(p) => Ok(&p.data),
+            Program::TpBtf(p) => Ok(&p.data),
+            Program::PerfEvent(p) => Ok(&p.data),
+            Program::SocketFilter(p) => Ok(&p.data),
+            Program::FlowDissector(p) => Ok(&p.data),
+            Program::Iter(p) => Ok(&p.data),
             Program::Unsupported(_) => Err(BpfdError::Error(
                 "Unsupported program type has no ProgramData".to_string(),
             )),
@@ -494,6 +960,129 @@ impl Program {
         }
     }
 
+    /// Marks an XDP or TC program as no longer attached to its dispatcher,
+    /// which has no position for it any more either.
+    pub(crate) fn set_detached(&mut self) {
+        match self {
+            Program::Xdp(p) => {
+                p.attached = false;
+                p.current_position = None;
+            }
+            Program::Tc(p) => {
+                p.attached = false;
+                p.current_position = None;
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns false for XDP and TC programs that were disabled and are left
+    /// out of their dispatcher. All other programs are always enabled.
+    pub(crate) fn enabled(&self) -> bool {
+        match self {
+            Program::Xdp(p) => !p.disabled,
+            Program::Tc(p) => !p.disabled,
+            _ => true,
+        }
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        match self {
+            Program::Xdp(p) => p.disabled = !enabled,
+            Program::Tc(p) => p.disabled = !enabled,
+            _ => (),
+        }
+    }
+
+    /// Returns true for XDP and TC programs that were loaded with
+    /// wait_for_iface, and follow their interface as it comes and goes.
+    pub(crate) fn wait_for_iface(&self) -> bool {
+        match self {
+            Program::Xdp(p) => p.wait_for_iface,
+            Program::Tc(p) => p.wait_for_iface,
+            _ => false,
+        }
+    }
+
+    /// Returns the network namespace of the interface of an XDP or TC
+    /// program, None if it is in bpfd's own.
+    pub(crate) fn netns(&self) -> Option<&Netns> {
+        match self {
+            Program::Xdp(p) => p.netns.as_ref(),
+            Program::Tc(p) => p.netns.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn netns_id(&self) -> Option<u64> {
+        self.netns().map(|n| n.id)
+    }
+
+    /// Returns the id of the program that an XDP or TC program was loaded
+    /// along with, when one request attached it to several interfaces. The
+    /// first interface gets that program, and the others a member of its
+    /// group each.
+    pub(crate) fn group(&self) -> Option<Uuid> {
+        match self {
+            Program::Xdp(p) => p.group,
+            Program::Tc(p) => p.group,
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set_group(&mut self, group: Option<Uuid>) {
+        match self {
+            Program::Xdp(p) => p.group = group,
+            Program::Tc(p) => p.group = group,
+            _ => (),
+        }
+    }
+
+    /// Returns true for XDP and TC programs that are waiting for their
+    /// interface, and have not been loaded in the kernel yet.
+    pub(crate) fn pending(&self) -> bool {
+        matches!(
+            self,
+            Program::Xdp(XdpProgram { if_index: None, .. })
+                | Program::Tc(TcProgram { if_index: None, .. })
+        )
+    }
+
+    /// Puts an XDP or TC program back to how it was before it was attached,
+    /// once its pins are gone, so it can be attached again from its bytecode.
+    pub(crate) fn set_pending(&mut self) {
+        match self {
+            Program::Xdp(p) => {
+                p.if_index = None;
+                p.attached = false;
+                p.current_position = None;
+                p.data.set_kernel_info(None);
+            }
+            Program::Tc(p) => {
+                p.if_index = None;
+                p.attached = false;
+                p.tcx = false;
+                p.current_position = None;
+                p.data.set_kernel_info(None);
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns true for TC programs attached through tcx rather than the
+    /// TC dispatcher.
+    pub(crate) fn tcx(&self) -> bool {
+        matches!(self, Program::Tc(p) if p.tcx)
+    }
+
+    pub(crate) fn position(&self) -> Option<usize> {
+        match self {
+            Program::Xdp(p) => p.current_position,
+            Program::Tc(p) => p.current_position,
+            _ => None,
+        }
+    }
+
     pub(crate) fn set_position(&mut self, pos: Option<usize>) {
         match self {
             Program::Xdp(p) => p.current_position = pos,
@@ -509,6 +1098,19 @@ impl Program {
             Program::Tracepoint(p) => p.data.kernel_info.as_ref(),
             Program::Kprobe(p) => p.data.kernel_info.as_ref(),
             Program::Uprobe(p) => p.data.kernel_info.as_ref(),
+            Program::Fentry(p) => p.data.kernel_info.as_ref(),
+            Program::Fexit(p) => p.data.kernel_info.as_ref(),
+            Program::Lsm(p) => p.data.kernel_info.as_ref(),
+            Program::Cgroup(p) => p.data.kernel_info.as_ref(),
+            Program::SkMsg(p) => p.data.kernel_info.as_ref(),
+            Program::SkSkb(p) => p.data.kernel_info.as_ref(),
+            Program::SkLookup(p) => p.data.kernel_info.as_ref(),
+            Program::RawTracepoint(p) => p.data.kernel_info.as_ref(),
+            Program::TpBtf(p) => p.data.kernel_info.as_ref(),
+            Program::PerfEvent(p) => p.data.kernel_info.as_ref(),
+            Program::SocketFilter(p) => p.data.kernel_info.as_ref(),
+            Program::FlowDissector(p) => p.data.kernel_info.as_ref(),
+            Program::Iter(p) => p.data.kernel_info.as_ref(),
             // KernelProgramInfo will never be nil for Unsupported programs
             Program::Unsupported(p) => Some(p),
         }
@@ -534,6 +1136,14 @@ impl Program {
         if PathBuf::from(&path).exists() {
             fs::remove_file(path)?;
         }
+        if let Program::PerfEvent(p) = self {
+            for cpu in &p.attached_cpus {
+                let path = format!("{RTDIR_FS}/prog_{id}_link_{cpu}");
+                if PathBuf::from(&path).exists() {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -569,6 +1179,14 @@ impl Program {
         }
     }
 
+    pub(crate) fn set_if_name(&mut self, if_name: String) {
+        match self {
+            Program::Xdp(p) => p.iface = if_name,
+            Program::Tc(p) => p.iface = if_name,
+            _ => (),
+        }
+    }
+
     pub(crate) fn priority(&self) -> Option<i32> {
         match self {
             Program::Xdp(p) => Some(p.priority),
@@ -584,6 +1202,19 @@ impl Program {
             Program::Tc(p) => Some(&p.data.location),
             Program::Kprobe(p) => Some(&p.data.location),
             Program::Uprobe(p) => Some(&p.data.location),
+            Program::Fentry(p) => Some(&p.data.location),
+            Program::Fexit(p) => Some(&p.data.location),
+            Program::Lsm(p) => Some(&p.data.location),
+            Program::Cgroup(p) => Some(&p.data.location),
+            Program::SkMsg(p) => Some(&p.data.location),
+            Program::SkSkb(p) => Some(&p.data.location),
+            Program::SkLookup(p) => Some(&p.data.location),
+            Program::RawTracepoint(p) => Some(&p.data.location),
+            Program::TpBtf(p) => Some(&p.data.location),
+            Program::PerfEvent(p) => Some(&p.data.location),
+            Program::SocketFilter(p) => Some(&p.data.location),
+            Program::FlowDissector(p) => Some(&p.data.location),
+            Program::Iter(p) => Some(&p.data.location),
             Program::Unsupported(_) => None,
         }
     }
@@ -602,6 +1233,19 @@ impl Program {
             Program::Tc(p) => &p.data.name,
             Program::Kprobe(p) => &p.data.name,
             Program::Uprobe(p) => &p.data.name,
+            Program::Fentry(p) => &p.data.name,
+            Program::Fexit(p) => &p.data.name,
+            Program::Lsm(p) => &p.data.name,
+            Program::Cgroup(p) => &p.data.name,
+            Program::SkMsg(p) => &p.data.name,
+            Program::SkSkb(p) => &p.data.name,
+            Program::SkLookup(p) => &p.data.name,
+            Program::RawTracepoint(p) => &p.data.name,
+            Program::TpBtf(p) => &p.data.name,
+            Program::PerfEvent(p) => &p.data.name,
+            Program::SocketFilter(p) => &p.data.name,
+            Program::FlowDissector(p) => &p.data.name,
+            Program::Iter(p) => &p.data.name,
             Program::Unsupported(k) => &k.name,
         }
     }
@@ -615,3 +1259,35 @@ impl Program {
 pub(crate) struct BpfMap {
     pub(crate) used_by: Vec<Uuid>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_xdp_program_round_trip() {
+        let mut program = Program::Xdp(XdpProgram::new(
+            ProgramData::new(
+                Location::File("/run/bpfd/examples/xdp_pass_kern.o".to_string()),
+                "pass".to_string(),
+                None,
+                HashMap::new(),
+                None,
+            ),
+            50,
+            "eth0".to_string(),
+            XdpProceedOn::default(),
+            true,
+            None,
+        ));
+        program.set_if_index(2);
+        program.set_attached();
+        program.set_pending();
+
+        let serialized = serde_json::to_string(&program).unwrap();
+        let program: Program = serde_json::from_str(&serialized).unwrap();
+        assert!(program.pending());
+        assert!(program.wait_for_iface());
+        assert_eq!(program.attached(), Some(false));
+    }
+}
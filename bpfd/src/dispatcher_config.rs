@@ -29,7 +29,7 @@ impl XdpDispatcherConfig {
     ) -> Self {
         Self {
             magic: 236u8,
-            dispatcher_version: 2u8,
+            dispatcher_version: 3u8,
             num_progs_enabled,
             is_xdp_frags,
             chain_call_actions,
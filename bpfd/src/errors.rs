@@ -11,19 +11,62 @@ pub enum BpfdError {
     #[error(transparent)]
     BpfProgramError(#[from] aya::programs::ProgramError),
     #[error(transparent)]
-    BpfLoadError(#[from] aya::BpfError),
+    BpfLoadError(#[from] aya::EbpfError),
+    #[error(transparent)]
+    BtfError(#[from] aya::BtfError),
+    #[error(transparent)]
+    BpfMapError(#[from] aya::maps::MapError),
+    #[error(transparent)]
+    BpfLinkError(#[from] aya::programs::links::LinkError),
     #[error("Unable to find a valid program with section name {0}")]
     SectionNameNotValid(String),
     #[error("No room to attach program. Please remove one and try again.")]
     TooManyPrograms,
+    #[error("XDP programs on {0} have to be either all frags aware (xdp.frags) or all not")]
+    XdpFragsMismatch(String),
     #[error("Invalid Interface")]
     InvalidInterface,
     #[error("Failed to pin link {0}")]
     UnableToPinLink(#[source] aya::pin::PinError),
     #[error("Failed to pin program {0}")]
     UnableToPinProgram(#[source] aya::pin::PinError),
+    #[error("Failed to pin map {0}")]
+    UnableToPinMap(#[source] aya::pin::PinError),
     #[error("{0} is not a valid attach point for this program")]
     InvalidAttach(String),
+    #[error("BPF LSM is not enabled, \"bpf\" must be listed in /sys/kernel/security/lsm")]
+    BpfLsmNotEnabled,
+    #[error("{0} is not a valid LSM hook")]
+    InvalidLsmHook(String),
+    #[error("map {0} is not a sockmap or sockhash")]
+    NotASockMap(String),
+    #[error("program {0} does not exist")]
+    ProgramNotFound(Uuid),
+    #[error("program {0} is not an iterator")]
+    NotAnIterator(Uuid),
+    #[error("program {0} is not attached through a dispatcher and can not be updated")]
+    NotUpdatable(Uuid),
+    #[error("program {0} is disabled")]
+    ProgramDisabled(Uuid),
+    #[error("program {0} is waiting for its interface")]
+    ProgramPending(Uuid),
+    #[error("program {0} is attached to several interfaces and can not be replaced")]
+    ProgramGrouped(Uuid),
+    #[error(
+        "program {0} shares its maps with other programs and can only be replaced keeping its id"
+    )]
+    MapsShared(Uuid),
+    #[error("program {0} has no map {1}")]
+    MapNotFound(Uuid, String),
+    #[error("the {what}s of map {map} are {expected} bytes long, not {got}")]
+    MapEntrySize {
+        map: String,
+        what: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("map {0} is not a {1}")]
+    WrongMapType(String, String),
     #[error("dispatcher is not loaded")]
     NotLoaded,
     #[error("dispatcher not required")]
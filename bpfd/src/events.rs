@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: (MIT OR Apache-2.0)
+// Copyright Authors of bpfd
+
+//! Reading the records programs send through ring buffers and perf event
+//! arrays, for clients that subscribe to them over gRPC. Each map is read by
+//! one thread, which hands every record to all subscribers of the map. A
+//! subscriber that doesn't keep up misses records instead of holding up the
+//! others, and is told how many it missed.
+//!
+//! bpfd takes over the maps it reads. A ring buffer has a single consumer
+//! position, so records bpfd reads are gone for any other reader of the map,
+//! and the other way around. A perf event array holds one perf buffer per
+//! CPU, and bpfd puts its own in, replacing those of any other reader. The
+//! kernel can't tell whether a map is already being read, so neither is
+//! refused. bpfd doesn't clear the entries of a perf event array when it
+//! stops reading it; the kernel drops the ones bpfd put in when it closes the
+//! map.
+use std::{
+    collections::HashMap,
+    fmt, io,
+    os::fd::{AsFd, AsRawFd},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use aya::maps::{
+    perf::{PerfBufferError, PerfEventArray, PerfEventArrayBuffer},
+    Map, MapData, RingBuf,
+};
+use bytes::BytesMut;
+use log::{debug, warn};
+use nix::libc;
+use tokio::sync::broadcast;
+
+use crate::{
+    errors::BpfdError,
+    maps::{read_cpus, PinnedMap},
+};
+
+const ONLINE_CPUS: &str = "/sys/devices/system/cpu/online";
+
+// Records a subscriber can fall behind by before it misses some.
+const SUBSCRIBER_BACKLOG: usize = 4096;
+// How often a reader checks whether it still has subscribers.
+const POLL_TIMEOUT_MS: libc::c_int = 200;
+// The size of the perf buffer of each CPU, in pages. Has to be a power of 2.
+const PERF_BUFFER_PAGES: usize = 64;
+// The most records read from a perf buffer at once.
+const PERF_READ_BATCH: usize = 16;
+
+/// The maps clients can subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EventMap {
+    RingBuf,
+    PerfEventArray,
+}
+
+impl fmt::Display for EventMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventMap::RingBuf => write!(f, "ringbuf"),
+            EventMap::PerfEventArray => write!(f, "perf_event_array"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Event {
+    Record(Vec<u8>),
+    /// Records the kernel could not write, because the buffer was full.
+    Lost(u64),
+    /// The map can no longer be read.
+    Error(String),
+}
+
+type Readers = Arc<Mutex<HashMap<u32, broadcast::Sender<Event>>>>;
+
+/// The maps being read, by kernel map id.
+#[derive(Debug, Default)]
+pub(crate) struct Subscriptions {
+    readers: Readers,
+}
+
+impl Subscriptions {
+    /// Returns the records sent through map from now on, starting a reader
+    /// for the map if it has none yet.
+    pub(crate) fn subscribe(
+        &self,
+        map: PinnedMap,
+        kind: EventMap,
+    ) -> Result<broadcast::Receiver<Event>, BpfdError> {
+        let info = map.info();
+        if info.type_name() != kind.to_string() {
+            return Err(BpfdError::WrongMapType(info.name.clone(), kind.to_string()));
+        }
+
+        let mut readers = self.readers.lock().unwrap();
+        if let Some(tx) = readers.get(&info.id) {
+            return Ok(tx.subscribe());
+        }
+
+        let id = info.id;
+        let name = info.name.clone();
+        let max_entries = info.max_entries;
+        let source = Source::new(map.into_map_data(), kind, max_entries)
+            .map_err(|e| BpfdError::Error(format!("can't read events of map {name}: {e}")))?;
+        let (tx, rx) = broadcast::channel(SUBSCRIBER_BACKLOG);
+        readers.insert(id, tx.clone());
+        let readers = self.readers.clone();
+        // The reader runs for as long as the map has subscribers, so it gets
+        // a thread of its own rather than one of tokio's blocking threads.
+        thread::spawn(move || read_events(source, id, tx, readers));
+        Ok(rx)
+    }
+}
+
+// read_events forwards the records of a map until it has no subscribers left
+// or can't be read anymore.
+fn read_events(mut source: Source, id: u32, tx: broadcast::Sender<Event>, readers: Readers) {
+    debug!("started reading events of map {id}");
+    let mut events = vec![];
+    loop {
+        let res = match source.poll() {
+            Ok(()) => source.read(&mut events).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = res {
+            warn!("can't read events of map {id}: {e}");
+            readers.lock().unwrap().remove(&id);
+            let _ = tx.send(Event::Error(format!("can't read events: {e}")));
+            break;
+        }
+        for event in events.drain(..) {
+            // Fails only when the last subscriber is gone, which is checked
+            // below.
+            let _ = tx.send(event);
+        }
+        if tx.receiver_count() == 0 {
+            // Subscribing holds the lock, so none can come in between.
+            let mut readers = readers.lock().unwrap();
+            if tx.receiver_count() == 0 {
+                readers.remove(&id);
+                break;
+            }
+        }
+    }
+    debug!("stopped reading events of map {id}");
+}
+
+enum Source {
+    RingBuf(RingBuf<MapData>),
+    Perf(PerfBuffers),
+}
+
+impl Source {
+    fn new(map: MapData, kind: EventMap, max_entries: u32) -> Result<Self, BpfdError> {
+        let map_err = |e| BpfdError::Error(format!("{e}"));
+        match kind {
+            EventMap::RingBuf => Ok(Source::RingBuf(
+                RingBuf::try_from(Map::RingBuf(map)).map_err(map_err)?,
+            )),
+            EventMap::PerfEventArray => Ok(Source::Perf(PerfBuffers::new(
+                PerfEventArray::try_from(Map::PerfEventArray(map)).map_err(map_err)?,
+                max_entries,
+            )?)),
+        }
+    }
+
+    // poll waits until there is something to read, or POLL_TIMEOUT_MS.
+    fn poll(&self) -> io::Result<()> {
+        let mut fds: Vec<libc::pollfd> = match self {
+            Source::RingBuf(r) => vec![r.as_fd().as_raw_fd()],
+            Source::Perf(p) => p.buffers.iter().map(|b| b.as_fd().as_raw_fd()).collect(),
+        }
+        .into_iter()
+        .map(|fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect();
+        // SAFETY: fds is a valid array of fds.len() pollfds.
+        let ret =
+            unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, POLL_TIMEOUT_MS) };
+        if ret < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, events: &mut Vec<Event>) -> Result<(), PerfBufferError> {
+        match self {
+            Source::RingBuf(r) => {
+                while let Some(record) = r.next() {
+                    events.push(Event::Record(record.to_vec()));
+                }
+                Ok(())
+            }
+            Source::Perf(p) => p.read(events),
+        }
+    }
+}
+
+// The perf buffers bpfd put in a perf event array, one per online CPU.
+struct PerfBuffers {
+    buffers: Vec<PerfEventArrayBuffer<MapData>>,
+    out: Vec<BytesMut>,
+}
+
+impl PerfBuffers {
+    fn new(mut array: PerfEventArray<MapData>, max_entries: u32) -> Result<Self, BpfdError> {
+        let mut buffers = vec![];
+        for cpu in read_cpus(ONLINE_CPUS)? {
+            if cpu >= max_entries {
+                break;
+            }
+            // Programs write to the buffer of the CPU they run on.
+            let buffer = array.open(cpu, Some(PERF_BUFFER_PAGES)).map_err(|e| {
+                BpfdError::Error(format!("can't open perf buffer of CPU {cpu}: {e}"))
+            })?;
+            buffers.push(buffer);
+        }
+        Ok(PerfBuffers {
+            buffers,
+            out: (0..PERF_READ_BATCH).map(|_| BytesMut::new()).collect(),
+        })
+    }
+
+    fn read(&mut self, events: &mut Vec<Event>) -> Result<(), PerfBufferError> {
+        for buffer in &mut self.buffers {
+            while buffer.readable() {
+                let read = buffer.read_events(&mut self.out)?;
+                if read.lost > 0 {
+                    events.push(Event::Lost(read.lost as u64));
+                }
+                for record in &self.out[..read.read] {
+                    events.push(Event::Record(record.to_vec()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
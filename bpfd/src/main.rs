@@ -9,11 +9,16 @@ use std::{
 };
 
 mod bpf;
+mod btf;
 mod certs;
 mod command;
 mod dispatcher_config;
 mod errors;
+mod events;
+mod maps;
 mod multiprog;
+mod netlink;
+mod netns;
 mod oci_utils;
 mod rpc;
 mod serve;
@@ -0,0 +1,595 @@
+// SPDX-License-Identifier: (MIT OR Apache-2.0)
+// Copyright Authors of bpfd
+
+//! Access to the maps bpfd pinned for its programs. Maps are opened and
+//! described through aya, but aya only reads and writes the entries of maps
+//! whose key and value types are known at compile time, so entries are
+//! accessed here with the bpf syscall directly.
+use std::{
+    fs, io, mem,
+    os::fd::{AsFd, AsRawFd, BorrowedFd},
+    path::{Path, PathBuf},
+};
+
+use aya::maps::{MapData, MapError};
+use log::warn;
+use nix::libc;
+
+use serde_json::{json, Value};
+
+use crate::{
+    btf::{map_btf, MapBtf, TypeDesc},
+    errors::BpfdError,
+    utils::sys_bpf,
+};
+
+const BPF_MAP_LOOKUP_ELEM: libc::c_int = 1;
+const BPF_MAP_UPDATE_ELEM: libc::c_int = 2;
+const BPF_MAP_DELETE_ELEM: libc::c_int = 3;
+const BPF_MAP_GET_NEXT_KEY: libc::c_int = 4;
+const BPF_MAP_LOOKUP_BATCH: libc::c_int = 24;
+
+// Not in libc, as it is internal to the kernel, but the bpf syscall
+// returns it.
+const ENOTSUPP: libc::c_int = 524;
+
+const POSSIBLE_CPUS: &str = "/sys/devices/system/cpu/possible";
+
+// The names of the map types, indexed by enum bpf_map_type.
+const MAP_TYPES: &[&str] = &[
+    "unspec",
+    "hash",
+    "array",
+    "prog_array",
+    "perf_event_array",
+    "percpu_hash",
+    "percpu_array",
+    "stack_trace",
+    "cgroup_array",
+    "lru_hash",
+    "lru_percpu_hash",
+    "lpm_trie",
+    "array_of_maps",
+    "hash_of_maps",
+    "devmap",
+    "sockmap",
+    "cpumap",
+    "xskmap",
+    "sockhash",
+    "cgroup_storage",
+    "reuseport_sockarray",
+    "percpu_cgroup_storage",
+    "queue",
+    "stack",
+    "sk_storage",
+    "devmap_hash",
+    "struct_ops",
+    "ringbuf",
+    "inode_storage",
+    "task_storage",
+    "bloom_filter",
+    "user_ringbuf",
+    "cgrp_storage",
+];
+
+/// A map pinned under the map pin path of a program.
+#[derive(Debug, Clone)]
+pub(crate) struct MapInfo {
+    pub(crate) name: String,
+    pub(crate) id: u32,
+    pub(crate) map_type: u32,
+    pub(crate) key_size: u32,
+    pub(crate) value_size: u32,
+    pub(crate) max_entries: u32,
+    pub(crate) flags: u32,
+    pub(crate) pin_path: PathBuf,
+}
+
+impl MapInfo {
+    // percpu_hash, percpu_array, lru_percpu_hash and percpu_cgroup_storage.
+    pub(crate) fn per_cpu(&self) -> bool {
+        matches!(self.map_type, 5 | 6 | 10 | 21)
+    }
+
+    pub(crate) fn type_name(&self) -> String {
+        match MAP_TYPES.get(self.map_type as usize) {
+            Some(name) => name.to_string(),
+            None => format!("unknown ({})", self.map_type),
+        }
+    }
+}
+
+// The part of union bpf_attr used by the BPF_MAP_*_ELEM commands and
+// BPF_MAP_GET_NEXT_KEY, where value is next_key.
+#[repr(C, align(8))]
+#[derive(Default)]
+#[allow(dead_code)]
+struct ElemAttr {
+    map_fd: u32,
+    _pad: u32,
+    key: u64,
+    value: u64,
+    flags: u64,
+}
+
+// The part of union bpf_attr used by the BPF_MAP_*_BATCH commands.
+#[repr(C, align(8))]
+#[derive(Default)]
+#[allow(dead_code)]
+struct BatchAttr {
+    in_batch: u64,
+    out_batch: u64,
+    keys: u64,
+    values: u64,
+    count: u32,
+    map_fd: u32,
+    elem_flags: u64,
+    flags: u64,
+}
+
+fn ptr_or_null(buf: &[u8]) -> u64 {
+    if buf.is_empty() {
+        0
+    } else {
+        buf.as_ptr() as u64
+    }
+}
+
+// Kernels that don't know a command return EINVAL, maps that don't
+// implement one return ENOTSUPP or EOPNOTSUPP.
+fn unsupported(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::EINVAL) | Some(ENOTSUPP) | Some(libc::EOPNOTSUPP)
+    )
+}
+
+/// Returns the CPUs in a list like /sys/devices/system/cpu/possible.
+pub(crate) fn read_cpus(path: &str) -> Result<Vec<u32>, BpfdError> {
+    let ranges = fs::read_to_string(path)
+        .map_err(|e| BpfdError::Error(format!("can't read {path}: {e}")))?;
+    parse_cpus(&ranges)
+}
+
+fn parse_cpus(ranges: &str) -> Result<Vec<u32>, BpfdError> {
+    let invalid = || BpfdError::Error(format!("invalid list of CPUs {ranges:?}"));
+    let mut cpus = vec![];
+    for range in ranges.trim().split(',') {
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (first, last),
+            None => (range, range),
+        };
+        let first: u32 = first.parse().map_err(|_| invalid())?;
+        let last: u32 = last.parse().map_err(|_| invalid())?;
+        if last < first {
+            return Err(invalid());
+        }
+        cpus.extend(first..=last);
+    }
+    Ok(cpus)
+}
+
+// counter_type is the type values of per-CPU maps without BTF are summed as.
+fn counter_type(size: usize) -> TypeDesc {
+    let int = |size| TypeDesc::Int {
+        size,
+        signed: false,
+        bool: false,
+    };
+    let elem_size = match size {
+        1 | 2 | 4 | 8 => return int(size as u32),
+        _ if size % 8 == 0 => 8,
+        _ if size % 4 == 0 => 4,
+        _ => return TypeDesc::Bytes { size: size as u32 },
+    };
+    TypeDesc::Array {
+        elem: Box::new(int(elem_size)),
+        elem_size,
+        len: size as u32 / elem_size,
+    }
+}
+
+// sum_values sums the values of type ty, padded to 8 bytes, in a value of a
+// per-CPU map.
+fn sum_values(ty: &TypeDesc, size: usize, value: &[u8]) -> Vec<u8> {
+    let mut values = value
+        .chunks(((size + 7) / 8 * 8).max(1))
+        .map(|v| &v[..size.min(v.len())]);
+    let mut sum = values.next().unwrap_or_default().to_vec();
+    for v in values {
+        ty.add(&mut sum, v);
+    }
+    sum
+}
+
+/// A key and its value in a map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MapEntry {
+    pub(crate) key: Vec<u8>,
+    pub(crate) value: Vec<u8>,
+}
+
+/// A map opened through its pin.
+#[derive(Debug)]
+pub(crate) struct PinnedMap {
+    map: MapData,
+    info: MapInfo,
+    // The number of values per-CPU maps hold for every key, 1 otherwise.
+    cpus: usize,
+    btf: Option<MapBtf>,
+}
+
+impl PinnedMap {
+    pub(crate) fn open(path: &Path) -> Result<Self, BpfdError> {
+        let map = MapData::from_pin(path).map_err(|e| {
+            BpfdError::Error(format!("can't open map pinned at {}: {e}", path.display()))
+        })?;
+        let info_error =
+            |e| BpfdError::Error(format!("can't get info of map {}: {e}", path.display()));
+        let info = map.info().map_err(info_error)?;
+        let map_type = match info.map_type() {
+            Ok(map_type) => map_type as u32,
+            // Map types aya doesn't know yet are still shown by number.
+            Err(MapError::InvalidMapType { map_type }) => map_type,
+            Err(e) => return Err(info_error(e)),
+        };
+
+        let info = MapInfo {
+            // Kernel map names are cut short at 15 bytes, pins are not.
+            name: path
+                .file_name()
+                .map_or(String::new(), |n| n.to_string_lossy().to_string()),
+            id: info.id(),
+            map_type,
+            key_size: info.key_size(),
+            value_size: info.value_size(),
+            max_entries: info.max_entries(),
+            flags: info.map_flags(),
+            pin_path: path.to_path_buf(),
+        };
+        // Per-CPU maps hold a value for every possible CPU.
+        let cpus = if info.per_cpu() {
+            read_cpus(POSSIBLE_CPUS)?.len()
+        } else {
+            1
+        };
+
+        Ok(PinnedMap {
+            map,
+            info,
+            cpus,
+            btf: None,
+        })
+    }
+
+    /// Looks up the types of the keys and values of the map in the BTF the
+    /// kernel has for it, which to_json needs. Maps without BTF are shown as
+    /// bytes.
+    pub(crate) fn with_btf(mut self) -> Self {
+        self.btf = map_btf(self.fd()).unwrap_or_else(|e| {
+            warn!("unable to read the BTF of map {}: {e}", self.info.name);
+            None
+        });
+        self
+    }
+
+    pub(crate) fn info(&self) -> &MapInfo {
+        &self.info
+    }
+
+    pub(crate) fn fd(&self) -> BorrowedFd<'_> {
+        self.map.fd().as_fd()
+    }
+
+    /// Gives up the map, for readers that use aya's types for it.
+    pub(crate) fn into_map_data(self) -> MapData {
+        self.map
+    }
+
+    /// Decodes a key and value of the map into JSON, if its BTF is known.
+    /// Values of per-CPU maps are decoded for every CPU, as bpftool does.
+    pub(crate) fn to_json(&self, key: &[u8], value: &[u8]) -> Option<(Value, Value)> {
+        let btf = self.btf.as_ref()?;
+        let value = if self.info.per_cpu() {
+            let stride = (self.info.value_size as usize + 7) / 8 * 8;
+            Value::Array(
+                value
+                    .chunks(stride.max(1))
+                    .enumerate()
+                    .map(|(cpu, v)| json!({"cpu": cpu, "value": btf.value.to_json(v)}))
+                    .collect(),
+            )
+        } else {
+            btf.value.to_json(value)
+        };
+        Some((btf.key.to_json(key), value))
+    }
+
+    /// Like to_json, for a value of a per-CPU map summed by sum_cpus.
+    pub(crate) fn sum_to_json(&self, key: &[u8], sum: &[u8]) -> Option<(Value, Value)> {
+        let btf = self.btf.as_ref()?;
+        Some((btf.key.to_json(key), btf.value.to_json(sum)))
+    }
+
+    /// Sums the values of all CPUs in a value of a per-CPU map, field by
+    /// field if the BTF of the map is known. Otherwise the value is taken to
+    /// be an integer, or an array of integers.
+    pub(crate) fn sum_cpus(&self, value: &[u8]) -> Vec<u8> {
+        let size = self.info.value_size as usize;
+        let fallback;
+        let ty = match &self.btf {
+            Some(btf) => &btf.value,
+            None => {
+                fallback = counter_type(size);
+                &fallback
+            }
+        };
+        sum_values(ty, size, value)
+    }
+
+    /// The length of the values of the map as seen from user space: per-CPU
+    /// maps hold a value, padded to 8 bytes, for every possible CPU.
+    pub(crate) fn value_len(&self) -> usize {
+        if self.info.per_cpu() {
+            (self.info.value_size as usize + 7) / 8 * 8 * self.cpus
+        } else {
+            self.info.value_size as usize
+        }
+    }
+
+    fn check_key(&self, key: &[u8]) -> Result<(), BpfdError> {
+        if key.len() != self.info.key_size as usize {
+            return Err(BpfdError::MapEntrySize {
+                map: self.info.name.clone(),
+                what: "key",
+                expected: self.info.key_size as usize,
+                got: key.len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn elem_attr(&self, key: &[u8], value: u64, flags: u64) -> ElemAttr {
+        ElemAttr {
+            map_fd: self.fd().as_raw_fd() as u32,
+            key: ptr_or_null(key),
+            value,
+            flags,
+            ..Default::default()
+        }
+    }
+
+    fn error(&self, op: &str, e: io::Error) -> BpfdError {
+        BpfdError::Error(format!("can't {op} map {}: {e}", self.info.name))
+    }
+
+    /// Returns the value of key, or None if the map doesn't hold it.
+    pub(crate) fn lookup(&self, key: &[u8]) -> Result<Option<Vec<u8>>, BpfdError> {
+        self.check_key(key)?;
+        let mut value = vec![0u8; self.value_len()];
+        let mut attr = self.elem_attr(key, value.as_mut_ptr() as u64, 0);
+        match sys_bpf(BPF_MAP_LOOKUP_ELEM, &mut attr) {
+            Ok(_) => Ok(Some(value)),
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Ok(None),
+            Err(e) => Err(self.error("look up", e)),
+        }
+    }
+
+    /// Sets the value of key, with flags being BPF_ANY, BPF_NOEXIST or
+    /// BPF_EXIST. A single value given for a per-CPU map is set on every CPU.
+    pub(crate) fn update(&self, key: &[u8], value: &[u8], flags: u64) -> Result<(), BpfdError> {
+        self.check_key(key)?;
+        let value = if self.info.per_cpu() && value.len() == self.info.value_size as usize {
+            let mut padded = value.to_vec();
+            padded.resize((self.info.value_size as usize + 7) / 8 * 8, 0);
+            padded.repeat(self.cpus)
+        } else {
+            value.to_vec()
+        };
+        if value.len() != self.value_len() {
+            return Err(BpfdError::MapEntrySize {
+                map: self.info.name.clone(),
+                what: "value",
+                expected: self.value_len(),
+                got: value.len(),
+            });
+        }
+        let mut attr = self.elem_attr(key, ptr_or_null(&value), flags);
+        sys_bpf(BPF_MAP_UPDATE_ELEM, &mut attr).map_err(|e| self.error("update", e))?;
+        Ok(())
+    }
+
+    /// Deletes key, returning whether the map held it.
+    pub(crate) fn delete(&self, key: &[u8]) -> Result<bool, BpfdError> {
+        self.check_key(key)?;
+        let mut attr = self.elem_attr(key, 0, 0);
+        match sys_bpf(BPF_MAP_DELETE_ELEM, &mut attr) {
+            Ok(_) => Ok(true),
+            Err(e) if e.raw_os_error() == Some(libc::ENOENT) => Ok(false),
+            Err(e) => Err(self.error("delete from", e)),
+        }
+    }
+
+    /// Passes all entries of the map to f, at most batch_size at a time,
+    /// until f returns false. The kernel looks up the entries in batches
+    /// where it can, which is a lot faster for large hash maps than one key
+    /// at a time.
+    pub(crate) fn dump(
+        &self,
+        batch_size: usize,
+        mut f: impl FnMut(Vec<MapEntry>) -> bool,
+    ) -> Result<(), BpfdError> {
+        if !self.dump_batched(batch_size, &mut f)? {
+            self.dump_by_key(batch_size, &mut f)?;
+        }
+        Ok(())
+    }
+
+    // dump_batched returns false if the kernel can't look up the map in
+    // batches.
+    fn dump_batched(
+        &self,
+        batch_size: usize,
+        f: &mut impl FnMut(Vec<MapEntry>) -> bool,
+    ) -> Result<bool, BpfdError> {
+        let key_size = self.info.key_size as usize;
+        let value_len = self.value_len();
+        let mut count = batch_size;
+        let mut keys = vec![0u8; key_size * count];
+        let mut values = vec![0u8; value_len * count];
+        // Where the previous batch ended. Array maps use a key for it, hash
+        // maps a bucket number.
+        let mut in_batch = vec![0u8; key_size.max(8)];
+        let mut out_batch = vec![0u8; key_size.max(8)];
+        let mut first = true;
+        loop {
+            let mut attr = BatchAttr {
+                in_batch: if first { 0 } else { in_batch.as_ptr() as u64 },
+                out_batch: out_batch.as_mut_ptr() as u64,
+                keys: keys.as_mut_ptr() as u64,
+                values: values.as_mut_ptr() as u64,
+                count: count as u32,
+                map_fd: self.fd().as_raw_fd() as u32,
+                ..Default::default()
+            };
+            let done = match sys_bpf(BPF_MAP_LOOKUP_BATCH, &mut attr) {
+                Ok(_) => false,
+                Err(e) if e.raw_os_error() == Some(libc::ENOENT) => true,
+                // A hash bucket holds more entries than fit in the batch.
+                Err(e) if e.raw_os_error() == Some(libc::ENOSPC) && attr.count == 0 => {
+                    count *= 2;
+                    keys.resize(key_size * count, 0);
+                    values.resize(value_len * count, 0);
+                    continue;
+                }
+                Err(e) if first && unsupported(&e) => return Ok(false),
+                Err(e) => return Err(self.error("dump", e)),
+            };
+
+            let entries: Vec<MapEntry> = (0..attr.count as usize)
+                .map(|i| MapEntry {
+                    key: keys[i * key_size..(i + 1) * key_size].to_vec(),
+                    value: values[i * value_len..(i + 1) * value_len].to_vec(),
+                })
+                .collect();
+            if (!entries.is_empty() && !f(entries)) || done {
+                break;
+            }
+            mem::swap(&mut in_batch, &mut out_batch);
+            first = false;
+        }
+        Ok(true)
+    }
+
+    fn dump_by_key(
+        &self,
+        batch_size: usize,
+        f: &mut impl FnMut(Vec<MapEntry>) -> bool,
+    ) -> Result<(), BpfdError> {
+        let mut key: Option<Vec<u8>> = None;
+        let mut entries = vec![];
+        loop {
+            let mut next = vec![0u8; self.info.key_size as usize];
+            let mut attr = self.elem_attr(
+                key.as_deref().unwrap_or_default(),
+                next.as_mut_ptr() as u64,
+                0,
+            );
+            match sys_bpf(BPF_MAP_GET_NEXT_KEY, &mut attr) {
+                Ok(_) => {}
+                Err(e) if e.raw_os_error() == Some(libc::ENOENT) => break,
+                Err(e) => return Err(self.error("dump", e)),
+            }
+            // The entry may have been deleted in the meantime.
+            if let Some(value) = self.lookup(&next)? {
+                entries.push(MapEntry {
+                    key: next.clone(),
+                    value,
+                });
+            }
+            key = Some(next);
+            if entries.len() == batch_size && !f(mem::take(&mut entries)) {
+                return Ok(());
+            }
+        }
+        if !entries.is_empty() {
+            f(entries);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the maps pinned in dir, sorted by name.
+pub(crate) fn pinned_maps(dir: &Path) -> Result<Vec<MapInfo>, BpfdError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| BpfdError::Error(format!("can't read {}: {e}", dir.display())))?;
+    let mut maps = vec![];
+    for entry in entries {
+        let entry = entry.map_err(|e| BpfdError::Error(format!("can't read map pin: {e}")))?;
+        maps.push(PinnedMap::open(&entry.path())?.info);
+    }
+    maps.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(maps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpf_attr_layout() {
+        // The sizes in the kernel UAPI headers.
+        assert_eq!(mem::size_of::<ElemAttr>(), 32);
+        assert_eq!(mem::size_of::<BatchAttr>(), 56);
+    }
+
+    #[test]
+    fn test_sum_values() {
+        // Two CPUs, each with a 12 byte value padded to 16 bytes.
+        let mut value = vec![];
+        for (cpu, n) in [(1u32, 100u32), (2, 200)] {
+            value.extend(cpu.to_ne_bytes());
+            value.extend(n.to_ne_bytes());
+            value.extend(7u32.to_ne_bytes());
+            value.extend([0xff; 4]);
+        }
+        let sum = sum_values(&counter_type(12), 12, &value);
+        let expected: Vec<u8> = [3u32, 300, 14]
+            .iter()
+            .flat_map(|n| n.to_ne_bytes())
+            .collect();
+        assert_eq!(sum, expected);
+
+        let sum = sum_values(&counter_type(3), 3, &[1, 2, 3, 0, 0, 0, 0, 0, 4, 5, 6]);
+        assert_eq!(sum, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_cpus() {
+        assert_eq!(parse_cpus("0\n").unwrap(), [0]);
+        assert_eq!(parse_cpus("0-7\n").unwrap().len(), 8);
+        assert_eq!(parse_cpus("0-1,8-9").unwrap(), [0, 1, 8, 9]);
+        assert_eq!(parse_cpus("0,2,4-5").unwrap(), [0, 2, 4, 5]);
+        assert!(parse_cpus("").is_err());
+        assert!(parse_cpus("3-1").is_err());
+    }
+
+    #[test]
+    fn test_map_type_name() {
+        let mut info = MapInfo {
+            name: "counters".to_string(),
+            id: 1,
+            map_type: 6,
+            key_size: 4,
+            value_size: 8,
+            max_entries: 1,
+            flags: 0,
+            pin_path: PathBuf::from("/run/bpfd/fs/maps/1/counters"),
+        };
+        assert_eq!(info.type_name(), "percpu_array");
+        info.map_type = 27;
+        assert_eq!(info.type_name(), "ringbuf");
+        info.map_type = 1000;
+        assert_eq!(info.type_name(), "unknown (1000)");
+    }
+}
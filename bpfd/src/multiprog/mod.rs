@@ -5,30 +5,46 @@ mod tc;
 mod xdp;
 
 use bpfd_api::{
-    config::{InterfaceConfig, XdpMode},
-    ProgramType,
+    config::{DispatcherSource, Dispatchers, InterfaceConfig, XdpMode},
+    ImagePullPolicy, ProgramType,
 };
-use log::debug;
+use log::{debug, info};
 pub use tc::TcDispatcher;
 use uuid::Uuid;
+pub(crate) use xdp::xdp_has_frags;
 pub use xdp::XdpDispatcher;
 
 use crate::{
     command::{Direction, Program},
+    dispatcher_config::MAX_DISPATCHER_ACTIONS,
     errors::BpfdError,
+    netns::Netns,
+    oci_utils::{image_manager::get_bytecode_from_image_store, BytecodeImage, ImageError},
 };
 
+// Dispatchers have a fixed number of program slots. When a hook has more
+// programs than that, the last slot of a dispatcher is used to chain to the
+// next one, up to MAX_CHAINED_DISPATCHERS dispatchers per hook.
+const MAX_CHAINED_DISPATCHERS: usize = 5;
+
+/// The number of programs bpfd can attach to a single XDP or TC hook.
+pub(crate) const MAX_PROGRAMS: usize = MAX_CHAINED_DISPATCHERS * (MAX_DISPATCHER_ACTIONS - 1) + 1;
+
 pub(crate) enum Dispatcher {
     Xdp(XdpDispatcher),
     Tc(TcDispatcher),
 }
 
 impl Dispatcher {
+    // new builds the dispatcher for programs at revision and attaches it in
+    // place of old_dispatcher, which is deleted once the new one is attached.
+    // On error old_dispatcher is left as it was, so the caller can keep it.
     pub async fn new(
         config: Option<&InterfaceConfig>,
+        sources: &Dispatchers,
         programs: &mut [(Uuid, Program)],
         revision: u32,
-        old_dispatcher: Option<Dispatcher>,
+        old_dispatcher: Option<&mut Dispatcher>,
     ) -> Result<Dispatcher, BpfdError> {
         debug!("Dispatcher::new()");
         let (_, p) = programs
@@ -41,6 +57,7 @@ impl Dispatcher {
             .if_name()
             .ok_or_else(|| BpfdError::Error("missing ifname".to_string()))?;
         let direction = p.direction();
+        let netns = p.netns().cloned();
         let xdp_mode = if let Some(c) = config {
             c.xdp_mode
         } else {
@@ -49,9 +66,11 @@ impl Dispatcher {
         let d = match p.kind() {
             ProgramType::Xdp => {
                 let x = XdpDispatcher::new(
+                    &sources.xdp,
                     xdp_mode,
                     &if_index,
                     if_name,
+                    netns,
                     programs,
                     revision,
                     old_dispatcher,
@@ -63,9 +82,11 @@ impl Dispatcher {
                 let direction =
                     direction.ok_or_else(|| BpfdError::Error("direction required".to_string()))?;
                 let t = TcDispatcher::new(
+                    &sources.tc,
                     direction,
                     &if_index,
                     if_name,
+                    netns,
                     programs,
                     revision,
                     old_dispatcher,
@@ -100,13 +121,138 @@ impl Dispatcher {
             Dispatcher::Tc(d) => d.if_name(),
         }
     }
+
+    pub(crate) fn netns(&self) -> Option<&Netns> {
+        match self {
+            Dispatcher::Xdp(d) => d.netns(),
+            Dispatcher::Tc(d) => d.netns(),
+        }
+    }
+
+    /// Checks with the kernel that the dispatcher is still attached to its
+    /// interface.
+    pub(crate) async fn is_attached(&self) -> Result<bool, BpfdError> {
+        match self {
+            Dispatcher::Xdp(d) => d.is_attached().await,
+            Dispatcher::Tc(d) => d.is_attached().await,
+        }
+    }
+
+    /// Drops what bpfd knows about how the dispatcher was attached, after it
+    /// was detached outside of bpfd.
+    pub(crate) fn forget_link(&mut self) -> Result<(), BpfdError> {
+        match self {
+            Dispatcher::Xdp(d) => d.forget_link(),
+            Dispatcher::Tc(d) => d.forget_link(),
+        }
+    }
+
+    /// Kernel ids of the chained dispatchers and chain programs behind this
+    /// dispatcher, which are internal to bpfd.
+    pub(crate) fn chain_program_ids(&self) -> Vec<u32> {
+        match self {
+            Dispatcher::Xdp(d) => d.chain_program_ids(),
+            Dispatcher::Tc(d) => d.chain_program_ids(),
+        }
+    }
+}
+
+// chain_chunks splits the programs of a hook, sorted by position, into the
+// programs each dispatcher of the chain runs. Every dispatcher but the last
+// one gives up its last slot to chain to the next dispatcher.
+pub(crate) fn chain_chunks<T>(programs: &mut [T], slots: usize) -> Vec<&mut [T]> {
+    let mut chunks = vec![];
+    let mut rest = programs;
+    while rest.len() > slots {
+        let (head, tail) = rest.split_at_mut(slots - 1);
+        chunks.push(head);
+        rest = tail;
+    }
+    chunks.push(rest);
+    chunks
 }
 
-#[derive(Debug, Hash, Eq, PartialEq)]
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub(crate) enum DispatcherId {
     Xdp(DispatcherInfo),
     Tc(DispatcherInfo),
 }
 
-#[derive(Debug, Hash, Eq, PartialEq)]
-pub(crate) struct DispatcherInfo(pub u32, pub Option<Direction>);
+/// The if_index and direction of a hook, and the id of the network namespace
+/// if_index is in, None for bpfd's own.
+#[derive(Debug, Hash, Eq, PartialEq, Clone)]
+pub(crate) struct DispatcherInfo(pub u32, pub Option<Direction>, pub Option<u64>);
+
+// dispatcher_bytecode returns the bytecode of a dispatcher from source, and
+// the name of its program. Unset fields of source fall back to image and
+// pull_policy. bundled, whose program is program_name, is used when the pull
+// policy is Never and the image is not in the local image store, so that
+// hosts without access to a registry can still create dispatchers.
+pub(crate) async fn dispatcher_bytecode(
+    source: &DispatcherSource,
+    image: &str,
+    pull_policy: ImagePullPolicy,
+    bundled: &[u8],
+    program_name: &str,
+) -> Result<(Vec<u8>, String), BpfdError> {
+    if let Some(path) = &source.path {
+        debug!("loading dispatcher from {path}");
+        return Ok((crate::utils::read(path).await?, program_name.to_string()));
+    }
+
+    let url = source.image.as_deref().unwrap_or(image);
+    let pull_policy = source.pull_policy.clone().unwrap_or(pull_policy);
+    let never = matches!(pull_policy, ImagePullPolicy::Never);
+    let image = BytecodeImage::new(url.to_string(), pull_policy.into(), None, None);
+    match image.get_image(None).await {
+        Ok((path, section_name)) => Ok((get_bytecode_from_image_store(path).await?, section_name)),
+        Err(ImageError::ByteCodeImageNotfound(_)) if never => {
+            info!("{url} is not in the local image store, using the bundled dispatcher");
+            Ok((bundled.to_vec(), program_name.to_string()))
+        }
+        Err(e) => Err(BpfdError::BpfBytecodeError(e.into())),
+    }
+}
+
+// netns_dir returns the directory under base that the state or pins of a
+// dispatcher in the network namespace with id netns go to. if_indexes are
+// only unique within a namespace, so each one gets a directory of its own.
+pub(crate) fn netns_dir(base: &str, netns: Option<u64>) -> String {
+    match netns {
+        Some(id) => format!("{base}/netns_{id}"),
+        None => base.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_chunks() {
+        let mut programs: Vec<usize> = (0..10).collect();
+        let chunks = chain_chunks(&mut programs, 10);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].len(), 10);
+
+        let mut programs: Vec<usize> = (0..11).collect();
+        let chunks = chain_chunks(&mut programs, 10);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], (0..9).collect::<Vec<_>>());
+        assert_eq!(chunks[1], vec![9, 10]);
+
+        let mut programs: Vec<usize> = (0..MAX_PROGRAMS).collect();
+        let chunks = chain_chunks(&mut programs, MAX_DISPATCHER_ACTIONS);
+        assert_eq!(chunks.len(), MAX_CHAINED_DISPATCHERS);
+        assert_eq!(chunks.last().unwrap().len(), MAX_DISPATCHER_ACTIONS);
+    }
+
+    #[test]
+    fn test_netns_dir() {
+        assert_eq!(netns_dir("/run/bpfd/xdp", None), "/run/bpfd/xdp");
+        assert_eq!(
+            netns_dir("/run/bpfd/xdp", Some(4026532281)),
+            "/run/bpfd/xdp/netns_4026532281"
+        );
+    }
+}
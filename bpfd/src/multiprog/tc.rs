@@ -1,23 +1,28 @@
 // SPDX-License-Identifier: (MIT OR Apache-2.0)
 // Copyright Authors of bpfd
 
-use std::{fs, io::BufReader, mem};
+use std::{fs, io::BufReader, mem::ManuallyDrop};
 
 use aya::{
     include_bytes_aligned,
+    maps::ProgramArray,
     programs::{
         links::FdLink,
-        tc::{self, SchedClassifierLink, TcOptions},
+        tc::{self, NlOptions, SchedClassifierLink, TcAttachOptions},
         Extension, Link, SchedClassifier, TcAttachType,
     },
-    Bpf, BpfLoader,
+    Ebpf, EbpfLoader,
+};
+use bpfd_api::{
+    config::{DispatcherSource, TC_DISPATCHER_IMAGE},
+    util::directories::*,
+    ImagePullPolicy,
 };
-use bpfd_api::util::directories::*;
 use log::debug;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::Dispatcher;
+use super::{chain_chunks, dispatcher_bytecode, netns_dir, Dispatcher};
 use crate::{
     bpf::calc_map_pin_path,
     command::{
@@ -25,15 +30,20 @@ use crate::{
         Direction::{Egress, Ingress},
         Program, TcProgram,
     },
-    dispatcher_config::TcDispatcherConfig,
+    dispatcher_config::{TcDispatcherConfig, TC_MAX_DISPATCHER_ACTIONS},
     errors::BpfdError,
+    netlink,
+    netns::{in_netns, Netns},
 };
 
 const DEFAULT_PRIORITY: u32 = 50; // Default priority for user programs in the dispatcher
 const TC_DISPATCHER_PRIORITY: u16 = 50; // Default TC priority for TC Dispatcher
 const DISPATCHER_PROGRAM_NAME: &str = "tc_dispatcher";
+const CHAIN_PROGRAM_NAME: &str = "tc_chain";
+const CHAIN_MAP_NAME: &str = "chain_next";
 
 static DISPATCHER_BYTES: &[u8] = include_bytes_aligned!("../../../.output/tc_dispatcher.bpf.o");
+static CHAIN_BYTES: &[u8] = include_bytes_aligned!("../../../.output/tc_chain.bpf.o");
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TcDispatcher {
@@ -43,20 +53,38 @@ pub struct TcDispatcher {
     direction: Direction,
     priority: u16,
     handle: Option<u32>,
+    // The filter has to stay in place while bpfd is not running, so the link
+    // is never dropped, which would detach it. delete() detaches it instead.
+    #[serde(skip)]
+    link: Option<ManuallyDrop<SchedClassifierLink>>,
     #[serde(skip)]
-    loader: Option<Bpf>,
+    loader: Option<Ebpf>,
+    // The dispatcher the last slot of this one chains to, if there are more
+    // programs than fit in one dispatcher.
+    #[serde(default)]
+    next: Option<Box<TcDispatcher>>,
+    #[serde(default)]
+    chain_ids: Vec<u32>,
+    #[serde(default)]
+    netns: Option<Netns>,
 }
 
 impl TcDispatcher {
     pub(crate) async fn new(
+        source: &DispatcherSource,
         direction: Direction,
         if_index: &u32,
         if_name: String,
+        netns: Option<Netns>,
         programs: &mut [(Uuid, Program)],
         revision: u32,
-        old_dispatcher: Option<Dispatcher>,
+        old_dispatcher: Option<&mut Dispatcher>,
     ) -> Result<TcDispatcher, BpfdError> {
         debug!("TcDispatcher::new() for if_index {if_index}, revision {revision}");
+        let mut old = match old_dispatcher {
+            Some(Dispatcher::Tc(d)) => Some(d),
+            _ => None,
+        };
         let mut extensions: Vec<(&mut Uuid, &mut TcProgram)> = programs
             .iter_mut()
             .filter_map(|(k, v)| match v {
@@ -64,60 +92,115 @@ impl TcDispatcher {
                 _ => None,
             })
             .collect();
-        let mut chain_call_actions = [0; 10];
-        for (_, v) in extensions.iter() {
-            chain_call_actions[v.current_position.unwrap()] = v.proceed_on.mask()
+        extensions.sort_by(|(_, a), (_, b)| a.current_position.cmp(&b.current_position));
+
+        // The TC dispatcher used to only be built into bpfd, so its image is
+        // never pulled unless that is configured.
+        let (program_bytes, program_name) = dispatcher_bytecode(
+            source,
+            TC_DISPATCHER_IMAGE,
+            ImagePullPolicy::Never,
+            DISPATCHER_BYTES,
+            DISPATCHER_PROGRAM_NAME,
+        )
+        .await?;
+        if program_name != DISPATCHER_PROGRAM_NAME {
+            return Err(BpfdError::SectionNameNotValid(program_name));
         }
 
-        let config = TcDispatcherConfig {
-            num_progs_enabled: extensions.len() as u8,
-            chain_call_actions,
-            run_prios: [DEFAULT_PRIORITY; 10],
-        };
+        let base = fs_dir(direction, netns.as_ref().map(|n| n.id));
+        let path = format!("{base}/dispatcher_{if_index}_{revision}");
+        fs::create_dir_all(&path).unwrap();
+
+        // A dispatcher that fails to build leaves no pins behind, so that the
+        // same revision can be built again.
+        let dispatcher: Result<TcDispatcher, BpfdError> = async {
+            // Build the chain back to front, so that every dispatcher can be
+            // chained to the next one before its own predecessor is built.
+            let mut next: Option<TcDispatcher> = None;
+            for chunk in chain_chunks(&mut extensions, TC_MAX_DISPATCHER_ACTIONS)
+                .into_iter()
+                .rev()
+            {
+                let mut chain_call_actions = [0; TC_MAX_DISPATCHER_ACTIONS];
+                for (i, (_, v)) in chunk.iter().enumerate() {
+                    chain_call_actions[i] = v.proceed_on.mask()
+                }
+                // The chain slot hands back whatever the next dispatcher returns.
+                let num_progs_enabled = chunk.len() + next.is_some() as usize;
 
-        debug!("tc dispatcher config: {:?}", config);
+                let config = TcDispatcherConfig {
+                    num_progs_enabled: num_progs_enabled as u8,
+                    chain_call_actions,
+                    run_prios: [DEFAULT_PRIORITY; TC_MAX_DISPATCHER_ACTIONS],
+                };
 
-        let mut loader = BpfLoader::new()
-            .set_global("CONFIG", &config, true)
-            .load(DISPATCHER_BYTES)?;
+                debug!("tc dispatcher config: {:?}", config);
 
-        let dispatcher: &mut SchedClassifier = loader
-            .program_mut(DISPATCHER_PROGRAM_NAME)
-            .unwrap()
-            .try_into()?;
+                let mut loader = EbpfLoader::new()
+                    .set_global("CONFIG", &config, true)
+                    .load(&program_bytes)?;
 
-        dispatcher.load()?;
+                let dispatcher: &mut SchedClassifier = loader
+                    .program_mut(DISPATCHER_PROGRAM_NAME)
+                    .ok_or_else(|| BpfdError::SectionNameNotValid(program_name.clone()))?
+                    .try_into()?;
 
-        let base = match direction {
-            Ingress => RTDIR_FS_TC_INGRESS,
-            Egress => RTDIR_FS_TC_EGRESS,
-        };
-        let path = format!("{base}/dispatcher_{if_index}_{revision}");
-        fs::create_dir_all(path).unwrap();
-
-        let mut dispatcher = TcDispatcher {
-            revision,
-            if_index: *if_index,
-            if_name,
-            direction,
-            priority: TC_DISPATCHER_PRIORITY,
-            handle: None,
-            loader: Some(loader),
-        };
-        dispatcher.attach_extensions(&mut extensions).await?;
-        dispatcher.attach(old_dispatcher)?;
-        dispatcher.save()?;
+                dispatcher.load()?;
+
+                let mut dispatcher = TcDispatcher {
+                    revision,
+                    if_index: *if_index,
+                    if_name: if_name.clone(),
+                    direction,
+                    priority: TC_DISPATCHER_PRIORITY,
+                    handle: None,
+                    link: None,
+                    loader: Some(loader),
+                    next: None,
+                    chain_ids: vec![],
+                    netns: netns.clone(),
+                };
+                dispatcher.attach_extensions(chunk).await?;
+                if let Some(n) = next.take() {
+                    dispatcher.chain_to(n)?;
+                }
+                next = Some(dispatcher);
+            }
+
+            let mut dispatcher = next.expect("at least one dispatcher is built");
+            dispatcher.attach(old.as_deref_mut())?;
+            dispatcher.save()?;
+            Ok(dispatcher)
+        }
+        .await;
+        let dispatcher = dispatcher.map_err(|e| {
+            let _ = fs::remove_dir_all(&path);
+            e
+        })?;
+        if let Some(d) = old {
+            // The new dispatcher normally took over the filter of the old one,
+            // which must then not be detached. It may also have been given
+            // the handle of an old dispatcher that was no longer attached.
+            if d.handle != dispatcher.handle {
+                d.delete(true)?;
+            } else {
+                d.delete(false)?;
+            }
+        }
         Ok(dispatcher)
     }
 
-    fn attach(&mut self, old_dispatcher: Option<Dispatcher>) -> Result<(), BpfdError> {
+    // attach attaches the dispatcher in place of old, by replacing the program
+    // of old's filter, so that every packet runs through either the old or
+    // the new dispatcher and never through both. Without an old dispatcher,
+    // or if its filter is gone, a new filter is added.
+    fn attach(&mut self, old: Option<&mut TcDispatcher>) -> Result<(), BpfdError> {
         debug!(
             "TcDispatcher::attach() for if_index {}, revision {}",
             self.if_index, self.revision
         );
         let iface = self.if_name.clone();
-        // Add clsact qdisc to the interface. This is harmless if it has already been added.
-        let _ = tc::qdisc_add_clsact(&iface);
 
         let new_dispatcher: &mut SchedClassifier = self
             .loader
@@ -132,34 +215,115 @@ impl TcDispatcher {
             Direction::Egress => TcAttachType::Egress,
         };
 
-        let link_id = new_dispatcher.attach_with_options(
-            &iface,
-            attach_type,
-            TcOptions {
-                priority: self.priority,
-                ..Default::default()
-            },
-        )?;
+        let priority = self.priority;
+        let link_id = in_netns(self.netns.as_ref(), || {
+            // A dispatcher rebuilt after a restart has no link of its own, it
+            // is found by its handle instead.
+            let old_link = old.and_then(|old| {
+                let handle = old.handle?;
+                match old.link.take() {
+                    Some(link) => Some(ManuallyDrop::into_inner(link)),
+                    None => {
+                        SchedClassifierLink::attached(&iface, attach_type, old.priority, handle)
+                            .ok()
+                    }
+                }
+            });
+            if let Some(old_link) = old_link {
+                match new_dispatcher.attach_to_link(old_link) {
+                    Ok(link_id) => return Ok(link_id),
+                    Err(e) => debug!("unable to replace the TC dispatcher filter: {e}"),
+                }
+            }
+            // Add clsact qdisc to the interface. This is harmless if it has already been added.
+            let _ = tc::qdisc_add_clsact(&iface);
+            new_dispatcher.attach_with_options(
+                &iface,
+                attach_type,
+                TcAttachOptions::Netlink(NlOptions {
+                    priority,
+                    ..Default::default()
+                }),
+            )
+        })??;
 
         let link = new_dispatcher.take_link(link_id)?;
-        self.handle = Some(link.handle());
-        mem::forget(link);
-
-        if let Some(Dispatcher::Tc(mut d)) = old_dispatcher {
-            // If the old dispatcher was not attached when the new dispatcher
-            // was attached above, the new dispatcher may get the same handle
-            // as the old one had.  If this happens, the new dispatcher will get
-            // detached if we do a full delete, so don't do it.
-            if d.handle != self.handle {
-                d.delete(true)?;
-            } else {
-                d.delete(false)?;
-            }
-        }
+        self.handle = Some(link.handle()?);
+        self.link = Some(ManuallyDrop::new(link));
+        Ok(())
+    }
 
+    // chain_to replaces the last program slot of this dispatcher with a chain
+    // program that tail calls next.
+    fn chain_to(&mut self, next: TcDispatcher) -> Result<(), BpfdError> {
+        debug!(
+            "TcDispatcher::chain_to() for if_index {}, revision {}",
+            self.if_index, self.revision
+        );
+        let if_index = self.if_index;
+        let dispatcher: &mut SchedClassifier = self
+            .loader
+            .as_mut()
+            .ok_or(BpfdError::NotLoaded)?
+            .program_mut(DISPATCHER_PROGRAM_NAME)
+            .unwrap()
+            .try_into()?;
+        let next_dispatcher: &SchedClassifier = next
+            .loader
+            .as_ref()
+            .ok_or(BpfdError::NotLoaded)?
+            .program(DISPATCHER_PROGRAM_NAME)
+            .unwrap()
+            .try_into()?;
+
+        let mut loader = EbpfLoader::new()
+            .extension(CHAIN_PROGRAM_NAME)
+            .load(CHAIN_BYTES)?;
+        let mut chain_next: ProgramArray<_> = loader
+            .take_map(CHAIN_MAP_NAME)
+            .ok_or_else(|| BpfdError::Error(format!("{CHAIN_MAP_NAME} map not found")))?
+            .try_into()?;
+        let ext: &mut Extension = loader
+            .program_mut(CHAIN_PROGRAM_NAME)
+            .ok_or_else(|| BpfdError::SectionNameNotValid(CHAIN_PROGRAM_NAME.to_string()))?
+            .try_into()?;
+
+        let target_fn = format!("prog{}", TC_MAX_DISPATCHER_ACTIONS - 1);
+        ext.load(dispatcher.fd()?.try_clone()?, &target_fn)?;
+        chain_next.set(0, next_dispatcher.fd()?, 0)?;
+
+        // The kernel empties a prog array once the last reference to it from
+        // user space is gone, so it is pinned next to the chain link.
+        let base = self.fs_dir();
+        let chain_path = format!(
+            "{base}/dispatcher_{if_index}_{}/chain_{}",
+            self.revision,
+            next_dispatcher.program_info()?.id(),
+        );
+        chain_next
+            .pin(format!("{chain_path}_map"))
+            .map_err(BpfdError::UnableToPinMap)?;
+
+        let link_id = ext.attach()?;
+        let link: FdLink = ext.take_link(link_id)?.into();
+        link.pin(chain_path).map_err(BpfdError::UnableToPinLink)?;
+
+        self.chain_ids = vec![
+            ext.program_info()?.id(),
+            next_dispatcher.program_info()?.id(),
+        ];
+        self.next = Some(Box::new(next));
         Ok(())
     }
 
+    pub(crate) fn chain_program_ids(&self) -> Vec<u32> {
+        let mut ids = self.chain_ids.clone();
+        if let Some(next) = &self.next {
+            ids.extend(next.chain_program_ids());
+        }
+        ids
+    }
+
     async fn attach_extensions(
         &mut self,
         extensions: &mut [(&mut Uuid, &mut TcProgram)],
@@ -187,10 +351,7 @@ impl TcDispatcher {
                     .attach_to_program(dispatcher.fd().unwrap(), &target_fn)
                     .unwrap();
                 let new_link: FdLink = ext.take_link(new_link_id)?.into();
-                let base = match self.direction {
-                    Direction::Ingress => RTDIR_FS_TC_INGRESS,
-                    Direction::Egress => RTDIR_FS_TC_EGRESS,
-                };
+                let base = self.fs_dir();
                 let path = format!("{base}/dispatcher_{if_index}_{}/link_{k}", self.revision);
                 new_link.pin(path).map_err(BpfdError::UnableToPinLink)?;
             } else {
@@ -198,7 +359,7 @@ impl TcDispatcher {
                 let name = v.data.name();
                 let global_data = v.data.global_data();
 
-                let mut bpf = BpfLoader::new();
+                let mut bpf = EbpfLoader::new();
 
                 for (name, value) in global_data {
                     bpf.set_global(name, value.as_slice(), true);
@@ -228,10 +389,7 @@ impl TcDispatcher {
                 let new_link_id = ext.attach()?;
                 let new_link = ext.take_link(new_link_id)?;
                 let fd_link: FdLink = new_link.into();
-                let base = match self.direction {
-                    Direction::Ingress => RTDIR_FS_TC_INGRESS,
-                    Direction::Egress => RTDIR_FS_TC_EGRESS,
-                };
+                let base = self.fs_dir();
                 fd_link
                     .pin(format!(
                         "{base}/dispatcher_{if_index}_{}/link_{k}",
@@ -248,10 +406,9 @@ impl TcDispatcher {
             "TcDispatcher::save() for if_index {}, revision {}",
             self.if_index, self.revision
         );
-        let base = match self.direction {
-            Direction::Ingress => RTDIR_TC_INGRESS_DISPATCHER,
-            Direction::Egress => RTDIR_TC_EGRESS_DISPATCHER,
-        };
+        let base = self.state_dir();
+        fs::create_dir_all(&base)
+            .map_err(|e| BpfdError::Error(format!("can't create state dir: {e}")))?;
         let path = format!("{base}/{}_{}", self.if_index, self.revision);
         serde_json::to_writer(&fs::File::create(path).unwrap(), &self)
             .map_err(|e| BpfdError::Error(format!("can't save state: {e}")))?;
@@ -262,12 +419,10 @@ impl TcDispatcher {
         if_index: u32,
         direction: Direction,
         revision: u32,
+        netns: Option<u64>,
     ) -> Result<Self, anyhow::Error> {
         debug!("TcDispatcher::load() for if_index {if_index}, revision {revision}");
-        let dir = match direction {
-            Direction::Ingress => RTDIR_TC_INGRESS_DISPATCHER,
-            Direction::Egress => RTDIR_TC_EGRESS_DISPATCHER,
-        };
+        let dir = state_dir(direction, netns);
         let path = format!("{dir}/{if_index}_{revision}");
         let file = fs::File::open(path)?;
         let reader = BufReader::new(file);
@@ -281,18 +436,12 @@ impl TcDispatcher {
             "TcDispatcher::delete() for if_index {}, revision {}",
             self.if_index, self.revision
         );
-        let base = match self.direction {
-            Direction::Ingress => RTDIR_TC_INGRESS_DISPATCHER,
-            Direction::Egress => RTDIR_TC_EGRESS_DISPATCHER,
-        };
+        let base = self.state_dir();
         let path = format!("{base}/{}_{}", self.if_index, self.revision);
         fs::remove_file(path)
             .map_err(|e| BpfdError::Error(format!("unable to cleanup state: {e}")))?;
 
-        let base = match self.direction {
-            Direction::Ingress => RTDIR_FS_TC_INGRESS,
-            Direction::Egress => RTDIR_FS_TC_EGRESS,
-        };
+        let base = self.fs_dir();
         let path = format!("{base}/dispatcher_{}_{}", self.if_index, self.revision);
         fs::remove_dir_all(path)
             .map_err(|e| BpfdError::Error(format!("unable to cleanup state: {e}")))?;
@@ -304,13 +453,24 @@ impl TcDispatcher {
                     Direction::Ingress => TcAttachType::Ingress,
                     Direction::Egress => TcAttachType::Egress,
                 };
-                if let Ok(old_link) = SchedClassifierLink::attached(
-                    &self.if_name,
-                    attach_type,
-                    self.priority,
-                    old_handle,
-                ) {
-                    let detach_result = old_link.detach();
+                // A dispatcher rebuilt after a restart has no link of its
+                // own, it is found by its handle instead.
+                let link = self.link.take().map(ManuallyDrop::into_inner);
+                // There is nothing left to detach if the namespace is gone.
+                if let Ok(Ok(detach_result)) = in_netns(self.netns.as_ref(), || {
+                    link.map_or_else(
+                        || {
+                            SchedClassifierLink::attached(
+                                &self.if_name,
+                                attach_type,
+                                self.priority,
+                                old_handle,
+                            )
+                        },
+                        Ok,
+                    )
+                    .map(|old_link| old_link.detach())
+                }) {
                     match detach_result {
                         Ok(_) => debug!(
                             "TC dispatcher {}, {}, {}, {} sucessfully detached",
@@ -330,4 +490,56 @@ impl TcDispatcher {
     pub(crate) fn if_name(&self) -> String {
         self.if_name.clone()
     }
+
+    pub(crate) fn netns(&self) -> Option<&Netns> {
+        self.netns.as_ref()
+    }
+
+    fn fs_dir(&self) -> String {
+        fs_dir(self.direction, self.netns.as_ref().map(|n| n.id))
+    }
+
+    fn state_dir(&self) -> String {
+        state_dir(self.direction, self.netns.as_ref().map(|n| n.id))
+    }
+
+    pub(crate) async fn is_attached(&self) -> Result<bool, BpfdError> {
+        match self.handle {
+            Some(handle) => {
+                netlink::tc_filter_exists(
+                    self.if_index,
+                    self.direction,
+                    self.priority,
+                    handle,
+                    self.netns.as_ref(),
+                )
+                .await
+            }
+            None => Ok(false),
+        }
+    }
+
+    // forget_link drops the handle of the filter that no longer attaches the
+    // dispatcher, so that replacing this revision does not detach a filter
+    // that may have been given the same handle since.
+    pub(crate) fn forget_link(&mut self) -> Result<(), BpfdError> {
+        self.handle = None;
+        Ok(())
+    }
+}
+
+fn fs_dir(direction: Direction, netns: Option<u64>) -> String {
+    let base = match direction {
+        Ingress => RTDIR_FS_TC_INGRESS,
+        Egress => RTDIR_FS_TC_EGRESS,
+    };
+    netns_dir(base, netns)
+}
+
+fn state_dir(direction: Direction, netns: Option<u64>) -> String {
+    let base = match direction {
+        Ingress => RTDIR_TC_INGRESS_DISPATCHER,
+        Egress => RTDIR_TC_EGRESS_DISPATCHER,
+    };
+    netns_dir(base, netns)
 }
@@ -4,27 +4,42 @@
 use std::{fs, io::BufReader, path::PathBuf};
 
 use aya::{
+    include_bytes_aligned,
+    maps::ProgramArray,
     programs::{
         links::{FdLink, PinnedLink},
         Extension, Xdp,
     },
-    Bpf, BpfLoader,
+    Ebpf, EbpfLoader,
+};
+use bpfd_api::{
+    config::{DispatcherSource, XdpMode, XDP_DISPATCHER_IMAGE},
+    util::directories::*,
+    ImagePullPolicy,
 };
-use bpfd_api::{config::XdpMode, util::directories::*, ImagePullPolicy};
 use log::debug;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use super::Dispatcher;
+use super::{chain_chunks, dispatcher_bytecode, netns_dir, Dispatcher};
 use crate::{
     bpf::calc_map_pin_path,
     command::{Program, XdpProgram},
-    dispatcher_config::XdpDispatcherConfig,
+    dispatcher_config::{XdpDispatcherConfig, MAX_DISPATCHER_ACTIONS},
     errors::BpfdError,
-    oci_utils::{image_manager::get_bytecode_from_image_store, BytecodeImage},
+    netlink,
+    netns::{in_netns, Netns},
+    utils::section_name,
 };
 
 pub(crate) const DEFAULT_PRIORITY: u32 = 50;
+const BPF_F_XDP_HAS_FRAGS: u32 = 1 << 5;
+const CHAIN_PROGRAM_NAME: &str = "xdp_chain";
+const CHAIN_MAP_NAME: &str = "chain_next";
+const DISPATCHER_PROGRAM_NAME: &str = "xdp_dispatcher";
+
+static DISPATCHER_BYTES: &[u8] = include_bytes_aligned!("../../../.output/xdp_dispatcher_v3.bpf.o");
+static CHAIN_BYTES: &[u8] = include_bytes_aligned!("../../../.output/xdp_chain.bpf.o");
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct XdpDispatcher {
@@ -33,18 +48,32 @@ pub struct XdpDispatcher {
     if_name: String,
     mode: XdpMode,
     #[serde(skip)]
-    loader: Option<Bpf>,
+    loader: Option<Ebpf>,
     progam_name: Option<String>,
+    // The kernel id of the dispatcher program, which is what the interface
+    // reports as its XDP program while this revision is attached.
+    #[serde(default)]
+    program_id: u32,
+    // The dispatcher the last slot of this one chains to, if there are more
+    // programs than fit in one dispatcher.
+    #[serde(default)]
+    next: Option<Box<XdpDispatcher>>,
+    #[serde(default)]
+    chain_ids: Vec<u32>,
+    #[serde(default)]
+    netns: Option<Netns>,
 }
 
 impl XdpDispatcher {
     pub(crate) async fn new(
+        source: &DispatcherSource,
         mode: XdpMode,
         if_index: &u32,
         if_name: String,
+        netns: Option<Netns>,
         programs: &mut [(Uuid, Program)],
         revision: u32,
-        old_dispatcher: Option<Dispatcher>,
+        old_dispatcher: Option<&mut Dispatcher>,
     ) -> Result<XdpDispatcher, BpfdError> {
         debug!("XdpDispatcher::new() for if_index {if_index}, revision {revision}");
         let mut extensions: Vec<(&mut Uuid, &mut XdpProgram)> = programs
@@ -54,55 +83,110 @@ impl XdpDispatcher {
                 _ => None,
             })
             .collect();
-        let mut chain_call_actions = [0; 10];
         extensions.sort_by(|(_, a), (_, b)| a.current_position.cmp(&b.current_position));
-        for (_, p) in extensions.iter() {
-            chain_call_actions[p.current_position.unwrap()] = p.proceed_on.mask();
-        }
 
-        let config = XdpDispatcherConfig::new(
-            extensions.len() as u8,
-            0x0,
-            chain_call_actions,
-            [DEFAULT_PRIORITY; 10],
-            [0; 10],
-        );
+        // bpfd only puts frags aware programs together with other frags aware
+        // programs, so the first one decides for the whole dispatcher.
+        let frags = extensions.first().map_or(false, |(_, p)| p.frags);
+        if frags && extensions.len() > MAX_DISPATCHER_ACTIONS {
+            return Err(BpfdError::Error(
+                "frags aware XDP dispatchers can not be chained".to_string(),
+            ));
+        }
 
-        debug!("xdp dispatcher config: {:?}", config);
-        let image = BytecodeImage::new(
-            "quay.io/bpfd/xdp-dispatcher:v2".to_string(),
-            ImagePullPolicy::IfNotPresent as i32,
-            None,
-            None,
-        );
-        let (path, section_name) = image
-            .get_image(None)
-            .await
-            .map_err(|e| BpfdError::BpfBytecodeError(e.into()))?;
-        let program_bytes = get_bytecode_from_image_store(path).await?;
-        let mut loader = BpfLoader::new()
-            .set_global("conf", &config, true)
-            .load(&program_bytes)?;
-
-        let dispatcher: &mut Xdp = loader.program_mut(&section_name).unwrap().try_into()?;
-
-        dispatcher.load()?;
-
-        let path = format!("{RTDIR_FS_XDP}/dispatcher_{if_index}_{revision}");
-        fs::create_dir_all(path).unwrap();
-
-        let mut dispatcher = XdpDispatcher {
-            if_index: *if_index,
-            if_name,
-            revision,
-            mode,
-            loader: Some(loader),
-            progam_name: Some(section_name),
+        let (program_bytes, section_name) = dispatcher_bytecode(
+            source,
+            XDP_DISPATCHER_IMAGE,
+            ImagePullPolicy::IfNotPresent,
+            DISPATCHER_BYTES,
+            DISPATCHER_PROGRAM_NAME,
+        )
+        .await?;
+        // Since v3 the dispatcher also carries a copy of itself in an
+        // xdp.frags section, which is loaded with BPF_F_XDP_HAS_FRAGS. Older
+        // dispatcher images lack it and can only run programs without frags.
+        let program_name = if frags {
+            format!("{section_name}_frags")
+        } else {
+            section_name
         };
-        dispatcher.attach_extensions(&mut extensions).await?;
-        dispatcher.attach()?;
-        dispatcher.save()?;
-        if let Some(mut old) = old_dispatcher {
+
+        let fs_dir = netns_dir(RTDIR_FS_XDP, netns.as_ref().map(|n| n.id));
+        let path = format!("{fs_dir}/dispatcher_{if_index}_{revision}");
+        fs::create_dir_all(&path).unwrap();
+
+        // A dispatcher that fails to build leaves no pins behind, so that the
+        // same revision can be built again.
+        let dispatcher: Result<XdpDispatcher, BpfdError> = async {
+            // Build the chain back to front, so that every dispatcher can be
+            // chained to the next one before its own predecessor is built.
+            let mut next: Option<XdpDispatcher> = None;
+            for chunk in chain_chunks(&mut extensions, MAX_DISPATCHER_ACTIONS)
+                .into_iter()
+                .rev()
+            {
+                let mut chain_call_actions = [0; MAX_DISPATCHER_ACTIONS];
+                let mut program_flags = [0; MAX_DISPATCHER_ACTIONS];
+                for (i, (_, p)) in chunk.iter().enumerate() {
+                    chain_call_actions[i] = p.proceed_on.mask();
+                    if p.frags {
+                        program_flags[i] = BPF_F_XDP_HAS_FRAGS;
+                    }
+                }
+                // The chain slot hands back whatever the next dispatcher returns.
+                let num_progs_enabled = chunk.len() + next.is_some() as usize;
+
+                let config = XdpDispatcherConfig::new(
+                    num_progs_enabled as u8,
+                    frags as u8,
+                    chain_call_actions,
+                    [DEFAULT_PRIORITY; MAX_DISPATCHER_ACTIONS],
+                    program_flags,
+                );
+
+                debug!("xdp dispatcher config: {:?}", config);
+                let mut loader = EbpfLoader::new()
+                    .set_global("conf", &config, true)
+                    .load(&program_bytes)?;
+
+                let dispatcher: &mut Xdp = loader
+                    .program_mut(&program_name)
+                    .ok_or_else(|| BpfdError::SectionNameNotValid(program_name.clone()))?
+                    .try_into()?;
+
+                dispatcher.load()?;
+                let program_id = dispatcher.program_info()?.id();
+
+                let mut dispatcher = XdpDispatcher {
+                    if_index: *if_index,
+                    if_name: if_name.clone(),
+                    revision,
+                    mode,
+                    loader: Some(loader),
+                    progam_name: Some(program_name.clone()),
+                    program_id,
+                    next: None,
+                    chain_ids: vec![],
+                    netns: netns.clone(),
+                };
+                dispatcher.attach_extensions(chunk).await?;
+                if let Some(n) = next.take() {
+                    dispatcher.chain_to(n)?;
+                }
+                next = Some(dispatcher);
+            }
+
+            let mut dispatcher = next.expect("at least one dispatcher is built");
+            dispatcher.attach()?;
+            dispatcher.save()?;
+            Ok(dispatcher)
+        }
+        .await;
+        let dispatcher = dispatcher.map_err(|e| {
+            let _ = fs::remove_dir_all(&path);
+            e
+        })?;
+        if let Some(old) = old_dispatcher {
             old.delete(false)?;
         }
         Ok(dispatcher)
@@ -114,6 +198,7 @@ impl XdpDispatcher {
             self.if_index, self.revision
         );
         let if_index = self.if_index;
+        let fs_dir = netns_dir(RTDIR_FS_XDP, self.netns_id());
         let iface = self.if_name.clone();
         let dispatcher: &mut Xdp = self
             .loader
@@ -123,7 +208,7 @@ impl XdpDispatcher {
             .unwrap()
             .try_into()?;
 
-        let path = PathBuf::from(format!("{RTDIR_FS_XDP}/dispatcher_{if_index}_link"));
+        let path = PathBuf::from(format!("{fs_dir}/dispatcher_{if_index}_link"));
         if path.exists() {
             let pinned_link: FdLink = PinnedLink::from_pin(path).unwrap().into();
             dispatcher
@@ -131,9 +216,9 @@ impl XdpDispatcher {
                 .unwrap();
         } else {
             let flags = self.mode.as_flags();
-            let link = dispatcher.attach(&iface, flags).unwrap();
+            let link = in_netns(self.netns.as_ref(), || dispatcher.attach(&iface, flags))?.unwrap();
             let owned_link = dispatcher.take_link(link)?;
-            let path = format!("{RTDIR_FS_XDP}/dispatcher_{if_index}_link");
+            let path = format!("{fs_dir}/dispatcher_{if_index}_link");
             let _ = TryInto::<FdLink>::try_into(owned_link)
                 .unwrap() // TODO: Don't unwrap, although due to minimum kernel version this shouldn't ever panic
                 .pin(path)
@@ -142,6 +227,77 @@ impl XdpDispatcher {
         Ok(())
     }
 
+    // chain_to replaces the last program slot of this dispatcher with a chain
+    // program that tail calls next.
+    fn chain_to(&mut self, next: XdpDispatcher) -> Result<(), BpfdError> {
+        debug!(
+            "XdpDispatcher::chain_to() for if_index {}, revision {}",
+            self.if_index, self.revision
+        );
+        let if_index = self.if_index;
+        let fs_dir = netns_dir(RTDIR_FS_XDP, self.netns_id());
+        let dispatcher: &mut Xdp = self
+            .loader
+            .as_mut()
+            .ok_or(BpfdError::NotLoaded)?
+            .program_mut(self.progam_name.clone().unwrap().as_str())
+            .unwrap()
+            .try_into()?;
+        let next_dispatcher: &Xdp = next
+            .loader
+            .as_ref()
+            .ok_or(BpfdError::NotLoaded)?
+            .program(next.progam_name.clone().unwrap().as_str())
+            .unwrap()
+            .try_into()?;
+
+        let mut loader = EbpfLoader::new()
+            .extension(CHAIN_PROGRAM_NAME)
+            .load(CHAIN_BYTES)?;
+        let mut chain_next: ProgramArray<_> = loader
+            .take_map(CHAIN_MAP_NAME)
+            .ok_or_else(|| BpfdError::Error(format!("{CHAIN_MAP_NAME} map not found")))?
+            .try_into()?;
+        let ext: &mut Extension = loader
+            .program_mut(CHAIN_PROGRAM_NAME)
+            .ok_or_else(|| BpfdError::SectionNameNotValid(CHAIN_PROGRAM_NAME.to_string()))?
+            .try_into()?;
+
+        let target_fn = format!("prog{}", MAX_DISPATCHER_ACTIONS - 1);
+        ext.load(dispatcher.fd()?.try_clone()?, &target_fn)?;
+        chain_next.set(0, next_dispatcher.fd()?, 0)?;
+
+        // The kernel empties a prog array once the last reference to it from
+        // user space is gone, so it is pinned next to the chain link.
+        let chain_path = format!(
+            "{fs_dir}/dispatcher_{if_index}_{}/chain_{}",
+            self.revision,
+            next_dispatcher.program_info()?.id(),
+        );
+        chain_next
+            .pin(format!("{chain_path}_map"))
+            .map_err(BpfdError::UnableToPinMap)?;
+
+        let link_id = ext.attach()?;
+        let link: FdLink = ext.take_link(link_id)?.into();
+        link.pin(chain_path).map_err(BpfdError::UnableToPinLink)?;
+
+        self.chain_ids = vec![
+            ext.program_info()?.id(),
+            next_dispatcher.program_info()?.id(),
+        ];
+        self.next = Some(Box::new(next));
+        Ok(())
+    }
+
+    pub(crate) fn chain_program_ids(&self) -> Vec<u32> {
+        let mut ids = self.chain_ids.clone();
+        if let Some(next) = &self.next {
+            ids.extend(next.chain_program_ids());
+        }
+        ids
+    }
+
     async fn attach_extensions(
         &mut self,
         extensions: &mut [(&mut Uuid, &mut XdpProgram)],
@@ -151,6 +307,7 @@ impl XdpDispatcher {
             self.if_index, self.revision
         );
         let if_index = self.if_index;
+        let fs_dir = netns_dir(RTDIR_FS_XDP, self.netns_id());
         let dispatcher: &mut Xdp = self
             .loader
             .as_mut()
@@ -167,17 +324,14 @@ impl XdpDispatcher {
                     .attach_to_program(dispatcher.fd().unwrap(), &target_fn)
                     .unwrap();
                 let new_link: FdLink = ext.take_link(new_link_id)?.into();
-                let path = format!(
-                    "{RTDIR_FS_XDP}/dispatcher_{if_index}_{}/link_{k}",
-                    self.revision
-                );
+                let path = format!("{fs_dir}/dispatcher_{if_index}_{}/link_{k}", self.revision);
                 new_link.pin(path).map_err(BpfdError::UnableToPinLink)?;
             } else {
                 let program_bytes = v.data.program_bytes().await?;
                 let name = v.data.name();
                 let global_data = v.data.global_data();
 
-                let mut bpf = BpfLoader::new();
+                let mut bpf = EbpfLoader::new();
 
                 for (name, value) in global_data {
                     bpf.set_global(name, value.as_slice(), true);
@@ -209,7 +363,7 @@ impl XdpDispatcher {
                 let fd_link: FdLink = new_link.into();
                 fd_link
                     .pin(format!(
-                        "{RTDIR_FS_XDP}/dispatcher_{if_index}_{}/link_{k}",
+                        "{fs_dir}/dispatcher_{if_index}_{}/link_{k}",
                         self.revision,
                     ))
                     .map_err(BpfdError::UnableToPinLink)?;
@@ -223,15 +377,19 @@ impl XdpDispatcher {
             "XdpDispatcher::save() for if_index {}, revision {}",
             self.if_index, self.revision
         );
-        let path = format!("{RTDIR_XDP_DISPATCHER}/{}_{}", self.if_index, self.revision);
+        let dir = netns_dir(RTDIR_XDP_DISPATCHER, self.netns_id());
+        fs::create_dir_all(&dir)
+            .map_err(|e| BpfdError::Error(format!("can't create state dir: {e}")))?;
+        let path = format!("{dir}/{}_{}", self.if_index, self.revision);
         serde_json::to_writer(&fs::File::create(path).unwrap(), &self)
             .map_err(|e| BpfdError::Error(format!("can't save state: {e}")))?;
         Ok(())
     }
 
-    pub fn load(if_index: u32, revision: u32) -> Result<Self, anyhow::Error> {
+    pub fn load(if_index: u32, revision: u32, netns: Option<u64>) -> Result<Self, anyhow::Error> {
         debug!("XdpDispatcher::load() for if_index {if_index}, revision {revision}");
-        let path = format!("{RTDIR_XDP_DISPATCHER}/{if_index}_{revision}");
+        let dir = netns_dir(RTDIR_XDP_DISPATCHER, netns);
+        let path = format!("{dir}/{if_index}_{revision}");
         let file = fs::File::open(path)?;
         let reader = BufReader::new(file);
         let prog = serde_json::from_reader(reader)?;
@@ -244,18 +402,17 @@ impl XdpDispatcher {
             "XdpDispatcher::delete() for if_index {}, revision {}",
             self.if_index, self.revision
         );
-        let path = format!("{RTDIR_XDP_DISPATCHER}/{}_{}", self.if_index, self.revision);
+        let dir = netns_dir(RTDIR_XDP_DISPATCHER, self.netns_id());
+        let path = format!("{dir}/{}_{}", self.if_index, self.revision);
         fs::remove_file(path)
             .map_err(|e| BpfdError::Error(format!("unable to cleanup state: {e}")))?;
 
-        let path = format!(
-            "{RTDIR_FS_XDP}/dispatcher_{}_{}",
-            self.if_index, self.revision
-        );
+        let fs_dir = netns_dir(RTDIR_FS_XDP, self.netns_id());
+        let path = format!("{fs_dir}/dispatcher_{}_{}", self.if_index, self.revision);
         fs::remove_dir_all(path)
             .map_err(|e| BpfdError::Error(format!("unable to cleanup state: {e}")))?;
         if full {
-            let path_link = format!("{RTDIR_FS_XDP}/dispatcher_{}_link", self.if_index);
+            let path_link = format!("{fs_dir}/dispatcher_{}_link", self.if_index);
             fs::remove_file(path_link)
                 .map_err(|e| BpfdError::Error(format!("unable to cleanup state: {e}")))?;
         }
@@ -265,4 +422,40 @@ impl XdpDispatcher {
     pub(crate) fn if_name(&self) -> String {
         self.if_name.clone()
     }
+
+    pub(crate) fn netns(&self) -> Option<&Netns> {
+        self.netns.as_ref()
+    }
+
+    fn netns_id(&self) -> Option<u64> {
+        self.netns.as_ref().map(|n| n.id)
+    }
+
+    // is_attached checks that the interface runs this revision, and not no
+    // program or one that was attached in its place outside of bpfd.
+    pub(crate) async fn is_attached(&self) -> Result<bool, BpfdError> {
+        Ok(
+            netlink::xdp_prog_id(self.if_index, self.netns.as_ref()).await?
+                == Some(self.program_id),
+        )
+    }
+
+    // forget_link unpins the link that no longer attaches the dispatcher to
+    // the interface, so that the next revision is attached with a new one.
+    pub(crate) fn forget_link(&mut self) -> Result<(), BpfdError> {
+        let fs_dir = netns_dir(RTDIR_FS_XDP, self.netns_id());
+        let path = PathBuf::from(format!("{fs_dir}/dispatcher_{}_link", self.if_index));
+        if path.exists() {
+            fs::remove_file(path)
+                .map_err(|e| BpfdError::Error(format!("unable to cleanup state: {e}")))?;
+        }
+        Ok(())
+    }
+}
+
+// xdp_has_frags reports whether the program name in bytecode is in an
+// xdp.frags section, which marks it as able to handle multi-buffer packets.
+pub(crate) fn xdp_has_frags(bytecode: &[u8], name: &str) -> Result<bool, BpfdError> {
+    let section_name = section_name(bytecode, name)?;
+    Ok(section_name == "xdp.frags" || section_name.starts_with("xdp.frags/"))
 }
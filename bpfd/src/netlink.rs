@@ -0,0 +1,167 @@
+// SPDX-License-Identifier: (MIT OR Apache-2.0)
+// Copyright Authors of bpfd
+
+//! Queries rtnetlink for what is attached to an interface, to find out when
+//! something other than bpfd changed it, and follows links as they come and
+//! go.
+use netlink_packet_core::{NetlinkMessage, NetlinkPayload, NLM_F_DUMP, NLM_F_REQUEST};
+use netlink_packet_route::{
+    link::nlas::{Nla, Xdp},
+    RtnlMessage, TcMessage, IFF_UP,
+};
+use nix::errno::Errno;
+use rtnetlink::{
+    constants::RTMGRP_LINK,
+    new_connection,
+    sys::{AsyncSocket, SocketAddr},
+    Handle,
+};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::{
+    command::Direction,
+    errors::BpfdError,
+    netns::{in_netns, Netns},
+};
+
+// Parents of the ingress and egress hooks of the clsact qdisc,
+// TC_H_MAKE(TC_H_CLSACT, TC_H_MIN_INGRESS) and TC_H_MIN_EGRESS.
+const TC_H_CLSACT_INGRESS: u32 = 0xFFFF_FFF2;
+const TC_H_CLSACT_EGRESS: u32 = 0xFFFF_FFF3;
+
+// connect opens a netlink socket in netns, which it keeps talking to after
+// the thread returns to bpfd's own namespace.
+fn connect(netns: Option<&Netns>) -> Result<Handle, BpfdError> {
+    let (connection, handle, _) = in_netns(netns, new_connection)?
+        .map_err(|e| BpfdError::Error(format!("unable to open netlink socket: {e}")))?;
+    tokio::spawn(connection);
+    Ok(handle)
+}
+
+/// Returns the id of the XDP program attached to if_index, if there is one.
+pub(crate) async fn xdp_prog_id(
+    if_index: u32,
+    netns: Option<&Netns>,
+) -> Result<Option<u32>, BpfdError> {
+    let handle = connect(netns)?;
+    let links = handle.link().get().match_index(if_index).execute();
+    tokio::pin!(links);
+
+    while let Some(link) = links.next().await {
+        let link =
+            link.map_err(|e| BpfdError::Error(format!("unable to get link {if_index}: {e}")))?;
+        for nla in link.nlas {
+            if let Nla::Xdp(xdp) = nla {
+                for attr in xdp {
+                    if let Xdp::ProgId(id) = attr {
+                        if id != 0 {
+                            return Ok(Some(id));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Returns None if there is no link with if_index, otherwise whether it is up.
+pub(crate) async fn link_state(if_index: u32) -> Result<Option<bool>, BpfdError> {
+    let handle = connect(None)?;
+    let links = handle.link().get().match_index(if_index).execute();
+    tokio::pin!(links);
+
+    match links.next().await {
+        Some(Ok(link)) => Ok(Some(link.header.flags & IFF_UP != 0)),
+        Some(Err(rtnetlink::Error::NetlinkError(e))) if e.raw_code() == -(Errno::ENODEV as i32) => {
+            Ok(None)
+        }
+        Some(Err(e)) => Err(BpfdError::Error(format!(
+            "unable to get link {if_index}: {e}"
+        ))),
+        None => Ok(None),
+    }
+}
+
+/// A link that was added, removed or changed, e.g. renamed or set up or
+/// down.
+#[derive(Debug)]
+pub(crate) struct LinkEvent {
+    pub(crate) if_index: u32,
+    pub(crate) if_name: Option<String>,
+    pub(crate) removed: bool,
+}
+
+/// Subscribes to the link events of the RTNLGRP_LINK multicast group.
+pub(crate) fn link_events() -> Result<impl Stream<Item = LinkEvent> + Send + Unpin, BpfdError> {
+    let (mut connection, _, messages) = new_connection()
+        .map_err(|e| BpfdError::Error(format!("unable to open netlink socket: {e}")))?;
+    connection
+        .socket_mut()
+        .socket_mut()
+        .bind(&SocketAddr::new(0, RTMGRP_LINK))
+        .map_err(|e| BpfdError::Error(format!("unable to subscribe to link events: {e}")))?;
+    tokio::spawn(connection);
+
+    Ok(messages.filter_map(|(message, _)| {
+        let (link, removed) = match message.payload {
+            NetlinkPayload::InnerMessage(RtnlMessage::NewLink(link)) => (link, false),
+            NetlinkPayload::InnerMessage(RtnlMessage::DelLink(link)) => (link, true),
+            _ => return None,
+        };
+        let if_name = link.nlas.into_iter().find_map(|nla| match nla {
+            Nla::IfName(name) => Some(name),
+            _ => None,
+        });
+        Some(LinkEvent {
+            if_index: link.header.index,
+            if_name,
+            removed,
+        })
+    }))
+}
+
+/// Returns true if if_index has a TC filter with the given priority and
+/// handle on the direction hook of its clsact qdisc.
+pub(crate) async fn tc_filter_exists(
+    if_index: u32,
+    direction: Direction,
+    priority: u16,
+    filter_handle: u32,
+    netns: Option<&Netns>,
+) -> Result<bool, BpfdError> {
+    let mut handle = connect(netns)?;
+
+    let mut message = TcMessage::with_index(if_index as i32);
+    message.header.parent = match direction {
+        Direction::Ingress => TC_H_CLSACT_INGRESS,
+        Direction::Egress => TC_H_CLSACT_EGRESS,
+    };
+    let mut request = NetlinkMessage::from(RtnlMessage::GetTrafficFilter(message));
+    request.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
+
+    let response = handle
+        .request(request)
+        .map_err(|e| BpfdError::Error(format!("unable to list tc filters: {e}")))?;
+    tokio::pin!(response);
+
+    while let Some(msg) = response.next().await {
+        match msg.payload {
+            NetlinkPayload::InnerMessage(RtnlMessage::NewTrafficFilter(filter)) => {
+                // tcm_info holds the priority in its upper 16 bits.
+                if filter.header.handle == filter_handle
+                    && (filter.header.info >> 16) as u16 == priority
+                {
+                    return Ok(true);
+                }
+            }
+            NetlinkPayload::Error(e) => {
+                return Err(BpfdError::Error(format!(
+                    "unable to list tc filters: {e:?}"
+                )))
+            }
+            _ => (),
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,96 @@
+// SPDX-License-Identifier: (MIT OR Apache-2.0)
+// Copyright Authors of bpfd
+
+//! Runs code inside another network namespace, for XDP and TC programs that
+//! attach to interfaces outside of bpfd's own namespace, e.g. the pod end of
+//! a veth pair.
+use std::{
+    fs::{self, File},
+    os::unix::{fs::MetadataExt, io::AsRawFd},
+    path::{Path, PathBuf},
+};
+
+use nix::sched::{setns, CloneFlags};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::BpfdError;
+
+const OWN_NETNS: &str = "/proc/thread-self/ns/net";
+
+/// A network namespace other than bpfd's own, given by the path to its nsfs
+/// file, e.g. /var/run/netns/<name> or /proc/<pid>/ns/net.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Netns {
+    pub(crate) path: PathBuf,
+    /// The inode of the namespace, which is the same whatever path it is
+    /// opened through.
+    pub(crate) id: u64,
+}
+
+impl Netns {
+    /// Returns None for an empty path, or a path to bpfd's own namespace.
+    pub(crate) fn open(path: &str) -> Result<Option<Self>, BpfdError> {
+        if path.is_empty() {
+            return Ok(None);
+        }
+        let id = netns_id(path)?;
+        if id == netns_id(OWN_NETNS)? {
+            return Ok(None);
+        }
+        Ok(Some(Netns {
+            path: PathBuf::from(path),
+            id,
+        }))
+    }
+}
+
+fn netns_id<P: AsRef<Path>>(path: P) -> Result<u64, BpfdError> {
+    fs::metadata(path.as_ref()).map(|m| m.ino()).map_err(|e| {
+        BpfdError::Error(format!(
+            "unable to open network namespace {}: {e}",
+            path.as_ref().display()
+        ))
+    })
+}
+
+/// Runs f inside netns, or as is if netns is None. Only the calling thread
+/// enters netns, and it returns to its own namespace before in_netns does,
+/// so f must not hand work to other threads or tasks.
+pub(crate) fn in_netns<T>(netns: Option<&Netns>, f: impl FnOnce() -> T) -> Result<T, BpfdError> {
+    let Some(netns) = netns else {
+        return Ok(f());
+    };
+    let own = File::open(OWN_NETNS)
+        .map_err(|e| BpfdError::Error(format!("unable to open own network namespace: {e}")))?;
+    let target = File::open(&netns.path).map_err(|e| {
+        BpfdError::Error(format!(
+            "unable to open network namespace {}: {e}",
+            netns.path.display()
+        ))
+    })?;
+
+    setns(target.as_raw_fd(), CloneFlags::CLONE_NEWNET).map_err(|e| {
+        BpfdError::Error(format!(
+            "unable to enter network namespace {}: {e}",
+            netns.path.display()
+        ))
+    })?;
+    let res = f();
+    // A thread left in the wrong namespace would quietly attach later
+    // programs there, so there is no carrying on if this fails.
+    setns(own.as_raw_fd(), CloneFlags::CLONE_NEWNET)
+        .expect("unable to return to own network namespace");
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_own_netns() {
+        assert!(Netns::open("").unwrap().is_none());
+        assert!(Netns::open("/proc/self/ns/net").unwrap().is_none());
+        assert!(Netns::open("/var/run/netns/does-not-exist").is_err());
+    }
+}
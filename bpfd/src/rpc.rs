@@ -1,41 +1,131 @@
 // SPDX-License-Identifier: (MIT OR Apache-2.0)
 // Copyright Authors of bpfd
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{ErrorKind, Read},
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use bpfd_api::{
+    util::directories::RTDIR_FS,
     v1::{
-        list_response::{list_result, list_result::AttachInfo, ListResult},
+        get_maps_response,
+        list_response::{list_result, list_result::AttachInfo, Attachment, ListResult},
         load_request,
         load_request_common::Location,
         loader_server::Loader,
-        KprobeAttachInfo, ListRequest, ListResponse, LoadRequest, LoadResponse,
-        PullBytecodeRequest, PullBytecodeResponse, TcAttachInfo, TracepointAttachInfo,
-        UnloadRequest, UnloadResponse, UprobeAttachInfo, XdpAttachInfo,
+        perf_event_attach_info::SamplePolicy,
+        replace_request, CgroupAttachInfo, FentryAttachInfo, FexitAttachInfo,
+        FlowDissectorAttachInfo, GetMapsRequest, GetMapsResponse, IterAttachInfo, KprobeAttachInfo,
+        ListRequest, ListResponse, LoadRequest, LoadResponse, LsmAttachInfo, MapDeleteRequest,
+        MapDeleteResponse, MapDumpRequest, MapDumpResponse, MapEntry, MapLookupRequest,
+        MapLookupResponse, MapUpdateRequest, MapUpdateResponse, PerfEventAttachInfo,
+        PullBytecodeRequest, PullBytecodeResponse, RawTracepointAttachInfo, ReadIteratorRequest,
+        ReadIteratorResponse, ReplaceRequest, ReplaceResponse, SetEnabledRequest,
+        SetEnabledResponse, SkLookupAttachInfo, SkMsgAttachInfo, SkSkbAttachInfo,
+        SocketFilterAttachInfo, SubscribePerfEventArrayRequest, SubscribePerfEventArrayResponse,
+        SubscribeRingBufRequest, SubscribeRingBufResponse, TcAttachInfo, TpBtfAttachInfo,
+        TracepointAttachInfo, UnloadRequest, UnloadResponse, UpdateRequest, UpdateResponse,
+        UprobeAttachInfo, WatchMapRequest, WatchMapResponse, XdpAttachInfo,
     },
-    TcProceedOn, XdpProceedOn,
+    CgroupAttachType, TcProceedOn, XdpProceedOn,
 };
 use log::warn;
-use tokio::sync::{mpsc, mpsc::Sender, oneshot};
+use tokio::{
+    sync::{
+        broadcast::{self, error::TryRecvError},
+        mpsc,
+        mpsc::Sender,
+        oneshot,
+    },
+    time::{interval, MissedTickBehavior},
+};
+use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
-use crate::command::{
-    Command, KprobeProgram, LoadArgs, Program, ProgramData, PullBytecodeArgs, TcProgram,
-    TracepointProgram, UnloadArgs, UprobeProgram, XdpProgram,
+use crate::{
+    command::{
+        CgroupProgram, Command, FentryProgram, FexitProgram, FlowDissectorProgram, GetMapsArgs,
+        IterProgram, KernelProgramInfo, KprobeProgram, LoadArgs, LsmProgram, OpenMapArgs,
+        PerfEventProgram, PerfEventSamplePolicy, Program, ProgramData, PullBytecodeArgs,
+        RawTracepointProgram, ReadIteratorArgs, ReplaceArgs, SetEnabledArgs, SkLookupProgram,
+        SkMsgProgram, SkSkbProgram, SocketFilterProgram, TcProgram, TpBtfProgram,
+        TracepointProgram, UnloadArgs, UpdateArgs, UprobeProgram, XdpProgram,
+    },
+    errors::BpfdError,
+    events::{Event, EventMap, Subscriptions},
+    maps::PinnedMap,
+    netns::Netns,
 };
 
 #[derive(Debug)]
 pub struct BpfdLoader {
     tx: Arc<Mutex<Sender<Command>>>,
+    subscriptions: Subscriptions,
 }
 
 impl BpfdLoader {
     pub(crate) fn new(tx: mpsc::Sender<Command>) -> BpfdLoader {
         let tx = Arc::new(Mutex::new(tx));
-        BpfdLoader { tx }
+        BpfdLoader {
+            tx,
+            subscriptions: Subscriptions::default(),
+        }
+    }
+
+    // open_map has the manager open a map of a program, which the map RPCs
+    // then use directly.
+    async fn open_map(&self, id: &str, name: String, rpc: &str) -> Result<PinnedMap, Status> {
+        let id = id
+            .parse()
+            .map_err(|_| Status::invalid_argument("invalid id"))?;
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::OpenMap(OpenMapArgs {
+            id,
+            name,
+            responder: resp_tx,
+        });
+
+        let tx = self.tx.lock().unwrap().clone();
+        tx.send(cmd).await.unwrap();
+
+        // Await the response
+        match resp_rx.await {
+            Ok(res) => res.map_err(|e| {
+                warn!("BPFD {rpc} error: {}", e);
+                Status::aborted(format!("{e}"))
+            }),
+            Err(e) => {
+                warn!("RPC {rpc} error: {}", e);
+                Err(Status::aborted(format!("{e}")))
+            }
+        }
+    }
+
+    // subscribe has a task forward the records of a ring buffer or perf event
+    // array to the client, as long as it is connected.
+    async fn subscribe<T: Send + 'static>(
+        &self,
+        id: &str,
+        name: String,
+        kind: EventMap,
+        rpc: &str,
+        response: fn(Vec<Vec<u8>>, u64, u64) -> T,
+    ) -> Result<ReceiverStream<Result<T, Status>>, Status> {
+        let map = self.open_map(id, name, rpc).await?;
+        let events = self.subscriptions.subscribe(map, kind).map_err(|e| {
+            warn!("BPFD {rpc} error: {}", e);
+            Status::aborted(format!("{e}"))
+        })?;
+
+        let (stream_tx, stream_rx) = mpsc::channel(ITER_STREAM_DEPTH);
+        tokio::spawn(forward_events(events, stream_tx, response));
+
+        Ok(ReceiverStream::new(stream_rx))
     }
 }
 
@@ -64,16 +154,7 @@ impl Loader for BpfdLoader {
             None => None,
         };
 
-        let map_owner_uuid = if common.map_owner_uuid.is_none()
-            || common.map_owner_uuid.clone().unwrap().is_empty()
-        {
-            None
-        } else {
-            Some(
-                Uuid::parse_str(&common.map_owner_uuid.unwrap())
-                    .map_err(|_| Status::aborted("invalid UUID for map_owner_uuid"))?,
-            )
-        };
+        let map_owner_uuid = parse_map_owner_uuid(common.map_owner_uuid)?;
 
         let data = ProgramData::new(
             bytecode_source,
@@ -83,16 +164,23 @@ impl Loader for BpfdLoader {
             map_owner_uuid,
         );
 
+        let mut ifaces = vec![];
         let load_args = LoadArgs {
             program: match request.attach_info.unwrap() {
-                load_request::AttachInfo::XdpAttachInfo(attach) => Program::Xdp(XdpProgram::new(
-                    data,
-                    attach.priority,
-                    attach.iface,
-                    XdpProceedOn::from_int32s(attach.proceed_on)
-                        .map_err(|_| Status::aborted("failed to parse proceed_on"))?,
-                )),
+                load_request::AttachInfo::XdpAttachInfo(attach) => {
+                    ifaces = attach.ifaces;
+                    Program::Xdp(XdpProgram::new(
+                        data,
+                        attach.priority,
+                        attach.iface,
+                        XdpProceedOn::from_int32s(attach.proceed_on)
+                            .map_err(|_| Status::aborted("failed to parse proceed_on"))?,
+                        attach.wait_for_iface,
+                        parse_netns(&attach.netns)?,
+                    ))
+                }
                 load_request::AttachInfo::TcAttachInfo(attach) => {
+                    ifaces = attach.ifaces;
                     let direction = attach
                         .direction
                         .try_into()
@@ -104,6 +192,8 @@ impl Loader for BpfdLoader {
                         TcProceedOn::from_int32s(attach.proceed_on)
                             .map_err(|_| Status::aborted("failed to parse proceed_on"))?,
                         direction,
+                        attach.wait_for_iface,
+                        parse_netns(&attach.netns)?,
                     ))
                 }
                 load_request::AttachInfo::TracepointAttachInfo(attach) => {
@@ -129,7 +219,100 @@ impl Loader for BpfdLoader {
                         attach.namespace,
                     ))
                 }
+                load_request::AttachInfo::FentryAttachInfo(attach) => {
+                    Program::Fentry(FentryProgram::new(data, attach.fn_name))
+                }
+                load_request::AttachInfo::FexitAttachInfo(attach) => {
+                    Program::Fexit(FexitProgram::new(data, attach.fn_name))
+                }
+                load_request::AttachInfo::LsmAttachInfo(attach) => {
+                    Program::Lsm(LsmProgram::new(data, attach.hook))
+                }
+                load_request::AttachInfo::CgroupAttachInfo(attach) => {
+                    let attach_type: CgroupAttachType = attach
+                        .attach_type
+                        .try_into()
+                        .map_err(|e| Status::aborted(format!("{e}")))?;
+                    if attach_type.program_type() as u32 != common.program_type {
+                        return Err(Status::aborted(format!(
+                            "attach type {attach_type} is not valid for program type {}",
+                            common.program_type
+                        )));
+                    }
+                    Program::Cgroup(CgroupProgram::new(data, attach.cgroup_path, attach_type))
+                }
+                load_request::AttachInfo::SkMsgAttachInfo(attach) => {
+                    Program::SkMsg(SkMsgProgram::new(
+                        data,
+                        parse_map_owner_uuid(attach.map_owner_uuid)?,
+                        attach.map_name,
+                    ))
+                }
+                load_request::AttachInfo::SkSkbAttachInfo(attach) => {
+                    let attach_type = attach
+                        .attach_type
+                        .try_into()
+                        .map_err(|e| Status::aborted(format!("{e}")))?;
+                    Program::SkSkb(SkSkbProgram::new(
+                        data,
+                        parse_map_owner_uuid(attach.map_owner_uuid)?,
+                        attach.map_name,
+                        attach_type,
+                    ))
+                }
+                load_request::AttachInfo::SkLookupAttachInfo(attach) => {
+                    Program::SkLookup(SkLookupProgram::new(data, attach.netns))
+                }
+                load_request::AttachInfo::SocketFilterAttachInfo(_) => {
+                    Program::SocketFilter(SocketFilterProgram::new(data))
+                }
+                load_request::AttachInfo::FlowDissectorAttachInfo(attach) => {
+                    Program::FlowDissector(FlowDissectorProgram::new(data, attach.netns))
+                }
+                load_request::AttachInfo::IterAttachInfo(attach) => {
+                    Program::Iter(IterProgram::new(data, attach.iter_type))
+                }
+                load_request::AttachInfo::RawTracepointAttachInfo(attach) => {
+                    Program::RawTracepoint(RawTracepointProgram::new(data, attach.tracepoint))
+                }
+                load_request::AttachInfo::TpBtfAttachInfo(attach) => {
+                    Program::TpBtf(TpBtfProgram::new(data, attach.tracepoint))
+                }
+                load_request::AttachInfo::PerfEventAttachInfo(attach) => {
+                    let event_type = attach
+                        .event_type
+                        .try_into()
+                        .map_err(|e| Status::aborted(format!("{e}")))?;
+                    let sample_policy = match attach.sample_policy {
+                        Some(SamplePolicy::SamplePeriod(p)) => PerfEventSamplePolicy::Period(p),
+                        Some(SamplePolicy::SampleFrequency(f)) => {
+                            PerfEventSamplePolicy::Frequency(f)
+                        }
+                        None => {
+                            return Err(Status::aborted(
+                                "sample_period or sample_frequency is required",
+                            ))
+                        }
+                    };
+                    // -1 is what perf_event_open takes for any process.
+                    let pid = match attach.pid {
+                        None | Some(-1) => None,
+                        Some(pid) => Some(
+                            u32::try_from(pid)
+                                .map_err(|_| Status::aborted(format!("invalid pid {pid}")))?,
+                        ),
+                    };
+                    Program::PerfEvent(PerfEventProgram::new(
+                        data,
+                        event_type,
+                        attach.config,
+                        sample_policy,
+                        attach.cpus,
+                        pid,
+                    ))
+                }
             },
+            ifaces,
             responder: resp_tx,
         };
 
@@ -205,7 +388,19 @@ impl Loader for BpfdLoader {
         match resp_rx.await {
             Ok(res) => match res {
                 Ok(results) => {
+                    // Programs loaded for the other interfaces of a group are
+                    // listed as attachments of the program of its first one.
+                    let mut attachments: HashMap<Uuid, Vec<Attachment>> = HashMap::new();
+                    for r in results.iter() {
+                        if let Some(group) = r.group() {
+                            attachments.entry(group).or_default().push(attachment(r));
+                        }
+                    }
+
                     for r in results {
+                        if r.group().is_some() {
+                            continue;
+                        }
                         let program_type = r.kind() as u32;
 
                         // initial prog type filtering
@@ -215,9 +410,17 @@ impl Loader for BpfdLoader {
                             }
                         }
 
-                        let kernel_info = r
-                            .kernel_info()
-                            .expect("kernel info should be set for all loaded programs");
+                        // Pending programs are not in the kernel yet, so they
+                        // have no kernel info.
+                        let kernel_info = match r.kernel_info() {
+                            Some(info) => info.clone(),
+                            None if r.pending() => KernelProgramInfo::default(),
+                            None => {
+                                let e = format!("program {} has no kernel info", r.name());
+                                warn!("BPFD list error: {}", e);
+                                return Err(Status::internal(e));
+                            }
+                        };
 
                         let mut reply_entry = ListResult {
                             id: None,
@@ -240,6 +443,9 @@ impl Loader for BpfdLoader {
                             bytes_jited: kernel_info.bytes_jited,
                             bytes_memlock: kernel_info.bytes_memlock,
                             verified_insns: kernel_info.verified_insns,
+                            enabled: r.enabled(),
+                            pending: r.pending(),
+                            attachments: Vec::new(),
                         };
 
                         match r.data() {
@@ -255,6 +461,14 @@ impl Loader for BpfdLoader {
                                 // populate id
                                 reply_entry.id = data.id().map(|v| v.to_string());
 
+                                if let Some(mut members) =
+                                    data.id().and_then(|id| attachments.remove(&id))
+                                {
+                                    members.sort_by(|a, b| a.iface.cmp(&b.iface));
+                                    reply_entry.attachments = vec![attachment(&r)];
+                                    reply_entry.attachments.append(&mut members);
+                                }
+
                                 reply_entry.map_pin_path = data
                                     .map_pin_path()
                                     .map_or(String::new(), |v| v.to_str().unwrap().to_string());
@@ -304,6 +518,10 @@ impl Loader for BpfdLoader {
                                             iface: p.iface,
                                             position: p.current_position.unwrap_or(0) as i32,
                                             proceed_on: p.proceed_on.as_action_vec(),
+                                            frags: p.frags,
+                                            wait_for_iface: p.wait_for_iface,
+                                            netns: netns_path(&p.netns),
+                                            ifaces: Vec::new(),
                                         }))
                                     }
                                     Program::Tc(p) => {
@@ -313,6 +531,9 @@ impl Loader for BpfdLoader {
                                             position: p.current_position.unwrap_or(0) as i32,
                                             direction: p.direction.to_string(),
                                             proceed_on: p.proceed_on.as_action_vec(),
+                                            wait_for_iface: p.wait_for_iface,
+                                            netns: netns_path(&p.netns),
+                                            ifaces: Vec::new(),
                                         }))
                                     }
                                     Program::Tracepoint(p) => Some(
@@ -338,6 +559,92 @@ impl Loader for BpfdLoader {
                                             namespace: p.namespace,
                                         }))
                                     }
+                                    Program::Fentry(p) => {
+                                        Some(AttachInfo::FentryAttachInfo(FentryAttachInfo {
+                                            fn_name: p.fn_name,
+                                        }))
+                                    }
+                                    Program::Fexit(p) => {
+                                        Some(AttachInfo::FexitAttachInfo(FexitAttachInfo {
+                                            fn_name: p.fn_name,
+                                        }))
+                                    }
+                                    Program::Lsm(p) => {
+                                        Some(AttachInfo::LsmAttachInfo(LsmAttachInfo {
+                                            hook: p.hook,
+                                        }))
+                                    }
+                                    Program::Cgroup(p) => {
+                                        Some(AttachInfo::CgroupAttachInfo(CgroupAttachInfo {
+                                            cgroup_path: p.cgroup_path,
+                                            attach_type: p.attach_type.to_string(),
+                                        }))
+                                    }
+                                    Program::SkMsg(p) => {
+                                        Some(AttachInfo::SkMsgAttachInfo(SkMsgAttachInfo {
+                                            map_owner_uuid: p.map_owner_id.map(|u| u.to_string()),
+                                            map_name: p.map_name,
+                                        }))
+                                    }
+                                    Program::SkSkb(p) => {
+                                        Some(AttachInfo::SkSkbAttachInfo(SkSkbAttachInfo {
+                                            map_owner_uuid: p.map_owner_id.map(|u| u.to_string()),
+                                            map_name: p.map_name,
+                                            attach_type: p.attach_type.to_string(),
+                                        }))
+                                    }
+                                    Program::SkLookup(p) => {
+                                        Some(AttachInfo::SkLookupAttachInfo(SkLookupAttachInfo {
+                                            netns: p.netns,
+                                        }))
+                                    }
+                                    Program::RawTracepoint(p) => {
+                                        Some(AttachInfo::RawTracepointAttachInfo(
+                                            RawTracepointAttachInfo {
+                                                tracepoint: p.tracepoint,
+                                            },
+                                        ))
+                                    }
+                                    Program::TpBtf(p) => {
+                                        Some(AttachInfo::TpBtfAttachInfo(TpBtfAttachInfo {
+                                            tracepoint: p.tracepoint,
+                                        }))
+                                    }
+                                    Program::PerfEvent(p) => {
+                                        Some(AttachInfo::PerfEventAttachInfo(PerfEventAttachInfo {
+                                            event_type: p.event_type.to_string(),
+                                            config: p.config,
+                                            sample_policy: Some(match p.sample_policy {
+                                                PerfEventSamplePolicy::Period(p) => {
+                                                    SamplePolicy::SamplePeriod(p)
+                                                }
+                                                PerfEventSamplePolicy::Frequency(f) => {
+                                                    SamplePolicy::SampleFrequency(f)
+                                                }
+                                            }),
+                                            cpus: p.cpus,
+                                            pid: p.pid.and_then(|pid| pid.try_into().ok()),
+                                        }))
+                                    }
+                                    Program::SocketFilter(_) => {
+                                        Some(AttachInfo::SocketFilterAttachInfo(
+                                            SocketFilterAttachInfo {
+                                                pin_path: data.id().map_or(String::new(), |id| {
+                                                    format!("{RTDIR_FS}/prog_{id}")
+                                                }),
+                                            },
+                                        ))
+                                    }
+                                    Program::FlowDissector(p) => {
+                                        Some(AttachInfo::FlowDissectorAttachInfo(
+                                            FlowDissectorAttachInfo { netns: p.netns },
+                                        ))
+                                    }
+                                    Program::Iter(p) => {
+                                        Some(AttachInfo::IterAttachInfo(IterAttachInfo {
+                                            iter_type: p.iter_type,
+                                        }))
+                                    }
                                     Program::Unsupported(_) => None,
                                 };
 
@@ -396,17 +703,631 @@ impl Loader for BpfdLoader {
             }
         }
     }
+
+    type ReadIteratorStream = ReceiverStream<Result<ReadIteratorResponse, Status>>;
+
+    async fn read_iterator(
+        &self,
+        request: Request<ReadIteratorRequest>,
+    ) -> Result<Response<Self::ReadIteratorStream>, Status> {
+        let request = request.into_inner();
+        let id = request
+            .id
+            .parse()
+            .map_err(|_| Status::invalid_argument("invalid id"))?;
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::ReadIterator(ReadIteratorArgs {
+            id,
+            responder: resp_tx,
+        });
+
+        let tx = self.tx.lock().unwrap().clone();
+        tx.send(cmd).await.unwrap();
+
+        // Await the response
+        let file = match resp_rx.await {
+            Ok(res) => res.map_err(|e| {
+                warn!("BPFD read_iterator error: {:#?}", e);
+                Status::aborted(format!("{e}"))
+            })?,
+            Err(e) => {
+                warn!("RPC read_iterator error: {:#?}", e);
+                return Err(Status::aborted(format!("{e}")));
+            }
+        };
+
+        let (stream_tx, stream_rx) = mpsc::channel(ITER_STREAM_DEPTH);
+        tokio::task::spawn_blocking(move || stream_iterator(file, stream_tx));
+
+        Ok(Response::new(ReceiverStream::new(stream_rx)))
+    }
+
+    async fn update(
+        &self,
+        request: Request<UpdateRequest>,
+    ) -> Result<Response<UpdateResponse>, Status> {
+        let reply = UpdateResponse {};
+        let request = request.into_inner();
+        let id = request
+            .id
+            .parse()
+            .map_err(|_| Status::invalid_argument("invalid id"))?;
+        let proceed_on = if request.proceed_on.is_empty() {
+            None
+        } else {
+            Some(request.proceed_on)
+        };
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::Update(UpdateArgs {
+            id,
+            priority: request.priority,
+            proceed_on,
+            responder: resp_tx,
+        });
+
+        let tx = self.tx.lock().unwrap().clone();
+        tx.send(cmd).await.unwrap();
+
+        // Await the response
+        match resp_rx.await {
+            Ok(res) => match res {
+                Ok(_) => Ok(Response::new(reply)),
+                Err(e) => {
+                    warn!("BPFD update error: {}", e);
+                    Err(Status::aborted(format!("{e}")))
+                }
+            },
+            Err(e) => {
+                warn!("RPC update error: {}", e);
+                Err(Status::aborted(format!("{e}")))
+            }
+        }
+    }
+
+    async fn set_enabled(
+        &self,
+        request: Request<SetEnabledRequest>,
+    ) -> Result<Response<SetEnabledResponse>, Status> {
+        let reply = SetEnabledResponse {};
+        let request = request.into_inner();
+        let id = request
+            .id
+            .parse()
+            .map_err(|_| Status::invalid_argument("invalid id"))?;
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::SetEnabled(SetEnabledArgs {
+            id,
+            enabled: request.enabled,
+            responder: resp_tx,
+        });
+
+        let tx = self.tx.lock().unwrap().clone();
+        tx.send(cmd).await.unwrap();
+
+        // Await the response
+        match resp_rx.await {
+            Ok(res) => match res {
+                Ok(_) => Ok(Response::new(reply)),
+                Err(e) => {
+                    warn!("BPFD set_enabled error: {}", e);
+                    Err(Status::aborted(format!("{e}")))
+                }
+            },
+            Err(e) => {
+                warn!("RPC set_enabled error: {}", e);
+                Err(Status::aborted(format!("{e}")))
+            }
+        }
+    }
+
+    async fn get_maps(
+        &self,
+        request: Request<GetMapsRequest>,
+    ) -> Result<Response<GetMapsResponse>, Status> {
+        let request = request.into_inner();
+        let id = request
+            .id
+            .parse()
+            .map_err(|_| Status::invalid_argument("invalid id"))?;
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::GetMaps(GetMapsArgs {
+            id,
+            responder: resp_tx,
+        });
+
+        let tx = self.tx.lock().unwrap().clone();
+        tx.send(cmd).await.unwrap();
+
+        // Await the response
+        match resp_rx.await {
+            Ok(res) => match res {
+                Ok(maps) => Ok(Response::new(GetMapsResponse {
+                    maps: maps
+                        .iter()
+                        .map(|m| get_maps_response::MapInfo {
+                            name: m.name.clone(),
+                            map_type: m.type_name(),
+                            key_size: m.key_size,
+                            value_size: m.value_size,
+                            max_entries: m.max_entries,
+                            flags: m.flags,
+                            pin_path: m.pin_path.to_string_lossy().to_string(),
+                            map_id: m.id,
+                        })
+                        .collect(),
+                })),
+                Err(e) => {
+                    warn!("BPFD get_maps error: {}", e);
+                    Err(Status::aborted(format!("{e}")))
+                }
+            },
+            Err(e) => {
+                warn!("RPC get_maps error: {}", e);
+                Err(Status::aborted(format!("{e}")))
+            }
+        }
+    }
+
+    async fn map_lookup(
+        &self,
+        request: Request<MapLookupRequest>,
+    ) -> Result<Response<MapLookupResponse>, Status> {
+        let request = request.into_inner();
+        let map = self
+            .open_map(&request.id, request.map_name, "map_lookup")
+            .await?;
+
+        match map.lookup(&request.key) {
+            Ok(Some(value)) => Ok(Response::new(MapLookupResponse {
+                value_json: map
+                    .to_json(&request.key, &value)
+                    .map_or(String::new(), |(_, v)| v.to_string()),
+                value,
+            })),
+            Ok(None) => Err(Status::aborted(format!(
+                "key not found in map {}",
+                map.info().name
+            ))),
+            Err(e) => {
+                warn!("BPFD map_lookup error: {}", e);
+                Err(Status::aborted(format!("{e}")))
+            }
+        }
+    }
+
+    async fn map_update(
+        &self,
+        request: Request<MapUpdateRequest>,
+    ) -> Result<Response<MapUpdateResponse>, Status> {
+        let request = request.into_inner();
+        let map = self
+            .open_map(&request.id, request.map_name, "map_update")
+            .await?;
+
+        // The entries before one that fails stay written, which the error
+        // has to tell.
+        let mut updated = 0;
+        for entry in request.entries {
+            map.update(&entry.key, &entry.value, request.flags)
+                .map_err(|e| {
+                    warn!("BPFD map_update error: {}", e);
+                    Status::aborted(format!("{e}, after writing {updated} entries"))
+                })?;
+            updated += 1;
+        }
+        Ok(Response::new(MapUpdateResponse { updated }))
+    }
+
+    async fn map_delete(
+        &self,
+        request: Request<MapDeleteRequest>,
+    ) -> Result<Response<MapDeleteResponse>, Status> {
+        let request = request.into_inner();
+        let map = self
+            .open_map(&request.id, request.map_name, "map_delete")
+            .await?;
+
+        let mut deleted = 0;
+        for key in request.keys {
+            let held = map.delete(&key).map_err(|e| {
+                warn!("BPFD map_delete error: {}", e);
+                Status::aborted(format!("{e}"))
+            })?;
+            if held {
+                deleted += 1;
+            }
+        }
+        Ok(Response::new(MapDeleteResponse { deleted }))
+    }
+
+    type MapDumpStream = ReceiverStream<Result<MapDumpResponse, Status>>;
+
+    async fn map_dump(
+        &self,
+        request: Request<MapDumpRequest>,
+    ) -> Result<Response<Self::MapDumpStream>, Status> {
+        let request = request.into_inner();
+        let map = self
+            .open_map(&request.id, request.map_name, "map_dump")
+            .await?;
+        let batch_size = match request.batch_size {
+            0 => MAP_DUMP_BATCH_SIZE,
+            n => (n as usize).min(MAP_DUMP_MAX_BATCH_SIZE),
+        };
+
+        let (stream_tx, stream_rx) = mpsc::channel(ITER_STREAM_DEPTH);
+        tokio::task::spawn_blocking(move || stream_map(map, batch_size, stream_tx));
+
+        Ok(Response::new(ReceiverStream::new(stream_rx)))
+    }
+
+    type WatchMapStream = ReceiverStream<Result<WatchMapResponse, Status>>;
+
+    async fn watch_map(
+        &self,
+        request: Request<WatchMapRequest>,
+    ) -> Result<Response<Self::WatchMapStream>, Status> {
+        let request = request.into_inner();
+        let map = self
+            .open_map(&request.id, request.map_name, "watch_map")
+            .await?;
+        let map_type = map.info().type_name();
+        if !matches!(
+            map_type.as_str(),
+            "hash" | "array" | "percpu_hash" | "percpu_array" | "lru_hash" | "lru_percpu_hash"
+        ) {
+            let e = BpfdError::WrongMapType(map.info().name.clone(), "hash or array".to_string());
+            warn!("BPFD watch_map error: {}", e);
+            return Err(Status::aborted(format!("{e}")));
+        }
+        let period = match request.interval_ms {
+            0 => WATCH_MAP_INTERVAL,
+            n => Duration::from_millis(n as u64).max(WATCH_MAP_MIN_INTERVAL),
+        };
+        let sum = map.info().per_cpu() && !request.per_cpu;
+
+        let (stream_tx, stream_rx) = mpsc::channel(ITER_STREAM_DEPTH);
+        tokio::spawn(watch_map(map, period, sum, stream_tx));
+
+        Ok(Response::new(ReceiverStream::new(stream_rx)))
+    }
+
+    type SubscribeRingBufStream = ReceiverStream<Result<SubscribeRingBufResponse, Status>>;
+
+    async fn subscribe_ring_buf(
+        &self,
+        request: Request<SubscribeRingBufRequest>,
+    ) -> Result<Response<Self::SubscribeRingBufStream>, Status> {
+        let request = request.into_inner();
+        let stream = self
+            .subscribe(
+                &request.id,
+                request.map_name,
+                EventMap::RingBuf,
+                "subscribe_ring_buf",
+                |records, dropped, _| SubscribeRingBufResponse { records, dropped },
+            )
+            .await?;
+        Ok(Response::new(stream))
+    }
+
+    type SubscribePerfEventArrayStream =
+        ReceiverStream<Result<SubscribePerfEventArrayResponse, Status>>;
+
+    async fn subscribe_perf_event_array(
+        &self,
+        request: Request<SubscribePerfEventArrayRequest>,
+    ) -> Result<Response<Self::SubscribePerfEventArrayStream>, Status> {
+        let request = request.into_inner();
+        let stream = self
+            .subscribe(
+                &request.id,
+                request.map_name,
+                EventMap::PerfEventArray,
+                "subscribe_perf_event_array",
+                |records, dropped, lost| SubscribePerfEventArrayResponse {
+                    records,
+                    dropped,
+                    lost,
+                },
+            )
+            .await?;
+        Ok(Response::new(stream))
+    }
+
+    async fn replace(
+        &self,
+        request: Request<ReplaceRequest>,
+    ) -> Result<Response<ReplaceResponse>, Status> {
+        let request = request.into_inner();
+        let id = request
+            .id
+            .parse()
+            .map_err(|_| Status::invalid_argument("invalid id"))?;
+        let location = match request.location {
+            Some(replace_request::Location::Image(i)) => crate::command::Location::Image(i.into()),
+            Some(replace_request::Location::File(p)) => crate::command::Location::File(p),
+            None => return Err(Status::aborted("missing location")),
+        };
+
+        let (resp_tx, resp_rx) = oneshot::channel();
+        let cmd = Command::Replace(ReplaceArgs {
+            id,
+            location,
+            keep_id: request.keep_id,
+            responder: resp_tx,
+        });
+
+        let tx = self.tx.lock().unwrap().clone();
+        tx.send(cmd).await.unwrap();
+
+        // Await the response
+        match resp_rx.await {
+            Ok(res) => match res {
+                Ok(id) => Ok(Response::new(ReplaceResponse { id: id.to_string() })),
+                Err(e) => {
+                    warn!("BPFD replace error: {}", e);
+                    Err(Status::aborted(format!("{e}")))
+                }
+            },
+            Err(e) => {
+                warn!("RPC replace error: {}", e);
+                Err(Status::aborted(format!("{e}")))
+            }
+        }
+    }
+}
+
+const ITER_READ_SIZE: usize = 4096;
+const ITER_STREAM_DEPTH: usize = 16;
+
+const MAP_DUMP_BATCH_SIZE: usize = 256;
+const MAP_DUMP_MAX_BATCH_SIZE: usize = 4096;
+
+// The most records sent to a subscriber in one response.
+const SUBSCRIBE_BATCH_SIZE: usize = 256;
+
+const WATCH_MAP_INTERVAL: Duration = Duration::from_secs(1);
+const WATCH_MAP_MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+// map_entry returns an entry of map, decoded into JSON if its BTF is known.
+// sum tells that the value is the sum of a per-CPU value.
+fn map_entry(map: &PinnedMap, key: Vec<u8>, value: Vec<u8>, sum: bool) -> MapEntry {
+    let json = if sum {
+        map.sum_to_json(&key, &value)
+    } else {
+        map.to_json(&key, &value)
+    };
+    let (key_json, value_json) = json.map_or((String::new(), String::new()), |(k, v)| {
+        (k.to_string(), v.to_string())
+    });
+    MapEntry {
+        key,
+        value,
+        key_json,
+        value_json,
+    }
+}
+
+// stream_map sends the entries of map in batches, until all were sent or the
+// client goes away.
+fn stream_map(map: PinnedMap, batch_size: usize, tx: Sender<Result<MapDumpResponse, Status>>) {
+    let res = map.dump(batch_size, |entries| {
+        let msg = MapDumpResponse {
+            entries: entries
+                .into_iter()
+                .map(|e| map_entry(&map, e.key, e.value, false))
+                .collect(),
+        };
+        tx.blocking_send(Ok(msg)).is_ok()
+    });
+    if let Err(e) = res {
+        warn!("BPFD map_dump error: {}", e);
+        let _ = tx.blocking_send(Err(Status::aborted(format!("{e}"))));
+    }
+}
+
+// watch_map samples map every period, and sends the entries that were
+// added, changed or removed since the previous sample, until the client goes
+// away. sum has the values of a per-CPU map summed.
+async fn watch_map(
+    map: PinnedMap,
+    period: Duration,
+    sum: bool,
+    tx: Sender<Result<WatchMapResponse, Status>>,
+) {
+    let map = Arc::new(map);
+    let mut ticks = interval(period);
+    // A slow dump pushes the next sample back rather than bunching them up.
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut previous: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+    let mut first = true;
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = tx.closed() => return,
+        }
+        let sampled = map.clone();
+        let current = match tokio::task::spawn_blocking(move || sample_map(&sampled, sum)).await {
+            Ok(Ok(current)) => current,
+            Ok(Err(e)) => {
+                warn!("BPFD watch_map error: {}", e);
+                let _ = tx.send(Err(Status::aborted(format!("{e}")))).await;
+                return;
+            }
+            Err(e) => {
+                warn!("BPFD watch_map error: {}", e);
+                let _ = tx.send(Err(Status::internal(format!("{e}")))).await;
+                return;
+            }
+        };
+
+        let mut msg = WatchMapResponse::default();
+        for (key, value) in &current {
+            match previous.remove(key) {
+                None => msg
+                    .added
+                    .push(map_entry(&map, key.clone(), value.clone(), sum)),
+                Some(old) if old != *value => {
+                    msg.changed
+                        .push(map_entry(&map, key.clone(), value.clone(), sum))
+                }
+                Some(_) => {}
+            }
+        }
+        // What is left of the previous sample was removed.
+        msg.removed = previous
+            .into_iter()
+            .map(|(key, value)| map_entry(&map, key, value, sum))
+            .collect();
+        previous = current;
+
+        let changed = !msg.added.is_empty() || !msg.changed.is_empty() || !msg.removed.is_empty();
+        if (first || changed) && tx.send(Ok(msg)).await.is_err() {
+            return;
+        }
+        first = false;
+    }
+}
+
+// sample_map returns all entries of map, with the values of a per-CPU map
+// summed if sum is set.
+fn sample_map(map: &PinnedMap, sum: bool) -> Result<BTreeMap<Vec<u8>, Vec<u8>>, BpfdError> {
+    let mut current = BTreeMap::new();
+    map.dump(MAP_DUMP_BATCH_SIZE, |entries| {
+        for e in entries {
+            let value = if sum { map.sum_cpus(&e.value) } else { e.value };
+            current.insert(e.key, value);
+        }
+        true
+    })?;
+    Ok(current)
+}
+
+// forward_events sends the records of a map to a subscriber, batching those
+// that arrive while it is busy. A subscriber that falls behind by more than
+// the reader keeps misses records, which the next response counts as dropped.
+async fn forward_events<T>(
+    mut events: broadcast::Receiver<Event>,
+    tx: Sender<Result<T, Status>>,
+    response: fn(Vec<Vec<u8>>, u64, u64) -> T,
+) {
+    loop {
+        let mut next = tokio::select! {
+            event = events.recv() => event.map_err(|e| match e {
+                broadcast::error::RecvError::Closed => TryRecvError::Closed,
+                broadcast::error::RecvError::Lagged(n) => TryRecvError::Lagged(n),
+            }),
+            _ = tx.closed() => return,
+        };
+        let (mut records, mut dropped, mut lost) = (vec![], 0, 0);
+        let mut error = None;
+        let mut done = false;
+        loop {
+            match next {
+                Ok(Event::Record(record)) => records.push(record),
+                Ok(Event::Lost(n)) => lost += n,
+                Ok(Event::Error(e)) => error = Some(e),
+                Err(TryRecvError::Lagged(n)) => dropped += n,
+                Err(TryRecvError::Closed) => done = true,
+                Err(TryRecvError::Empty) => break,
+            }
+            if error.is_some() || done || records.len() >= SUBSCRIBE_BATCH_SIZE {
+                break;
+            }
+            next = events.try_recv();
+        }
+
+        let empty = records.is_empty() && dropped == 0 && lost == 0;
+        if !empty && tx.send(Ok(response(records, dropped, lost))).await.is_err() {
+            return;
+        }
+        if let Some(e) = error {
+            let _ = tx.send(Err(Status::aborted(e))).await;
+            return;
+        }
+        if done {
+            return;
+        }
+    }
+}
+
+// stream_iterator runs the iterator by reading it until the kernel signals
+// the end, and forwards its output in chunks. Reading stops as soon as the
+// client goes away.
+fn stream_iterator(mut file: File, tx: Sender<Result<ReadIteratorResponse, Status>>) {
+    let mut buf = vec![0u8; ITER_READ_SIZE];
+    loop {
+        let msg = match file.read(&mut buf) {
+            Ok(0) => return,
+            Ok(n) => Ok(ReadIteratorResponse {
+                data: buf[..n].to_vec(),
+            }),
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => Err(Status::aborted(format!("failed to read iterator: {e}"))),
+        };
+        let failed = msg.is_err();
+        if tx.blocking_send(msg).is_err() || failed {
+            return;
+        }
+    }
+}
+
+fn parse_map_owner_uuid(map_owner_uuid: Option<String>) -> Result<Option<Uuid>, Status> {
+    match map_owner_uuid {
+        Some(uuid) if !uuid.is_empty() => {
+            Ok(Some(Uuid::parse_str(&uuid).map_err(|_| {
+                Status::aborted("invalid UUID for map_owner_uuid")
+            })?))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn parse_netns(netns: &str) -> Result<Option<Netns>, Status> {
+    Netns::open(netns).map_err(|e| Status::aborted(format!("invalid netns: {e}")))
+}
+
+fn attachment(program: &Program) -> Attachment {
+    Attachment {
+        id: program
+            .data()
+            .ok()
+            .and_then(|d| d.id())
+            .map_or(String::new(), |id| id.to_string()),
+        iface: program.if_name().unwrap_or_default(),
+        bpf_id: program.kernel_info().map_or(0, |i| i.id),
+        position: program.position().unwrap_or(0) as i32,
+        pending: program.pending(),
+    }
+}
+
+fn netns_path(netns: &Option<Netns>) -> String {
+    netns
+        .as_ref()
+        .map_or(String::new(), |n| n.path.to_string_lossy().to_string())
 }
 
 #[cfg(test)]
 mod test {
-    use bpfd_api::v1::{
-        load_request::AttachInfo, load_request_common::Location, LoadRequest, LoadRequestCommon,
-        XdpAttachInfo,
+    use bpfd_api::{
+        v1::{
+            load_request::AttachInfo, load_request_common::Location,
+            perf_event_attach_info::SamplePolicy, CgroupAttachInfo, LoadRequest, LoadRequestCommon,
+            PerfEventAttachInfo, XdpAttachInfo,
+        },
+        ProgramType,
     };
+    use std::path::PathBuf;
+
     use tokio::sync::mpsc::Receiver;
 
     use super::*;
+    use crate::maps::MapInfo;
 
     #[tokio::test]
     async fn test_load_with_valid_id() {
@@ -427,6 +1348,10 @@ mod test {
                 priority: 50,
                 position: 0,
                 proceed_on: vec![2, 31],
+                frags: false,
+                wait_for_iface: false,
+                netns: String::new(),
+                ifaces: vec![],
             })),
         };
 
@@ -457,6 +1382,63 @@ mod test {
                 priority: 50,
                 position: 0,
                 proceed_on: vec![2, 31],
+                frags: false,
+                wait_for_iface: false,
+                netns: String::new(),
+                ifaces: vec![],
+            })),
+        };
+
+        tokio::spawn(async move {
+            mock_serve(rx).await;
+        });
+
+        let res = loader.load(Request::new(request)).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_cgroup_with_mismatched_attach_type() {
+        let (tx, rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        let request = LoadRequest {
+            common: Some(LoadRequestCommon {
+                location: Some(Location::File("/tmp/cgroup.o".to_string())),
+                program_type: ProgramType::CgroupSkb as u32,
+                ..Default::default()
+            }),
+            attach_info: Some(AttachInfo::CgroupAttachInfo(CgroupAttachInfo {
+                cgroup_path: "/sys/fs/cgroup".to_string(),
+                attach_type: "connect4".to_string(),
+            })),
+        };
+
+        tokio::spawn(async move {
+            mock_serve(rx).await;
+        });
+
+        let res = loader.load(Request::new(request)).await;
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_load_perf_event_with_invalid_pid() {
+        let (tx, rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        let request = LoadRequest {
+            common: Some(LoadRequestCommon {
+                location: Some(Location::File("/tmp/perf_event.o".to_string())),
+                program_type: ProgramType::PerfEvent as u32,
+                ..Default::default()
+            }),
+            attach_info: Some(AttachInfo::PerfEventAttachInfo(PerfEventAttachInfo {
+                event_type: "software".to_string(),
+                config: 0,
+                cpus: vec![],
+                pid: Some(-2),
+                sample_policy: Some(SamplePolicy::SamplePeriod(1000)),
             })),
         };
 
@@ -488,6 +1470,222 @@ mod test {
         assert!(res.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_read_iterator_errors() {
+        let (tx, rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        tokio::spawn(async move { mock_serve(rx).await });
+
+        let res = loader
+            .read_iterator(Request::new(ReadIteratorRequest {
+                id: "notauuid".to_string(),
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let res = loader
+            .read_iterator(Request::new(ReadIteratorRequest {
+                id: Uuid::new_v4().to_string(),
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::Aborted);
+    }
+
+    #[tokio::test]
+    async fn test_get_maps() {
+        let (tx, rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        tokio::spawn(async move { mock_serve(rx).await });
+
+        let res = loader
+            .get_maps(Request::new(GetMapsRequest {
+                id: "notauuid".to_string(),
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let id = Uuid::new_v4().to_string();
+        let maps = loader
+            .get_maps(Request::new(GetMapsRequest { id: id.clone() }))
+            .await
+            .unwrap()
+            .into_inner()
+            .maps;
+        assert_eq!(maps.len(), 1);
+        assert_eq!(maps[0].name, "xdp_stats_map");
+        assert_eq!(maps[0].map_type, "percpu_array");
+        assert_eq!(maps[0].map_id, 7);
+        assert_eq!(
+            maps[0].pin_path,
+            format!("/run/bpfd/fs/maps/{id}/xdp_stats_map")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_map_rpc_errors() {
+        let (tx, rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        tokio::spawn(async move { mock_serve(rx).await });
+
+        let res = loader
+            .map_lookup(Request::new(MapLookupRequest {
+                id: "notauuid".to_string(),
+                map_name: "counters".to_string(),
+                key: vec![0; 4],
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let id = Uuid::new_v4();
+        let res = loader
+            .map_delete(Request::new(MapDeleteRequest {
+                id: id.to_string(),
+                map_name: "counters".to_string(),
+                keys: vec![vec![0; 4]],
+            }))
+            .await;
+        let status = res.err().unwrap();
+        assert_eq!(status.code(), tonic::Code::Aborted);
+        assert_eq!(
+            status.message(),
+            format!("program {id} has no map counters")
+        );
+
+        let res = loader
+            .map_dump(Request::new(MapDumpRequest {
+                id: id.to_string(),
+                map_name: "counters".to_string(),
+                batch_size: 0,
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::Aborted);
+
+        let res = loader
+            .subscribe_ring_buf(Request::new(SubscribeRingBufRequest {
+                id: "notauuid".to_string(),
+                map_name: "events".to_string(),
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let res = loader
+            .subscribe_perf_event_array(Request::new(SubscribePerfEventArrayRequest {
+                id: id.to_string(),
+                map_name: "events".to_string(),
+            }))
+            .await;
+        let status = res.err().unwrap();
+        assert_eq!(status.code(), tonic::Code::Aborted);
+        assert_eq!(status.message(), format!("program {id} has no map events"));
+
+        let res = loader
+            .watch_map(Request::new(WatchMapRequest {
+                id: "notauuid".to_string(),
+                map_name: "counters".to_string(),
+                interval_ms: 0,
+                per_cpu: false,
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::InvalidArgument);
+
+        let res = loader
+            .watch_map(Request::new(WatchMapRequest {
+                id: id.to_string(),
+                map_name: "counters".to_string(),
+                interval_ms: 10,
+                per_cpu: true,
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::Aborted);
+    }
+
+    #[tokio::test]
+    async fn test_replace_keeps_id() {
+        let (tx, rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        tokio::spawn(async move { mock_serve(rx).await });
+
+        let id = Uuid::new_v4().to_string();
+        let res = loader
+            .replace(Request::new(ReplaceRequest {
+                id: id.clone(),
+                keep_id: true,
+                location: None,
+            }))
+            .await;
+        assert_eq!(res.err().unwrap().code(), tonic::Code::Aborted);
+
+        let res = loader
+            .replace(Request::new(ReplaceRequest {
+                id: id.clone(),
+                keep_id: true,
+                location: Some(replace_request::Location::File(
+                    "/path/to/bytecode".to_string(),
+                )),
+            }))
+            .await;
+        assert_eq!(res.unwrap().into_inner().id, id);
+    }
+
+    #[tokio::test]
+    async fn test_list_folds_group_members() {
+        let (tx, mut rx) = mpsc::channel(32);
+        let loader = BpfdLoader::new(tx.clone());
+
+        let program = |id: Uuid, iface: &str, group: Option<Uuid>| {
+            let mut program = Program::Xdp(XdpProgram::new(
+                ProgramData::new(
+                    crate::command::Location::File("/path/to/bytecode".to_string()),
+                    "pass".to_string(),
+                    Some(id),
+                    HashMap::new(),
+                    group,
+                ),
+                50,
+                iface.to_string(),
+                XdpProceedOn::default(),
+                true,
+                None,
+            ));
+            program.set_group(group);
+            program
+        };
+        let id = Uuid::new_v4();
+        let member = Uuid::new_v4();
+        let programs = vec![program(member, "eth1", Some(id)), program(id, "eth0", None)];
+        tokio::spawn(async move {
+            if let Some(Command::List { responder }) = rx.recv().await {
+                responder.send(Ok(programs)).unwrap();
+            }
+        });
+
+        let results = loader
+            .list(Request::new(ListRequest::default()))
+            .await
+            .unwrap()
+            .into_inner()
+            .results;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, Some(id.to_string()));
+        let attachments: Vec<(&str, &str)> = results[0]
+            .attachments
+            .iter()
+            .map(|a| (a.iface.as_str(), a.id.as_str()))
+            .collect();
+        assert_eq!(
+            attachments,
+            [
+                ("eth0", id.to_string().as_str()),
+                ("eth1", member.to_string().as_str())
+            ]
+        );
+        assert!(results[0].attachments.iter().all(|a| a.pending));
+    }
+
     async fn mock_serve(mut rx: Receiver<Command>) {
         while let Some(cmd) = rx.recv().await {
             match cmd {
@@ -495,6 +1693,33 @@ mod test {
                 Command::Unload(args) => args.responder.send(Ok(())).unwrap(),
                 Command::List { responder, .. } => responder.send(Ok(vec![])).unwrap(),
                 Command::PullBytecode(args) => args.responder.send(Ok(())).unwrap(),
+                Command::ReadIterator(args) => args
+                    .responder
+                    .send(Err(BpfdError::ProgramNotFound(args.id)))
+                    .unwrap(),
+                Command::Update(args) => args.responder.send(Ok(())).unwrap(),
+                Command::Replace(args) => args.responder.send(Ok(args.id)).unwrap(),
+                Command::SetEnabled(args) => args.responder.send(Ok(())).unwrap(),
+                Command::OpenMap(args) => args
+                    .responder
+                    .send(Err(BpfdError::MapNotFound(args.id, args.name)))
+                    .unwrap(),
+                Command::GetMaps(args) => args
+                    .responder
+                    .send(Ok(vec![MapInfo {
+                        name: "xdp_stats_map".to_string(),
+                        id: 7,
+                        map_type: 6,
+                        key_size: 4,
+                        value_size: 16,
+                        max_entries: 5,
+                        flags: 0,
+                        pin_path: PathBuf::from(format!(
+                            "/run/bpfd/fs/maps/{}/xdp_stats_map",
+                            args.id
+                        )),
+                    }]))
+                    .unwrap(),
             }
         }
     }
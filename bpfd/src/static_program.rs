@@ -17,6 +17,7 @@ use crate::{
         Location::{File, Image},
         Program, ProgramData, TcProgram, TracepointProgram, XdpProgram,
     },
+    netns::Netns,
     oci_utils::BytecodeImage,
     utils::read_to_string,
 };
@@ -26,6 +27,10 @@ pub(crate) struct XdpAttachInfo {
     pub(crate) priority: i32,
     pub(crate) iface: String,
     pub(crate) proceed_on: XdpProceedOn,
+    #[serde(default)]
+    pub(crate) wait_for_iface: bool,
+    #[serde(default)]
+    pub(crate) netns: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -34,6 +39,10 @@ pub(crate) struct TcAttachInfo {
     pub(crate) iface: String,
     pub(crate) proceed_on: TcProceedOn,
     pub(crate) direction: Direction,
+    #[serde(default)]
+    pub(crate) wait_for_iface: bool,
+    #[serde(default)]
+    pub(crate) netns: String,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -149,7 +158,14 @@ pub(crate) async fn get_static_programs<P: AsRef<Path>>(
             let prog = match program.program_type {
                 ProgramType::Xdp => {
                     if let Some(m) = program.xdp_attach {
-                        Program::Xdp(XdpProgram::new(data, m.priority, m.iface, m.proceed_on))
+                        Program::Xdp(XdpProgram::new(
+                            data,
+                            m.priority,
+                            m.iface,
+                            m.proceed_on,
+                            m.wait_for_iface,
+                            Netns::open(&m.netns)?,
+                        ))
                     } else {
                         bail!("invalid info for xdp program")
                     }
@@ -162,6 +178,8 @@ pub(crate) async fn get_static_programs<P: AsRef<Path>>(
                             m.iface,
                             m.proceed_on,
                             m.direction,
+                            m.wait_for_iface,
+                            Netns::open(&m.netns)?,
                         ))
                     } else {
                         bail!("invalid attach type for tc program")
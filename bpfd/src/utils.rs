@@ -1,16 +1,23 @@
 // SPDX-License-Identifier: (MIT OR Apache-2.0)
 // Copyright Authors of bpfd
 
-use std::{os::unix::fs::PermissionsExt, path::Path, str};
+use std::{io, mem, os::unix::fs::PermissionsExt, path::Path, str};
 
 use bpfd_api::util::USRGRP_BPFD;
 use log::{info, warn};
-use nix::net::if_::if_nametoindex;
+use nix::{
+    libc,
+    net::if_::{if_nameindex, if_nametoindex},
+};
+use object::{Object, ObjectSection, ObjectSymbol, SymbolKind};
 use tokio::{fs, io::AsyncReadExt};
 use users::get_group_by_name;
 
 use crate::errors::BpfdError;
 
+// List of the LSMs which are active in the running kernel
+const SYSFS_LSM_PATH: &str = "/sys/kernel/security/lsm";
+
 // Like tokio::fs::read, but with O_NOCTTY set
 pub(crate) async fn read<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, BpfdError> {
     let mut data = vec![];
@@ -54,6 +61,125 @@ pub(crate) fn get_ifindex(iface: &str) -> Result<u32, BpfdError> {
     }
 }
 
+// Returns the interfaces that patterns name, in order and without duplicates.
+// Glob patterns, with * and ?, are matched against the interfaces that exist
+// in the calling thread's network namespace, plain names are kept as they are.
+pub(crate) fn expand_ifaces(patterns: &[String]) -> Result<Vec<String>, BpfdError> {
+    let mut existing = None;
+    let mut ifaces: Vec<String> = vec![];
+    for pattern in patterns {
+        let matches = if pattern.contains(['*', '?']) {
+            if existing.is_none() {
+                let names = if_nameindex()
+                    .map_err(|e| BpfdError::Error(format!("can't list interfaces: {e}")))?
+                    .iter()
+                    .map(|i| i.name().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>();
+                existing = Some(names);
+            }
+            existing
+                .iter()
+                .flatten()
+                .filter(|name| iface_matches(pattern, name))
+                .cloned()
+                .collect()
+        } else {
+            vec![pattern.clone()]
+        };
+        for iface in matches {
+            if !ifaces.contains(&iface) {
+                ifaces.push(iface);
+            }
+        }
+    }
+    if ifaces.is_empty() {
+        return Err(BpfdError::Error(format!(
+            "no interface matches {}",
+            patterns.join(", ")
+        )));
+    }
+    Ok(ifaces)
+}
+
+fn iface_matches(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    // Position to go back to after a mismatch: the pattern just past the last
+    // *, and the name one byte further than that * matched last time.
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                backtrack = Some((p + 1, n + 1));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bn)) => {
+                    backtrack = Some((bp, bn + 1));
+                    p = bp;
+                    n = bn;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+// section_name returns the name of the ELF section the program name is in,
+// which is what decides the program type, without loading the bytecode.
+pub(crate) fn section_name(bytecode: &[u8], name: &str) -> Result<String, BpfdError> {
+    let obj = object::File::parse(bytecode)
+        .map_err(|e| BpfdError::Error(format!("unable to parse bytecode: {e}")))?;
+    let section_index = obj
+        .symbols()
+        .find(|s| s.kind() == SymbolKind::Text && s.name() == Ok(name))
+        .and_then(|s| s.section_index())
+        .ok_or_else(|| BpfdError::SectionNameNotValid(name.to_string()))?;
+    let section = obj
+        .section_by_index(section_index)
+        .map_err(|e| BpfdError::Error(format!("unable to parse bytecode: {e}")))?;
+    let section_name = section
+        .name()
+        .map_err(|e| BpfdError::Error(format!("unable to parse bytecode: {e}")))?;
+
+    Ok(section_name.to_string())
+}
+
+// sys_bpf runs the bpf command cmd for the things aya has no API for. attr is
+// the part of union bpf_attr the command uses.
+pub(crate) fn sys_bpf<T>(cmd: libc::c_int, attr: &mut T) -> io::Result<libc::c_long> {
+    // SAFETY: attr is one of the repr(C) parts of union bpf_attr, and the
+    // kernel does not access it past its size.
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_bpf,
+            cmd,
+            attr as *mut T,
+            mem::size_of::<T>() as libc::c_uint,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(ret)
+}
+
+// Returns true if the BPF LSM is active, which is required before any
+// BPF_PROG_TYPE_LSM program can be attached.
+pub(crate) async fn bpf_lsm_enabled() -> Result<bool, BpfdError> {
+    let lsms = read_to_string(SYSFS_LSM_PATH).await?;
+    Ok(lsm_list_contains_bpf(&lsms))
+}
+
+fn lsm_list_contains_bpf(lsms: &str) -> bool {
+    lsms.trim().split(',').any(|lsm| lsm == "bpf")
+}
+
 pub(crate) async fn set_file_permissions(path: &str, mode: u32) {
     // Determine if User Group exists, if not, do nothing
     if get_group_by_name(USRGRP_BPFD).is_some() {
@@ -76,3 +202,38 @@ pub(crate) async fn set_dir_permissions(directory: &str, mode: u32) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lsm_list_contains_bpf() {
+        assert!(lsm_list_contains_bpf("lockdown,capability,yama,bpf"));
+        assert!(lsm_list_contains_bpf("bpf\n"));
+        assert!(!lsm_list_contains_bpf("lockdown,capability,selinux"));
+        assert!(!lsm_list_contains_bpf("lockdown,bpf_foo"));
+        assert!(!lsm_list_contains_bpf(""));
+    }
+
+    #[test]
+    fn test_iface_matches() {
+        assert!(iface_matches("eth*", "eth0"));
+        assert!(iface_matches("eth*", "eth"));
+        assert!(iface_matches("*", "lo"));
+        assert!(iface_matches("veth?", "veth1"));
+        assert!(iface_matches("en*s*1", "enp0s31"));
+        assert!(iface_matches("eth0", "eth0"));
+        assert!(!iface_matches("eth*", "veth0"));
+        assert!(!iface_matches("veth?", "veth10"));
+        assert!(!iface_matches("en*s*1", "enp0s3"));
+    }
+
+    #[test]
+    fn test_expand_ifaces() {
+        let patterns = ["eth1", "eth0", "eth1"].map(String::from);
+        assert_eq!(expand_ifaces(&patterns).unwrap(), ["eth1", "eth0"]);
+        assert!(expand_ifaces(&["no-such-iface*".to_string()]).is_err());
+        assert_eq!(expand_ifaces(&["lo*".to_string()]).unwrap(), ["lo"]);
+    }
+}
@@ -72,6 +72,39 @@ fn test_load_unload_xdp() {
     assert!(!bpffs_has_entries(RTDIR_FS_XDP));
 }
 
+#[integration_test]
+fn test_load_unload_xdp_chained_dispatcher() {
+    let _namespace_guard = create_namespace().unwrap();
+    let _ping_guard = start_ping().unwrap();
+    let _bpfd_guard = start_bpfd().unwrap();
+
+    assert!(iface_exists(DEFAULT_BPFD_IFACE));
+
+    debug!("Installing more xdp_pass programs than one dispatcher holds");
+
+    let mut uuids = vec![];
+    for priority in 0..12 {
+        let uuid = add_xdp_pass(
+            DEFAULT_BPFD_IFACE,
+            10 + priority,
+            None,
+            None,
+            &LoadType::File,
+        );
+        uuids.push(uuid.unwrap());
+    }
+
+    // The second dispatcher is only reachable through the chain map of the
+    // first one, which must still be filled once the load has returned.
+    let chain_maps = dispatcher_chain_maps(RTDIR_FS_XDP);
+    assert_eq!(chain_maps.len(), 1);
+    assert_eq!(pinned_map_entries(&chain_maps[0]), 1);
+
+    verify_and_delete_programs(uuids);
+
+    assert!(!bpffs_has_entries(RTDIR_FS_XDP));
+}
+
 #[integration_test]
 fn test_load_unload_tc() {
     let _namespace_guard = create_namespace().unwrap();
@@ -744,3 +744,34 @@ pub fn verify_and_delete_programs(uuids: Vec<String>) {
 pub fn bpffs_has_entries(path: &str) -> bool {
     PathBuf::from(path).read_dir().unwrap().next().is_some()
 }
+
+/// Returns the pinned chain maps of all dispatchers under path
+pub fn dispatcher_chain_maps(path: &str) -> Vec<PathBuf> {
+    let mut maps = vec![];
+    for dir in PathBuf::from(path).read_dir().unwrap() {
+        let dir = dir.unwrap().path();
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in dir.read_dir().unwrap() {
+            let entry = entry.unwrap().path();
+            let name = entry.file_name().unwrap().to_string_lossy().to_string();
+            if name.starts_with("chain_") && name.ends_with("_map") {
+                maps.push(entry);
+            }
+        }
+    }
+    maps
+}
+
+/// Returns the number of entries in the map pinned at path
+pub fn pinned_map_entries(path: &Path) -> usize {
+    let output = Command::new("bpftool")
+        .args(["map", "dump", "pinned"])
+        .arg(path)
+        .arg("--json")
+        .output()
+        .expect("bpftool map dump pinned");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout.matches("\"key\"").count()
+}